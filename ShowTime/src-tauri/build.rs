@@ -1,3 +1,19 @@
 fn main() {
-    tauri_build::build()
+    // Every app command has to be listed here; tauri-build generates an
+    // `allow-<command>` / `deny-<command>` permission for each and commands
+    // without a granted permission are rejected at runtime.
+    tauri_build::try_build(tauri_build::Attributes::new().app_manifest(
+        tauri_build::AppManifest::new().commands(&[
+            "greet",
+            "trigger_blackout",
+            "restore_window",
+            "get_protection_status",
+            "verify_audit_log",
+            "export_audit_log",
+            "get_detector_logs",
+            "get_metrics",
+            "control_detector",
+        ]),
+    ))
+    .expect("failed to run tauri-build");
 }
@@ -0,0 +1,59 @@
+"""Freeze detector.py into the sidecar listed under bundle.externalBin.
+
+Tauri expects external binaries suffixed with the Rust target triple, e.g.
+binaries/showtime-detector-x86_64-unknown-linux-gnu, and installs them next
+to the app's executable without the suffix. Only the bundle configuration
+lists it, so plain `cargo build` and `tauri dev` work without it. Run this
+from anywhere, with PyInstaller installed, then bundle with the overlay:
+
+    python detector/build_sidecar.py [--target <triple>]
+    cargo tauri build --config tauri.bundle.conf.json
+"""
+
+import argparse
+import shutil
+import subprocess
+import sys
+import tempfile
+from pathlib import Path
+
+NAME = "showtime-detector"
+SRC_TAURI = Path(__file__).resolve().parent.parent
+
+
+def host_triple():
+    output = subprocess.run(["rustc", "-vV"], check=True, capture_output=True, text=True).stdout
+    for line in output.splitlines():
+        if line.startswith("host:"):
+            return line.split(":", 1)[1].strip()
+    sys.exit("Error: cannot determine the target triple from rustc -vV")
+
+
+def main():
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--target", help="Rust target triple, defaults to the host's")
+    args = parser.parse_args()
+    target = args.target or host_triple()
+    suffix = ".exe" if "windows" in target else ""
+
+    with tempfile.TemporaryDirectory() as work:
+        subprocess.run(
+            [
+                sys.executable, "-m", "PyInstaller",
+                "--onefile",
+                "--name", NAME,
+                "--distpath", str(Path(work) / "dist"),
+                "--workpath", str(Path(work) / "build"),
+                "--specpath", work,
+                str(SRC_TAURI / "detector" / "detector.py"),
+            ],
+            check=True,
+        )
+        out = SRC_TAURI / "binaries" / f"{NAME}-{target}{suffix}"
+        out.parent.mkdir(exist_ok=True)
+        shutil.copy2(Path(work) / "dist" / f"{NAME}{suffix}", out)
+    print(f"Built {out}", file=sys.stderr)
+
+
+if __name__ == "__main__":
+    main()
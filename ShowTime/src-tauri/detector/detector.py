@@ -1,7 +1,12 @@
 import argparse
 import json
+import os
 import signal
+import socket
+import struct
 import sys
+import threading
+import time
 from datetime import datetime
 import cv2
 from ultralytics import YOLO
@@ -11,7 +16,11 @@ MODEL_NAME = "yolov8n.pt"
 CONFIDENCE_THRESHOLD = 0.5
 FRAME_SKIP = 2
 CAMERA_INDEX = 0
+HEARTBEAT_INTERVAL = 1.0
 TARGET_LABELS = ("cell phone", "phone", "mobile", "camera", "camcorder")
+PROTOCOL_VERSION = 2  # must match src/protocol.rs
+CONTROL_VERSION = 1
+MAX_MESSAGE_LEN = 1 << 20
 
 # Graceful exit flag
 running = True
@@ -27,12 +36,181 @@ signal.signal(signal.SIGINT, signal_handler)
 signal.signal(signal.SIGTERM, signal_handler)
 
 
+def emit(event_type, **fields):
+    """Write one protocol line to stdout. See src/protocol.rs."""
+    event = {"v": PROTOCOL_VERSION, "type": event_type, **fields}
+    print(json.dumps(event), flush=True)
+
+
+class Controls:
+    """Settings the app can change while detection runs. See src/detector/control.rs."""
+
+    def __init__(self, conf_threshold, frame_skip):
+        self.lock = threading.Lock()
+        self.paused = False
+        self.conf_threshold = conf_threshold
+        self.frame_skip = frame_skip
+        self.frame = 0
+        self.fps = 0.0
+
+    def status(self):
+        with self.lock:
+            return {
+                "paused": self.paused,
+                "conf_threshold": self.conf_threshold,
+                "frame_skip": self.frame_skip,
+                "frame": self.frame,
+                "fps": self.fps,
+            }
+
+    def apply(self, request):
+        """Carry out one request. Returns whether a setting changed."""
+        global running
+        command = request.get("command")
+        with self.lock:
+            before = (self.paused, self.conf_threshold, self.frame_skip)
+            if command == "status":
+                pass
+            elif command == "pause":
+                self.paused = True
+            elif command == "resume":
+                self.paused = False
+            elif command == "set_conf_threshold":
+                value = float(request["value"])
+                if not 0.0 <= value <= 1.0:
+                    raise ValueError("conf_threshold must be between 0 and 1")
+                self.conf_threshold = value
+            elif command == "set_frame_skip":
+                value = int(request["value"])
+                if value < 1:
+                    raise ValueError("frame_skip must be at least 1")
+                self.frame_skip = value
+            elif command == "shutdown":
+                running = False
+            else:
+                raise ValueError(f"unknown command {command!r}")
+            return before != (self.paused, self.conf_threshold, self.frame_skip)
+
+
+def read_message(reader):
+    """One length-prefixed JSON message, or None once the app hung up."""
+    header = reader.read(4)
+    if len(header) < 4:
+        return None
+    (length,) = struct.unpack(">I", header)
+    if length > MAX_MESSAGE_LEN:
+        raise ValueError("control message too long")
+    body = reader.read(length)
+    if len(body) < length:
+        return None
+    return json.loads(body)
+
+
+def write_message(writer, message_type, **fields):
+    body = json.dumps({"v": CONTROL_VERSION, "type": message_type, **fields}).encode()
+    writer.write(struct.pack(">I", len(body)) + body)
+    writer.flush()
+
+
+def accept_unix(address):
+    """Listen on a Unix domain socket and yield a reader and writer per connection."""
+    if os.path.exists(address):
+        os.unlink(address)
+    server = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+    server.bind(address)
+    server.listen(1)
+    try:
+        while running:
+            connection, _ = server.accept()
+            with connection:
+                yield connection.makefile("rb"), connection.makefile("wb")
+    finally:
+        server.close()
+        os.unlink(address)
+
+
+def accept_pipes(address):
+    """Create the request and event pipes and yield them per connection.
+
+    Two one-way pipes, as a synchronous pipe handle cannot be read on one
+    thread while another writes to it.
+    """
+    import _winapi
+    import msvcrt
+
+    # From winbase.h and winerror.h; byte mode and blocking are all zero.
+    PIPE_ACCESS_INBOUND = 0x1
+    PIPE_ACCESS_OUTBOUND = 0x2
+    FILE_FLAG_FIRST_PIPE_INSTANCE = 0x80000
+    ERROR_PIPE_CONNECTED = 535
+
+    def create(suffix, access):
+        return _winapi.CreateNamedPipe(
+            address + suffix, access | FILE_FLAG_FIRST_PIPE_INSTANCE, 0,
+            1, MAX_MESSAGE_LEN, MAX_MESSAGE_LEN, 0, _winapi.NULL,
+        )
+
+    def connect(handle):
+        try:
+            _winapi.ConnectNamedPipe(handle, False)
+        except OSError as err:
+            # The app opened the pipe before we started waiting for it.
+            if err.winerror != ERROR_PIPE_CONNECTED:
+                raise
+
+    while running:
+        requests = create(".in", PIPE_ACCESS_INBOUND)
+        events = create(".out", PIPE_ACCESS_OUTBOUND)
+        connect(requests)
+        connect(events)
+        reader = os.fdopen(msvcrt.open_osfhandle(requests, os.O_RDONLY), "rb")
+        writer = os.fdopen(msvcrt.open_osfhandle(events, os.O_WRONLY), "wb")
+        with reader, writer:
+            yield reader, writer
+
+
+def serve_control(address, controls):
+    """Answer requests from the app, one connection at a time."""
+    connections = accept_pipes(address) if sys.platform == "win32" else accept_unix(address)
+    for reader, writer in connections:
+        try:
+            write_message(writer, "status", status=controls.status())
+            while True:
+                request = read_message(reader)
+                if request is None:
+                    break
+                if request.get("v") != CONTROL_VERSION:
+                    write_message(writer, "response", id=request.get("id"), ok=False,
+                                  error=f"unsupported control version {request.get('v')}")
+                    continue
+                try:
+                    changed = controls.apply(request)
+                except (KeyError, TypeError, ValueError) as err:
+                    write_message(writer, "response", id=request.get("id"), ok=False, error=str(err))
+                    continue
+                status = controls.status()
+                write_message(writer, "response", id=request.get("id"), ok=True, status=status)
+                if changed:
+                    print(f"Control: {request['command']} -> {status}", file=sys.stderr)
+                    write_message(writer, "status", status=status)
+        except (OSError, ValueError) as err:
+            print(f"Control connection failed: {err}", file=sys.stderr)
+
+
 def main():
     parser = argparse.ArgumentParser()
     parser.add_argument("--device", type=int, default=CAMERA_INDEX, help="Camera index")
     parser.add_argument("--conf-threshold", type=float, default=CONFIDENCE_THRESHOLD, help="Confidence threshold")
     parser.add_argument("--frame-skip", type=int, default=FRAME_SKIP, help="Number of frames to skip between detections")
+    parser.add_argument("--heartbeat-interval", type=float, default=HEARTBEAT_INTERVAL, help="Seconds between heartbeats")
+    parser.add_argument("--labels", default=",".join(TARGET_LABELS), help="Comma-separated labels to report")
+    parser.add_argument("--control", help="Unix socket or named pipe to take control requests on")
     args = parser.parse_args()
+    target_labels = {label.strip().lower() for label in args.labels.split(",") if label.strip()}
+
+    controls = Controls(args.conf_threshold, args.frame_skip)
+    if args.control:
+        threading.Thread(target=serve_control, args=(args.control, controls), daemon=True).start()
 
     model = YOLO(MODEL_NAME)
     cap = cv2.VideoCapture(args.device)
@@ -42,17 +220,41 @@ def main():
         sys.exit(1)
 
     frame_count = 0
+    # Heartbeats come from the capture loop itself, so a hung camera read
+    # or inference silences them and the app notices the stall.
+    emit("heartbeat", frame=0, fps=0.0, timestamp=datetime.now().isoformat())
+    last_beat = time.monotonic()
+    last_beat_frame = 0
 
     while running:
         ret, frame = cap.read()
         if not ret:
             break
+        captured_at = datetime.now().isoformat()
 
         frame_count += 1
-        if frame_count % args.frame_skip != 0:
+        now = time.monotonic()
+        if now - last_beat >= args.heartbeat_interval:
+            fps = round((frame_count - last_beat_frame) / (now - last_beat), 1)
+            emit("heartbeat", frame=frame_count, fps=fps, timestamp=datetime.now().isoformat())
+            last_beat = now
+            last_beat_frame = frame_count
+            with controls.lock:
+                controls.fps = fps
+
+        with controls.lock:
+            controls.frame = frame_count
+            paused = controls.paused
+            conf_threshold = controls.conf_threshold
+            frame_skip = controls.frame_skip
+        # Paused, frames are still read so heartbeats keep coming and the
+        # camera does not fall behind.
+        if paused or frame_count % frame_skip != 0:
             continue
 
         results = model(frame, verbose=False)
+        timestamp = datetime.now().isoformat()
+        detected = False
         for r in results:
             boxes = r.boxes
             for box in boxes:
@@ -60,20 +262,19 @@ def main():
                 label = r.names[label_index].lower()
                 confidence = float(box.conf)
 
-                if label in TARGET_LABELS and confidence >= args.conf_threshold:
-                    # JSON output for logging
-                    event = {
-                        "label": label,
-                        "confidence": confidence,
-                        "timestamp": datetime.now().isoformat()
-                    }
-                    print(json.dumps(event), flush=True)
-
-                    # Plain text output for external readers
-                    if label in ("cell phone", "phone", "mobile"):
-                        print("Phone detected", flush=True)
-                    elif label in ("camera", "camcorder"):
-                        print("Camera detected", flush=True)
+                if label in target_labels and confidence >= conf_threshold:
+                    detected = True
+                    emit(
+                        "detection",
+                        frame=frame_count,
+                        label=label,
+                        confidence=confidence,
+                        timestamp=timestamp,
+                        captured_at=captured_at,
+                    )
+
+        if not detected:
+            emit("clear", frame=frame_count, timestamp=timestamp, captured_at=captured_at)
 
     cap.release()
 
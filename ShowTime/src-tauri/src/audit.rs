@@ -0,0 +1,460 @@
+//! Append-only, hash-chained audit log.
+//!
+//! Every record is one JSON line. Its `hash` is the SHA-256 of the previous
+//! record's hash together with the record's own content, so editing,
+//! reordering or dropping a line that other records follow breaks the chain
+//! from that point on. [`verify`] walks the chain and reports the first
+//! broken record.
+//!
+//! The chain only catches careless changes. Records dropped from the end
+//! leave a valid chain behind, and as the hashes are not keyed, anyone who
+//! can write the file can rewrite it and recompute every hash. Detecting
+//! either takes an anchor kept somewhere else: the record count and last
+//! hash [`verify`] reports, or the support codes users quote, compared with
+//! the log later.
+
+use crate::detector::ControlCommand;
+use crate::policy::Action;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// File name of the audit log inside the app data directory.
+pub const AUDIT_FILE_NAME: &str = "audit.jsonl";
+
+/// `prev` of the first record in a log.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditEvent {
+    Detection {
+        frame: u64,
+        label: String,
+        confidence: f32,
+        action: Action,
+    },
+    Enforced {
+        action: Action,
+    },
+    Restored,
+    RestoreRefused {
+        origin: String,
+        reason: String,
+    },
+    DetectorStalled {
+        silent_ms: u64,
+    },
+    DetectorCrashed {
+        crashes: u32,
+        reason: String,
+    },
+    DetectorGaveUp {
+        crashes: u32,
+        reason: String,
+    },
+    /// A setting of the running detector was changed from the frontend.
+    DetectorControlled {
+        request: ControlCommand,
+    },
+    /// The app started, under the session shown in the watermark.
+    SessionStarted {
+        session: String,
+        user: String,
+    },
+    /// The screen recorders running changed; `names` is empty once they
+    /// have all exited.
+    ScreenRecorders {
+        names: Vec<String>,
+        action: Action,
+    },
+    EnvironmentCheck {
+        virtual_machine: bool,
+        allowed: bool,
+    },
+    AccessDenied {
+        reason: String,
+        exit_code: i32,
+    },
+    /// The log ended in a partial record, left by a crash in the middle of
+    /// an append. Its `bytes` from `line` on were moved to `moved_to`.
+    TornRecordSetAside {
+        line: usize,
+        bytes: u64,
+        moved_to: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub seq: u64,
+    pub time: DateTime<Utc>,
+    pub event: AuditEvent,
+    pub prev: String,
+    pub hash: String,
+}
+
+/// The hashed part of a record, i.e. everything but `hash` itself.
+#[derive(Serialize)]
+struct Unsealed<'a> {
+    seq: u64,
+    time: &'a DateTime<Utc>,
+    event: &'a AuditEvent,
+    prev: &'a str,
+}
+
+impl AuditRecord {
+    fn seal(seq: u64, time: DateTime<Utc>, event: AuditEvent, prev: String) -> Self {
+        let hash = digest(&Unsealed {
+            seq,
+            time: &time,
+            event: &event,
+            prev: &prev,
+        });
+        Self {
+            seq,
+            time,
+            event,
+            prev,
+            hash,
+        }
+    }
+
+    /// Short code that identifies this record, for users to quote to
+    /// support: the sequence number and the start of the hash.
+    pub fn support_code(&self) -> String {
+        format!("ST-{}-{}", self.seq, self.hash[..8].to_uppercase())
+    }
+
+    fn expected_hash(&self) -> String {
+        digest(&Unsealed {
+            seq: self.seq,
+            time: &self.time,
+            event: &self.event,
+            prev: &self.prev,
+        })
+    }
+}
+
+fn digest(unsealed: &Unsealed<'_>) -> String {
+    let content = serde_json::to_vec(unsealed).expect("audit records are always serializable");
+    Sha256::digest(&content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum AuditError {
+    Io(io::Error),
+    /// A line is not a record at all.
+    Malformed { line: usize, error: serde_json::Error },
+    /// A record does not continue the chain.
+    Broken { line: usize, seq: u64, reason: String },
+    /// A date range bound could not be parsed.
+    InvalidBound(String),
+}
+
+impl fmt::Display for AuditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditError::Io(err) => write!(f, "audit log I/O error: {}", err),
+            AuditError::Malformed { line, error } => write!(f, "line {}: malformed record: {}", line, error),
+            AuditError::Broken { line, seq, reason } => {
+                write!(f, "line {}: chain broken at record {}: {}", line, seq, reason)
+            }
+            AuditError::InvalidBound(bound) => {
+                write!(f, "invalid date {:?}, expected YYYY-MM-DD or RFC 3339", bound)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuditError {}
+
+impl From<io::Error> for AuditError {
+    fn from(err: io::Error) -> Self {
+        AuditError::Io(err)
+    }
+}
+
+struct Tail {
+    seq: u64,
+    hash: String,
+}
+
+/// Writer side of the log. Safe to share between threads.
+pub struct AuditLog {
+    path: PathBuf,
+    tail: Mutex<Tail>,
+}
+
+impl AuditLog {
+    /// Opens (or creates) the log at `path`, continuing its chain.
+    ///
+    /// A last line that is not a record was torn by a crash in the middle of
+    /// an append. It is moved to `<path>.torn-<seq>` and the recovery is
+    /// recorded, so one bad shutdown cannot keep the app from starting. A
+    /// malformed line anywhere else is still an error.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, AuditError> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut tail = Tail {
+            seq: 0,
+            hash: GENESIS_HASH.to_string(),
+        };
+        // Line number, parse error and byte offset of a line that is not a
+        // record, as long as nothing but blank lines follows it.
+        let mut torn: Option<(usize, serde_json::Error, u64)> = None;
+        let mut unterminated = false;
+        if path.exists() {
+            let mut reader = BufReader::new(File::open(&path)?);
+            let mut line = Vec::new();
+            let (mut number, mut offset) = (0, 0);
+            loop {
+                line.clear();
+                let read = reader.read_until(b'\n', &mut line)?;
+                if read == 0 {
+                    break;
+                }
+                number += 1;
+                let start = offset;
+                offset += read as u64;
+                unterminated = !line.ends_with(b"\n");
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                if let Some((line, error, _)) = torn.take() {
+                    return Err(AuditError::Malformed { line, error });
+                }
+                match serde_json::from_slice::<AuditRecord>(&line) {
+                    Ok(record) => {
+                        tail = Tail {
+                            seq: record.seq,
+                            hash: record.hash,
+                        }
+                    }
+                    Err(error) => torn = Some((number, error, start)),
+                }
+            }
+        }
+
+        let set_aside = match torn {
+            Some((line, error, start)) => {
+                let mut moved_to = path.clone().into_os_string();
+                moved_to.push(format!(".torn-{}", tail.seq + 1));
+                let moved_to = PathBuf::from(moved_to);
+                let bytes = set_aside(&path, start, &moved_to)?;
+                tracing::warn!(line, bytes, moved_to = %moved_to.display(), %error, "set aside a torn audit record");
+                Some(AuditEvent::TornRecordSetAside {
+                    line,
+                    bytes,
+                    moved_to: moved_to.display().to_string(),
+                })
+            }
+            None => {
+                if unterminated {
+                    // Torn between a record and its newline.
+                    OpenOptions::new().append(true).open(&path)?.write_all(b"\n")?;
+                }
+                None
+            }
+        };
+
+        let log = Self {
+            path,
+            tail: Mutex::new(tail),
+        };
+        if let Some(event) = set_aside {
+            log.append(event)?;
+        }
+        Ok(log)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `event` and returns the sealed record.
+    pub fn append(&self, event: AuditEvent) -> Result<AuditRecord, AuditError> {
+        let mut tail = self.tail.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let record = AuditRecord::seal(tail.seq + 1, Utc::now(), event, tail.hash.clone());
+
+        let mut line = serde_json::to_vec(&record).expect("audit records are always serializable");
+        line.push(b'\n');
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(&line)?;
+        file.sync_data()?;
+
+        tail.seq = record.seq;
+        tail.hash = record.hash.clone();
+        Ok(record)
+    }
+
+    /// Appends `event`, reporting but otherwise ignoring failures. Auditing
+    /// must never take protection down with it.
+    pub fn record(&self, event: AuditEvent) {
+        if let Err(err) = self.append(event) {
+            tracing::error!(error = %err, "failed to write audit record");
+        }
+    }
+}
+
+/// Moves everything in `path` from byte `start` on to `moved_to` and returns
+/// how many bytes that was.
+fn set_aside(path: &Path, start: u64, moved_to: &Path) -> io::Result<u64> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut torn = Vec::new();
+    file.read_to_end(&mut torn)?;
+    std::fs::write(moved_to, &torn)?;
+    file.set_len(start)?;
+    file.sync_data()?;
+    Ok(torn.len() as u64)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VerifyReport {
+    pub records: u64,
+    pub last_hash: String,
+}
+
+/// Reads every record of the log at `path`, checking the chain as it goes.
+pub fn read_verified(path: &Path) -> Result<Vec<AuditRecord>, AuditError> {
+    let mut records = Vec::new();
+    let mut prev_hash = GENESIS_HASH.to_string();
+    let mut prev_seq = 0;
+
+    for (index, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let number = index + 1;
+        let record: AuditRecord =
+            serde_json::from_str(&line).map_err(|error| AuditError::Malformed { line: number, error })?;
+
+        let broken = |reason: String| AuditError::Broken {
+            line: number,
+            seq: record.seq,
+            reason,
+        };
+        if record.seq != prev_seq + 1 {
+            return Err(broken(format!("expected sequence number {}", prev_seq + 1)));
+        }
+        if record.prev != prev_hash {
+            return Err(broken("previous hash does not match".to_string()));
+        }
+        if record.hash != record.expected_hash() {
+            return Err(broken("record content does not match its hash".to_string()));
+        }
+
+        prev_seq = record.seq;
+        prev_hash = record.hash.clone();
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Checks the whole chain of the log at `path`.
+pub fn verify(path: &Path) -> Result<VerifyReport, AuditError> {
+    let records = read_verified(path)?;
+    Ok(VerifyReport {
+        records: records.len() as u64,
+        last_hash: records
+            .last()
+            .map_or_else(|| GENESIS_HASH.to_string(), |record| record.hash.clone()),
+    })
+}
+
+/// Returns the verified records whose time falls within `from..=to`.
+pub fn export(
+    path: &Path,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Vec<AuditRecord>, AuditError> {
+    Ok(read_verified(path)?
+        .into_iter()
+        .filter(|record| from.is_none_or(|from| record.time >= from))
+        .filter(|record| to.is_none_or(|to| record.time <= to))
+        .collect())
+}
+
+/// Parses a range bound given as RFC 3339 or as a plain date. A plain date
+/// covers the whole day, so it means its first instant for `from` and its
+/// last for `to`.
+pub fn parse_bound(value: &str, end_of_day: bool) -> Result<DateTime<Utc>, AuditError> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| AuditError::InvalidBound(value.to_string()))?;
+    let time = if end_of_day {
+        NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999)
+    } else {
+        NaiveTime::from_hms_opt(0, 0, 0)
+    }
+    .expect("valid time of day");
+    Ok(date.and_time(time).and_utc())
+}
+
+/// Entry point of the `showtime audit` subcommand. Returns the exit code.
+///
+/// ```text
+/// showtime audit verify <FILE>
+/// showtime audit export <FILE> [--from DATE] [--to DATE]
+/// ```
+pub fn cli(args: &[String]) -> i32 {
+    match run_cli(args) {
+        Ok(()) => 0,
+        Err(message) => {
+            eprintln!("{}", message);
+            1
+        }
+    }
+}
+
+fn run_cli(args: &[String]) -> Result<(), String> {
+    const USAGE: &str =
+        "usage: showtime audit verify <FILE>\n       showtime audit export <FILE> [--from DATE] [--to DATE]";
+
+    match args {
+        [command, file] if command == "verify" => {
+            let report = verify(Path::new(file)).map_err(|err| err.to_string())?;
+            println!("OK: {} records, last hash {}", report.records, report.last_hash);
+            Ok(())
+        }
+        [command, file, options @ ..] if command == "export" => {
+            let mut from = None;
+            let mut to = None;
+            let mut options = options.iter();
+            while let Some(option) = options.next() {
+                let value = options.next().ok_or(USAGE)?;
+                match option.as_str() {
+                    "--from" => from = Some(parse_bound(value, false).map_err(|err| err.to_string())?),
+                    "--to" => to = Some(parse_bound(value, true).map_err(|err| err.to_string())?),
+                    _ => return Err(USAGE.to_string()),
+                }
+            }
+
+            let records = export(Path::new(file), from, to).map_err(|err| err.to_string())?;
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            for record in records {
+                let line = serde_json::to_string(&record).map_err(|err| err.to_string())?;
+                writeln!(out, "{}", line).map_err(|err| err.to_string())?;
+            }
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
@@ -0,0 +1,138 @@
+//! Where the in-process detector gets its frames.
+//!
+//! A [`FrameSource`] hands out still images one after the other: a V4L2
+//! camera behind the `webcam` feature on Linux, a directory of images or a
+//! Motion JPEG video file. [`Capture`] numbers the frames, drops the skipped
+//! ones and scales the rest to the configured resolution the same way for
+//! all of them, so a recording goes through exactly what a camera would and
+//! detection can be exercised deterministically on machines without one.
+//!
+//! Only the in-process `onnx` backend reads frames through here. The Python
+//! detector, and the sidecar frozen from it, still open the camera with
+//! OpenCV and apply frame skip and device selection themselves; nothing
+//! hands them frames from Rust.
+
+use crate::config::{DetectorConfig, Resolution};
+use chrono::{Local, NaiveDateTime};
+use image::imageops::{self, FilterType};
+use image::RgbImage;
+use std::io;
+
+#[cfg(all(feature = "webcam", target_os = "linux"))]
+mod camera;
+mod images;
+mod video;
+
+#[cfg(all(feature = "webcam", target_os = "linux"))]
+pub use camera::Camera;
+pub use images::ImageDir;
+pub use video::{read_jpeg, VideoFile};
+
+/// A sequence of frames.
+pub trait FrameSource: Send {
+    /// Starts over from the first frame, or from now for a camera. Called
+    /// before every run.
+    fn open(&mut self) -> io::Result<()>;
+
+    /// The next frame, `None` once the source is exhausted.
+    fn read(&mut self) -> io::Result<Option<RgbImage>>;
+
+    /// Moves past the next frame without decoding it. Returns whether there
+    /// was one.
+    fn grab(&mut self) -> io::Result<bool> {
+        Ok(self.read()?.is_some())
+    }
+}
+
+/// A frame to analyse.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Position in the source, counting skipped frames, from 1.
+    pub number: u64,
+    pub image: RgbImage,
+    pub captured_at: NaiveDateTime,
+}
+
+/// Reads a [`FrameSource`] with frame skip and resolution applied.
+pub struct Capture {
+    source: Box<dyn FrameSource>,
+    frame_skip: u64,
+    resolution: Option<Resolution>,
+    frame: u64,
+}
+
+impl Capture {
+    /// Every frame of `source`, at its own size.
+    pub fn new(source: impl FrameSource + 'static) -> Self {
+        Self {
+            source: Box::new(source),
+            frame_skip: 1,
+            resolution: None,
+            frame: 0,
+        }
+    }
+
+    /// The source `config` selects: the images or video in `frames`, or the
+    /// camera at `camera_index` when unset.
+    pub fn from_config(config: &DetectorConfig) -> Self {
+        let capture = match &config.frames {
+            Some(path) if path.is_dir() => Self::new(ImageDir::new(path)),
+            Some(path) => Self::new(VideoFile::new(path)),
+            #[cfg(all(feature = "webcam", target_os = "linux"))]
+            None => Self::new(Camera::new(config.camera_index).resolution(config.resolution)),
+            #[cfg(not(all(feature = "webcam", target_os = "linux")))]
+            None => unreachable!("validation rejects a camera without the webcam feature"),
+        };
+        capture.frame_skip(config.frame_skip).resolution(config.resolution)
+    }
+
+    /// Analyse only every `frame_skip`-th frame, as `detector.py` does.
+    pub fn frame_skip(mut self, frame_skip: u32) -> Self {
+        self.frame_skip = frame_skip.max(1).into();
+        self
+    }
+
+    /// Scale frames to `resolution`, if set.
+    pub fn resolution(mut self, resolution: Option<Resolution>) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    pub fn open(&mut self) -> io::Result<()> {
+        self.frame = 0;
+        self.source.open()
+    }
+
+    /// The next frame to analyse, `None` once the source is exhausted.
+    pub fn next_frame(&mut self) -> io::Result<Option<Frame>> {
+        loop {
+            self.frame += 1;
+            if !self.frame.is_multiple_of(self.frame_skip) {
+                if !self.source.grab()? {
+                    return Ok(None);
+                }
+                continue;
+            }
+
+            let Some(mut image) = self.source.read()? else {
+                return Ok(None);
+            };
+            let captured_at = Local::now().naive_local();
+            if let Some(Resolution { width, height }) = self.resolution {
+                if image.dimensions() != (width, height) {
+                    image = imageops::resize(&image, width, height, FilterType::Triangle);
+                }
+            }
+            return Ok(Some(Frame {
+                number: self.frame,
+                image,
+                captured_at,
+            }));
+        }
+    }
+}
+
+/// Error for a frame that cannot be decoded.
+fn undecodable(what: impl std::fmt::Display, err: image::ImageError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("cannot read frame {}: {}", what, err))
+}
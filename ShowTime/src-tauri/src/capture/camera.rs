@@ -0,0 +1,130 @@
+use super::{undecodable, FrameSource};
+use crate::config::Resolution;
+use image::{ImageFormat, RgbImage};
+use std::io::{self, ErrorKind};
+use std::time::Duration;
+use v4l::buffer::Type;
+use v4l::io::mmap::Stream;
+use v4l::io::traits::CaptureStream;
+use v4l::video::Capture as _;
+use v4l::{Device, FourCC};
+
+/// Buffers the driver fills while a frame is being analysed.
+const BUFFERS: u32 = 4;
+
+/// Longest wait for a frame before the camera counts as stuck.
+const FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A V4L2 camera, `/dev/video<index>`, read as Motion JPEG or, failing that,
+/// YUYV.
+pub struct Camera {
+    index: u32,
+    resolution: Option<Resolution>,
+    stream: Option<Streaming>,
+}
+
+struct Streaming {
+    stream: Stream<'static>,
+    fourcc: FourCC,
+    width: u32,
+    height: u32,
+}
+
+impl Camera {
+    pub fn new(index: u32) -> Self {
+        Self {
+            index,
+            resolution: None,
+            stream: None,
+        }
+    }
+
+    /// Asks the driver for `resolution`, if set. Drivers pick the nearest
+    /// size they support.
+    pub fn resolution(mut self, resolution: Option<Resolution>) -> Self {
+        self.resolution = resolution;
+        self
+    }
+}
+
+impl FrameSource for Camera {
+    fn open(&mut self) -> io::Result<()> {
+        // Release the device before opening it again.
+        self.stream = None;
+        let device = Device::new(self.index as usize)?;
+        let mut format = device.format()?;
+        format.fourcc = FourCC::new(b"MJPG");
+        if let Some(Resolution { width, height }) = self.resolution {
+            format.width = width;
+            format.height = height;
+        }
+        let mut format = device.set_format(&format)?;
+        if format.fourcc != FourCC::new(b"MJPG") {
+            format.fourcc = FourCC::new(b"YUYV");
+            format = device.set_format(&format)?;
+        }
+        if format.fourcc != FourCC::new(b"YUYV") && format.fourcc != FourCC::new(b"MJPG") {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                format!("camera {} offers neither MJPG nor YUYV, only {}", self.index, format.fourcc),
+            ));
+        }
+
+        let mut stream = Stream::with_buffers(&device, Type::VideoCapture, BUFFERS)?;
+        stream.set_timeout(FRAME_TIMEOUT);
+        self.stream = Some(Streaming {
+            stream,
+            fourcc: format.fourcc,
+            width: format.width,
+            height: format.height,
+        });
+        Ok(())
+    }
+
+    fn read(&mut self) -> io::Result<Option<RgbImage>> {
+        let Some(streaming) = &mut self.stream else {
+            return Ok(None);
+        };
+        let (buffer, meta) = CaptureStream::next(&mut streaming.stream)?;
+        let data = &buffer[..(meta.bytesused as usize).min(buffer.len())];
+        let image = if streaming.fourcc == FourCC::new(b"MJPG") {
+            image::load_from_memory_with_format(data, ImageFormat::Jpeg)
+                .map_err(|err| undecodable(format!("from camera {}", self.index), err))?
+                .to_rgb8()
+        } else {
+            yuyv_to_rgb(data, streaming.width, streaming.height)?
+        };
+        Ok(Some(image))
+    }
+
+    fn grab(&mut self) -> io::Result<bool> {
+        let Some(streaming) = &mut self.stream else {
+            return Ok(false);
+        };
+        CaptureStream::next(&mut streaming.stream)?;
+        Ok(true)
+    }
+}
+
+/// Converts packed 4:2:2 YUV, two pixels in every four bytes, with the
+/// BT.601 coefficients cameras use.
+fn yuyv_to_rgb(data: &[u8], width: u32, height: u32) -> io::Result<RgbImage> {
+    let pixels = width as usize * height as usize;
+    if data.len() < pixels * 2 {
+        return Err(io::Error::new(ErrorKind::InvalidData, "short YUYV frame"));
+    }
+    let mut rgb = Vec::with_capacity(pixels * 3);
+    for chunk in data[..pixels * 2].chunks_exact(4) {
+        let u = chunk[1] as f32 - 128.0;
+        let v = chunk[3] as f32 - 128.0;
+        for y in [chunk[0], chunk[2]] {
+            let y = y as f32;
+            rgb.extend([
+                (y + 1.402 * v).clamp(0.0, 255.0) as u8,
+                (y - 0.344 * u - 0.714 * v).clamp(0.0, 255.0) as u8,
+                (y + 1.772 * u).clamp(0.0, 255.0) as u8,
+            ]);
+        }
+    }
+    RgbImage::from_raw(width, height, rgb).ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "short YUYV frame"))
+}
@@ -0,0 +1,44 @@
+use super::{undecodable, FrameSource};
+use image::RgbImage;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// The images in a directory, in file name order.
+pub struct ImageDir {
+    dir: PathBuf,
+    pending: std::vec::IntoIter<PathBuf>,
+}
+
+impl ImageDir {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            pending: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl FrameSource for ImageDir {
+    fn open(&mut self) -> io::Result<()> {
+        let mut frames: Vec<PathBuf> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .collect();
+        frames.sort();
+        self.pending = frames.into_iter();
+        Ok(())
+    }
+
+    fn read(&mut self) -> io::Result<Option<RgbImage>> {
+        let Some(path) = self.pending.next() else {
+            return Ok(None);
+        };
+        let image = image::open(&path).map_err(|err| undecodable(path.display(), err))?;
+        Ok(Some(image.to_rgb8()))
+    }
+
+    fn grab(&mut self) -> io::Result<bool> {
+        Ok(self.pending.next().is_some())
+    }
+}
@@ -0,0 +1,171 @@
+use super::{undecodable, FrameSource};
+use image::{ImageFormat, RgbImage};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, ErrorKind, Read};
+use std::path::PathBuf;
+
+/// Start of image.
+const SOI: u8 = 0xD8;
+/// End of image.
+const EOI: u8 = 0xD9;
+/// Start of scan, followed by entropy-coded data.
+const SOS: u8 = 0xDA;
+
+/// A Motion JPEG video: JPEG images one after the other, either bare, as
+/// `ffmpeg -c:v mjpeg -f mjpeg` writes them, or inside a container such as
+/// the AVI files webcams record.
+///
+/// Motion JPEG is the only codec read. H.264, VP9 and the like are not
+/// decoded; convert them with `ffmpeg -i <video> -c:v mjpeg -f mjpeg
+/// <video>.mjpeg` first. A file without a single JPEG image is an error
+/// rather than an empty video.
+pub struct VideoFile {
+    path: PathBuf,
+    reader: Option<BufReader<File>>,
+    frame: u64,
+}
+
+impl VideoFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            reader: None,
+            frame: 0,
+        }
+    }
+
+    fn next_jpeg(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let Some(reader) = &mut self.reader else {
+            return Ok(None);
+        };
+        match read_jpeg(reader) {
+            Ok(None) if self.frame == 0 => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{} holds no Motion JPEG frame, other video codecs are not supported",
+                    self.path.display()
+                ),
+            )),
+            Ok(jpeg) => {
+                self.frame += 1;
+                Ok(jpeg)
+            }
+            // A recording cut short ends with a partial frame.
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                tracing::warn!(path = %self.path.display(), "video ends inside a frame");
+                self.reader = None;
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl FrameSource for VideoFile {
+    fn open(&mut self) -> io::Result<()> {
+        self.reader = Some(BufReader::new(File::open(&self.path)?));
+        self.frame = 0;
+        Ok(())
+    }
+
+    fn read(&mut self) -> io::Result<Option<RgbImage>> {
+        let Some(jpeg) = self.next_jpeg()? else {
+            return Ok(None);
+        };
+        let image = image::load_from_memory_with_format(&jpeg, ImageFormat::Jpeg)
+            .map_err(|err| undecodable(format!("{} of {}", self.frame, self.path.display()), err))?;
+        Ok(Some(image.to_rgb8()))
+    }
+
+    fn grab(&mut self) -> io::Result<bool> {
+        Ok(self.next_jpeg()?.is_some())
+    }
+}
+
+/// Reads the next JPEG image from `reader`, skipping whatever comes before
+/// its start of image marker. `None` at the end of the stream.
+///
+/// The image is delimited by walking its segments rather than by searching
+/// for the end of image marker, which may also close a thumbnail embedded in
+/// its metadata.
+pub fn read_jpeg(reader: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut previous = 0;
+    loop {
+        let Some(byte) = read_byte(reader)? else {
+            return Ok(None);
+        };
+        if previous == 0xFF && byte == SOI {
+            break;
+        }
+        previous = byte;
+    }
+
+    let mut jpeg = vec![0xFF, SOI];
+    let mut marker = read_marker(reader, &mut jpeg)?;
+    loop {
+        match marker {
+            EOI => return Ok(Some(jpeg)),
+            // Markers without a segment.
+            0x01 | 0xD0..=0xD7 => {}
+            _ => {
+                let mut length = [0; 2];
+                reader.read_exact(&mut length)?;
+                jpeg.extend_from_slice(&length);
+                let length = u16::from_be_bytes(length).saturating_sub(2) as u64;
+                if reader.by_ref().take(length).read_to_end(&mut jpeg)? as u64 != length {
+                    return Err(ErrorKind::UnexpectedEof.into());
+                }
+            }
+        }
+        marker = if marker == SOS {
+            read_scan(reader, &mut jpeg)?
+        } else {
+            read_marker(reader, &mut jpeg)?
+        };
+    }
+}
+
+/// Reads a marker, which must come next, onto `jpeg`.
+fn read_marker(reader: &mut impl BufRead, jpeg: &mut Vec<u8>) -> io::Result<u8> {
+    if read_byte(reader)?.ok_or(ErrorKind::UnexpectedEof)? != 0xFF {
+        return Err(io::Error::new(ErrorKind::InvalidData, "expected a JPEG marker"));
+    }
+    loop {
+        // Any number of 0xFF may pad a marker.
+        let marker = read_byte(reader)?.ok_or(ErrorKind::UnexpectedEof)?;
+        if marker != 0xFF {
+            jpeg.extend_from_slice(&[0xFF, marker]);
+            return Ok(marker);
+        }
+    }
+}
+
+/// Reads entropy-coded data onto `jpeg` up to and including the marker that
+/// ends it, which it returns.
+fn read_scan(reader: &mut impl BufRead, jpeg: &mut Vec<u8>) -> io::Result<u8> {
+    loop {
+        let byte = read_byte(reader)?.ok_or(ErrorKind::UnexpectedEof)?;
+        if byte != 0xFF {
+            jpeg.push(byte);
+            continue;
+        }
+        let mut next = read_byte(reader)?.ok_or(ErrorKind::UnexpectedEof)?;
+        while next == 0xFF {
+            next = read_byte(reader)?.ok_or(ErrorKind::UnexpectedEof)?;
+        }
+        jpeg.extend_from_slice(&[0xFF, next]);
+        // A stuffed zero byte and restart markers belong to the scan.
+        if !matches!(next, 0x00 | 0xD0..=0xD7) {
+            return Ok(next);
+        }
+    }
+}
+
+fn read_byte(reader: &mut impl BufRead) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    match reader.read_exact(&mut byte) {
+        Ok(()) => Ok(Some(byte[0])),
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(err) => Err(err),
+    }
+}
@@ -0,0 +1,123 @@
+//! Commands invoked by the frontend.
+
+use crate::audit::{self, AuditEvent, AuditLog, AuditRecord, VerifyReport};
+use crate::detector::{ControlCommand, ControlStatus, DetectorControl, REQUEST_TIMEOUT};
+use crate::logs::{DetectorLogs, LogLine};
+use crate::metrics::{Metrics, MetricsSnapshot};
+use crate::pipeline;
+use crate::status::{ProtectionState, ProtectionStatus};
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Runtime, State, Webview};
+
+#[tauri::command]
+pub fn greet(name: &str) -> String {
+    format!("Hello, {}! You've been greeted from Rust!", name)
+}
+
+#[tauri::command]
+pub fn trigger_blackout<R: Runtime>(app_handle: AppHandle<R>, protection: State<'_, ProtectionState>) {
+    if let Some(response) = protection.pin() {
+        pipeline::respond(&app_handle, response);
+    }
+}
+
+#[tauri::command]
+pub fn restore_window<R: Runtime>(
+    app_handle: AppHandle<R>,
+    webview: Webview<R>,
+    protection: State<'_, ProtectionState>,
+    audit_log: State<'_, AuditLog>,
+) -> Result<(), String> {
+    match protection.release() {
+        Ok(Some(response)) => pipeline::respond(&app_handle, response),
+        Ok(None) => {}
+        Err(reason) => {
+            let origin = webview
+                .url()
+                .map(|url| url.origin().ascii_serialization())
+                .unwrap_or_default();
+            tracing::warn!(%origin, %reason, "refused restore");
+            audit_log.record(AuditEvent::RestoreRefused {
+                origin,
+                reason: reason.clone(),
+            });
+            return Err(reason);
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_protection_status(protection: State<'_, ProtectionState>) -> ProtectionStatus {
+    protection.status()
+}
+
+#[tauri::command]
+pub fn verify_audit_log(audit_log: State<'_, AuditLog>) -> Result<VerifyReport, String> {
+    audit::verify(audit_log.path()).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub fn export_audit_log(
+    audit_log: State<'_, AuditLog>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<AuditRecord>, String> {
+    let from = from
+        .map(|value| audit::parse_bound(&value, false))
+        .transpose()
+        .map_err(|err| err.to_string())?;
+    let to = to
+        .map(|value| audit::parse_bound(&value, true))
+        .transpose()
+        .map_err(|err| err.to_string())?;
+    audit::export(audit_log.path(), from, to).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub fn get_detector_logs(logs: State<'_, DetectorLogs>) -> Vec<LogLine> {
+    logs.lines()
+}
+
+#[tauri::command]
+pub fn get_metrics(metrics: State<'_, Metrics>) -> MetricsSnapshot {
+    metrics.snapshot()
+}
+
+/// Sends `command` to the running detector and returns its status after it.
+/// Everything but `status` is audited.
+#[tauri::command]
+pub async fn control_detector<R: Runtime>(
+    app_handle: AppHandle<R>,
+    control: State<'_, DetectorControl>,
+    audit_log: State<'_, AuditLog>,
+    command: ControlCommand,
+) -> Result<ControlStatus, String> {
+    let control = control.inner().clone();
+    let request = command.clone();
+    let status = tauri::async_runtime::spawn_blocking(move || control.request(request, REQUEST_TIMEOUT))
+        .await
+        .map_err(|err| err.to_string())?
+        .map_err(|err| err.to_string())?;
+    if command != ControlCommand::Status {
+        tracing::info!(?command, "detector controlled");
+        audit_log.record(AuditEvent::DetectorControlled { request: command });
+    }
+    pipeline::detector_paused(&app_handle, status.paused);
+    Ok(status)
+}
+
+/// Invoke handler for every command, for `Builder::invoke_handler`.
+pub fn handler<R: Runtime>() -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    tauri::generate_handler![
+        greet,
+        trigger_blackout,
+        restore_window,
+        get_protection_status,
+        verify_audit_log,
+        export_audit_log,
+        get_detector_logs,
+        get_metrics,
+        control_detector
+    ]
+}
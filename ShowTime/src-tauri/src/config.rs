@@ -0,0 +1,636 @@
+//! Application configuration.
+//!
+//! Settings are layered, later layers winning key by key:
+//!
+//! 1. built-in defaults,
+//! 2. `plugins.showtime` in `tauri.conf.json`,
+//! 3. a TOML file (`$SHOWTIME_CONFIG`, or `showtime.toml` in the app config
+//!    directory),
+//! 4. `SHOWTIME_*` environment variables.
+//!
+//! The environment only tunes the detector and the diagnostics. Settings that
+//! decide whether the app is protected at all come from the first three
+//! layers, which a deployment controls and a local user does not.
+
+use crate::environment::{Admission, EnvironmentReport, VmPolicy};
+use crate::hysteresis::HysteresisConfig;
+use crate::logs::LogFormat;
+use crate::policy::PolicyTable;
+use crate::protocol::{CAMERA_LABELS, PHONE_LABELS};
+use crate::recorders::{self, RecorderRule};
+use crate::supervisor::{FailurePolicy, SupervisorConfig};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Name of the configuration file looked up in the app config directory.
+pub const CONFIG_FILE_NAME: &str = "showtime.toml";
+
+/// Extensions of the video files `frames` may name. Only Motion JPEG is
+/// decoded, whatever the container.
+const MJPEG_EXTENSIONS: &[&str] = &["mjpeg", "mjpg", "avi"];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShowTimeConfig {
+    pub detector: DetectorConfig,
+    pub protection: ProtectionConfig,
+    pub policy: PolicyTable,
+    pub audit: AuditConfig,
+    pub environment: EnvironmentConfig,
+    pub logging: LoggingConfig,
+    pub metrics: MetricsConfig,
+    pub watermark: WatermarkConfig,
+    pub recorders: RecorderConfig,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectorBackend {
+    /// The frozen detector bundled with the app. Only bundles built with
+    /// `tauri.bundle.conf.json` ship it, and select it.
+    Sidecar,
+    /// `detector.py` run by a Python interpreter.
+    Python,
+    /// YOLOv8 exported to ONNX, run in process. Needs the `onnx` feature.
+    Onnx,
+    Replay,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DetectorConfig {
+    pub backend: DetectorBackend,
+    /// Python interpreter used by the `python` backend, a path or a name
+    /// looked up on `PATH`. Defaults to the platform's usual names.
+    pub interpreter: Option<String>,
+    /// Script run by the `python` backend, relative to the app's resources.
+    pub script: PathBuf,
+    /// ONNX model run by the `onnx` backend, relative to the app's resources,
+    /// e.g. from `yolo export model=yolov8n.pt format=onnx`.
+    pub model: PathBuf,
+    /// What the `onnx` backend analyses instead of the camera: a directory of
+    /// still images, taken in file name order, or a Motion JPEG video file.
+    /// Other video codecs are not decoded, and the other backends read the
+    /// camera themselves.
+    pub frames: Option<PathBuf>,
+    /// Recorded session played by the `replay` backend.
+    pub replay_file: Option<PathBuf>,
+    pub camera_index: u32,
+    /// Size the `onnx` backend asks the camera for and scales frames to.
+    /// Frames keep their own size when unset.
+    pub resolution: Option<Resolution>,
+    pub conf_threshold: f32,
+    pub frame_skip: u32,
+    pub target_labels: Vec<String>,
+    /// How often the detector sends a heartbeat.
+    pub heartbeat_interval_ms: u64,
+}
+
+/// Frame size in pixels, written `640x480` in the environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for Resolution {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, ()> {
+        let (width, height) = value.split_once('x').ok_or(())?;
+        Ok(Self {
+            width: width.trim().parse().map_err(drop)?,
+            height: height.trim().parse().map_err(drop)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProtectionConfig {
+    pub failure_policy: FailurePolicy,
+    pub max_restarts: u32,
+    pub vote_window: usize,
+    pub vote_threshold: usize,
+    pub min_hold_ms: u64,
+    pub restore_cooldown_ms: u64,
+    /// Silence after which a running detector counts as stalled.
+    pub heartbeat_timeout_ms: u64,
+    /// Silence tolerated before a detector run sends its first line.
+    pub startup_timeout_ms: u64,
+    /// Time the detector gets to exit on SIGTERM when the app quits.
+    pub shutdown_grace_ms: u64,
+    /// Labels of the windows to protect; `*` matches any run of characters.
+    pub windows: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// Audit log location. Defaults to `audit.jsonl` in the app data
+    /// directory.
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// Loopback address to serve Prometheus metrics on, e.g.
+    /// `127.0.0.1:9464`. Off when unset.
+    pub prometheus_addr: Option<SocketAddr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Log file location. Defaults to the app log directory.
+    pub directory: Option<PathBuf>,
+    /// Number of daily log files kept.
+    pub max_files: usize,
+    /// Detector stderr lines kept for `get_detector_logs`.
+    pub detector_lines: usize,
+    pub format: LogFormat,
+    /// Where to write spans as OTLP JSON lines. Needs the `otel` feature.
+    pub otlp_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatermarkConfig {
+    /// Off unless a deployment turns it on, since it puts the user's name
+    /// on screen.
+    pub enabled: bool,
+    /// `{user}`, `{session}` and `{time}` are filled in.
+    pub text: String,
+    pub opacity: f32,
+    /// Shown as `{user}`. Defaults to the OS account name.
+    pub user: Option<String>,
+    /// How often the time and position are updated.
+    pub refresh_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecorderConfig {
+    /// Whether to look for screen recorders while the app runs.
+    pub enabled: bool,
+    pub scan_interval_ms: u64,
+    /// Tools that count as screen recorders. Replaces the built-in list.
+    pub denylist: Vec<RecorderRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnvironmentConfig {
+    pub on_virtual_machine: VmPolicy,
+    /// Combined signal confidence from which the host counts as a virtual
+    /// machine.
+    pub min_confidence: f32,
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        Self {
+            backend: DetectorBackend::Python,
+            interpreter: None,
+            script: PathBuf::from("detector/detector.py"),
+            model: PathBuf::from("detector/yolov8n.onnx"),
+            frames: None,
+            replay_file: None,
+            camera_index: 0,
+            resolution: None,
+            conf_threshold: 0.5,
+            frame_skip: 2,
+            target_labels: PHONE_LABELS
+                .iter()
+                .chain(CAMERA_LABELS)
+                .map(|label| label.to_string())
+                .collect(),
+            heartbeat_interval_ms: 1000,
+        }
+    }
+}
+
+impl Default for ProtectionConfig {
+    fn default() -> Self {
+        let supervisor = SupervisorConfig::default();
+        let hysteresis = HysteresisConfig::default();
+        Self {
+            failure_policy: supervisor.failure_policy,
+            max_restarts: supervisor.max_restarts,
+            vote_window: hysteresis.window,
+            vote_threshold: hysteresis.threshold,
+            min_hold_ms: hysteresis.min_hold.as_millis() as u64,
+            restore_cooldown_ms: hysteresis.restore_cooldown.as_millis() as u64,
+            heartbeat_timeout_ms: supervisor.heartbeat_timeout.as_millis() as u64,
+            startup_timeout_ms: supervisor.startup_timeout.as_millis() as u64,
+            shutdown_grace_ms: supervisor.shutdown_grace.as_millis() as u64,
+            windows: vec!["*".to_string()],
+        }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            max_files: 7,
+            detector_lines: 200,
+            format: LogFormat::default(),
+            otlp_file: None,
+        }
+    }
+}
+
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            text: "{user} · {session} · {time}".to_string(),
+            opacity: 0.12,
+            user: None,
+            refresh_ms: 1000,
+        }
+    }
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            scan_interval_ms: 2000,
+            denylist: recorders::default_denylist(),
+        }
+    }
+}
+
+impl Default for EnvironmentConfig {
+    fn default() -> Self {
+        Self {
+            on_virtual_machine: VmPolicy::Deny,
+            min_confidence: 0.8,
+        }
+    }
+}
+
+impl DetectorConfig {
+    /// Command line arguments for `detector.py`.
+    pub fn script_args(&self) -> Vec<String> {
+        vec![
+            "--device".to_string(),
+            self.camera_index.to_string(),
+            "--conf-threshold".to_string(),
+            self.conf_threshold.to_string(),
+            "--frame-skip".to_string(),
+            self.frame_skip.to_string(),
+            "--labels".to_string(),
+            self.target_labels.join(","),
+            "--heartbeat-interval".to_string(),
+            (self.heartbeat_interval_ms as f64 / 1000.0).to_string(),
+        ]
+    }
+}
+
+#[cfg(feature = "onnx")]
+impl DetectorConfig {
+    /// The settings `script_args` passes to `detector.py`, for the in-process
+    /// detector.
+    pub fn onnx_settings(&self) -> crate::detector::onnx::OnnxSettings {
+        crate::detector::onnx::OnnxSettings {
+            conf_threshold: self.conf_threshold,
+            target_labels: self.target_labels.clone(),
+            heartbeat_interval: Duration::from_millis(self.heartbeat_interval_ms),
+        }
+    }
+}
+
+impl ProtectionConfig {
+    pub fn supervisor(&self) -> SupervisorConfig {
+        SupervisorConfig {
+            failure_policy: self.failure_policy,
+            max_restarts: self.max_restarts,
+            heartbeat_timeout: Duration::from_millis(self.heartbeat_timeout_ms),
+            startup_timeout: Duration::from_millis(self.startup_timeout_ms),
+            shutdown_grace: Duration::from_millis(self.shutdown_grace_ms),
+            ..SupervisorConfig::default()
+        }
+    }
+
+    pub fn hysteresis(&self) -> HysteresisConfig {
+        HysteresisConfig {
+            window: self.vote_window,
+            threshold: self.vote_threshold,
+            min_hold: Duration::from_millis(self.min_hold_ms),
+            restore_cooldown: Duration::from_millis(self.restore_cooldown_ms),
+        }
+    }
+}
+
+impl RecorderConfig {
+    pub fn scan_interval(&self) -> Duration {
+        Duration::from_millis(self.scan_interval_ms)
+    }
+}
+
+impl EnvironmentConfig {
+    /// Decides whether the app may run in the environment `report` describes.
+    pub fn admit(&self, report: &EnvironmentReport) -> Admission {
+        self.on_virtual_machine.admit(report.is_virtual_machine(self.min_confidence))
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Toml(PathBuf, toml::de::Error),
+    /// A layer did not match the expected shape.
+    Invalid(String, serde_json::Error),
+    /// An environment variable held a value that could not be parsed.
+    Env(&'static str, String),
+    /// The settings parsed but do not make sense together.
+    Inconsistent(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "cannot read {}: {}", path.display(), err),
+            ConfigError::Toml(path, err) => write!(f, "invalid TOML in {}: {}", path.display(), err),
+            ConfigError::Invalid(source, err) => write!(f, "invalid settings in {}: {}", source, err),
+            ConfigError::Env(var, value) => write!(f, "invalid value {:?} for {}", value, var),
+            ConfigError::Inconsistent(reason) => write!(f, "inconsistent settings: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl ShowTimeConfig {
+    /// Loads the configuration from all layers using the process environment.
+    pub fn load(plugin: Option<&serde_json::Value>, file: Option<&Path>) -> Result<Self, ConfigError> {
+        Self::load_with_env(plugin, file, |var| std::env::var(var).ok())
+    }
+
+    /// Like [`ShowTimeConfig::load`] but with an injectable environment.
+    pub fn load_with_env<F>(
+        plugin: Option<&serde_json::Value>,
+        file: Option<&Path>,
+        env: F,
+    ) -> Result<Self, ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut merged = serde_json::to_value(Self::default())
+            .expect("default configuration is always serializable");
+
+        if let Some(plugin) = plugin {
+            Self::check_layer(plugin, "tauri.conf.json")?;
+            merge(&mut merged, plugin.clone());
+        }
+
+        if let Some(path) = file {
+            let text = std::fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+            let layer: serde_json::Value =
+                toml::from_str(&text).map_err(|err| ConfigError::Toml(path.to_path_buf(), err))?;
+            Self::check_layer(&layer, &path.display().to_string())?;
+            merge(&mut merged, layer);
+        }
+
+        let mut config: Self =
+            serde_json::from_value(merged).map_err(|err| ConfigError::Invalid("merged settings".into(), err))?;
+        config.apply_env(env)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Resolves the TOML file to load, if any.
+    pub fn locate(app_config_dir: Option<PathBuf>) -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("SHOWTIME_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        app_config_dir
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .filter(|path| path.is_file())
+    }
+
+    /// Reports layer errors against the layer they came from rather than the
+    /// merged result.
+    fn check_layer(layer: &serde_json::Value, source: &str) -> Result<(), ConfigError> {
+        serde_json::from_value::<Self>(layer.clone())
+            .map(drop)
+            .map_err(|err| ConfigError::Invalid(source.to_string(), err))
+    }
+
+    fn apply_env<F>(&mut self, env: F) -> Result<(), ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        fn parse<T: std::str::FromStr>(var: &'static str, value: String) -> Result<T, ConfigError> {
+            value.trim().parse().map_err(|_| ConfigError::Env(var, value))
+        }
+
+        let detector = &mut self.detector;
+        if let Some(value) = env("SHOWTIME_DETECTOR_INTERPRETER") {
+            detector.interpreter = Some(value);
+        }
+        if let Some(value) = env("SHOWTIME_DETECTOR_SCRIPT") {
+            detector.script = PathBuf::from(value);
+        }
+        if let Some(value) = env("SHOWTIME_DETECTOR_MODEL") {
+            detector.model = PathBuf::from(value);
+        }
+        if let Some(value) = env("SHOWTIME_DETECTOR_FRAMES") {
+            detector.frames = Some(PathBuf::from(value));
+        }
+        if let Some(value) = env("SHOWTIME_CAMERA_INDEX") {
+            detector.camera_index = parse("SHOWTIME_CAMERA_INDEX", value)?;
+        }
+        if let Some(value) = env("SHOWTIME_RESOLUTION") {
+            detector.resolution = Some(parse("SHOWTIME_RESOLUTION", value)?);
+        }
+        if let Some(value) = env("SHOWTIME_CONF_THRESHOLD") {
+            detector.conf_threshold = parse("SHOWTIME_CONF_THRESHOLD", value)?;
+        }
+        if let Some(value) = env("SHOWTIME_FRAME_SKIP") {
+            detector.frame_skip = parse("SHOWTIME_FRAME_SKIP", value)?;
+        }
+        if let Some(value) = env("SHOWTIME_TARGET_LABELS") {
+            detector.target_labels = value
+                .split(',')
+                .map(|label| label.trim().to_lowercase())
+                .filter(|label| !label.is_empty())
+                .collect();
+        }
+        if let Some(value) = env("SHOWTIME_AUDIT_LOG") {
+            self.audit.path = Some(PathBuf::from(value));
+        }
+        if let Some(value) = env("SHOWTIME_LOG_FORMAT") {
+            self.logging.format = match value.trim() {
+                "pretty" => LogFormat::Pretty,
+                "json" => LogFormat::Json,
+                _ => return Err(ConfigError::Env("SHOWTIME_LOG_FORMAT", value)),
+            };
+        }
+        if let Some(value) = env("SHOWTIME_OTLP_FILE") {
+            self.logging.otlp_file = Some(PathBuf::from(value));
+        }
+        if let Some(value) = env("SHOWTIME_METRICS_ADDR") {
+            self.metrics.prometheus_addr = Some(parse("SHOWTIME_METRICS_ADDR", value)?);
+        }
+        if let Some(value) = env("SHOWTIME_WATERMARK") {
+            self.watermark.enabled = parse("SHOWTIME_WATERMARK", value)?;
+        }
+        if let Some(value) = env("SHOWTIME_WATERMARK_TEXT") {
+            self.watermark.text = value;
+        }
+        if let Some(value) = env("SHOWTIME_WATERMARK_OPACITY") {
+            self.watermark.opacity = parse("SHOWTIME_WATERMARK_OPACITY", value)?;
+        }
+        if let Some(value) = env("SHOWTIME_USER_ID") {
+            self.watermark.user = Some(value);
+        }
+        if let Some(value) = env("SHOWTIME_RECORDER_SCAN_INTERVAL_MS") {
+            self.recorders.scan_interval_ms = parse("SHOWTIME_RECORDER_SCAN_INTERVAL_MS", value)?;
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let detector = &self.detector;
+        if !(0.0..=1.0).contains(&detector.conf_threshold) {
+            return Err(ConfigError::Inconsistent(format!(
+                "conf_threshold {} is outside 0..=1",
+                detector.conf_threshold
+            )));
+        }
+        if detector.frame_skip == 0 {
+            return Err(ConfigError::Inconsistent("frame_skip must be at least 1".into()));
+        }
+        if detector.target_labels.is_empty() {
+            return Err(ConfigError::Inconsistent("target_labels is empty".into()));
+        }
+        if detector.backend == DetectorBackend::Replay && detector.replay_file.is_none() {
+            return Err(ConfigError::Inconsistent("the replay backend needs replay_file".into()));
+        }
+        if detector.backend == DetectorBackend::Onnx {
+            if !cfg!(feature = "onnx") {
+                return Err(ConfigError::Inconsistent("the onnx backend needs the onnx feature".into()));
+            }
+            if detector.frames.is_none() && !cfg!(all(feature = "webcam", target_os = "linux")) {
+                return Err(ConfigError::Inconsistent(
+                    "the onnx backend needs frames, or the webcam feature on Linux".into(),
+                ));
+            }
+        }
+        if let Some(frames) = &detector.frames {
+            if detector.backend != DetectorBackend::Onnx {
+                return Err(ConfigError::Inconsistent(
+                    "frames is only read by the onnx backend, the others read the camera themselves".into(),
+                ));
+            }
+            let is_video = frames
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| MJPEG_EXTENSIONS.contains(&extension.to_lowercase().as_str()));
+            if !is_video && !frames.is_dir() {
+                return Err(ConfigError::Inconsistent(format!(
+                    "frames {} is neither a directory of images nor a Motion JPEG video (.mjpeg, .mjpg \
+                     or .avi); convert other video with `ffmpeg -i <video> -c:v mjpeg -f mjpeg <out>.mjpeg`",
+                    frames.display()
+                )));
+            }
+        }
+        if let Some(Resolution { width, height }) = detector.resolution {
+            if width == 0 || height == 0 {
+                return Err(ConfigError::Inconsistent(format!("resolution {}x{} is empty", width, height)));
+            }
+        }
+
+        let protection = &self.protection;
+        if protection.vote_threshold == 0 || protection.vote_threshold > protection.vote_window {
+            return Err(ConfigError::Inconsistent(format!(
+                "vote_threshold {} must be between 1 and vote_window {}",
+                protection.vote_threshold, protection.vote_window
+            )));
+        }
+        if detector.heartbeat_interval_ms == 0 || detector.heartbeat_interval_ms >= protection.heartbeat_timeout_ms {
+            return Err(ConfigError::Inconsistent(format!(
+                "heartbeat_interval_ms {} must be between 1 and heartbeat_timeout_ms {}",
+                detector.heartbeat_interval_ms, protection.heartbeat_timeout_ms
+            )));
+        }
+        if protection.startup_timeout_ms == 0 {
+            return Err(ConfigError::Inconsistent("startup_timeout_ms must be at least 1".into()));
+        }
+        if protection.windows.is_empty() {
+            return Err(ConfigError::Inconsistent("windows is empty, nothing would be protected".into()));
+        }
+
+        if let Some(addr) = self.metrics.prometheus_addr {
+            if !addr.ip().is_loopback() {
+                return Err(ConfigError::Inconsistent(format!(
+                    "prometheus_addr {} is not a loopback address",
+                    addr
+                )));
+            }
+        }
+
+        if self.logging.max_files == 0 {
+            return Err(ConfigError::Inconsistent("max_files must be at least 1".into()));
+        }
+
+        if !(0.0..=1.0).contains(&self.environment.min_confidence) {
+            return Err(ConfigError::Inconsistent(format!(
+                "min_confidence {} is outside 0..=1",
+                self.environment.min_confidence
+            )));
+        }
+
+        let watermark = &self.watermark;
+        if !(0.0..=1.0).contains(&watermark.opacity) {
+            return Err(ConfigError::Inconsistent(format!(
+                "watermark opacity {} is outside 0..=1",
+                watermark.opacity
+            )));
+        }
+        if watermark.enabled && watermark.text.trim().is_empty() {
+            return Err(ConfigError::Inconsistent("watermark text is empty".into()));
+        }
+        if watermark.refresh_ms == 0 {
+            return Err(ConfigError::Inconsistent("watermark refresh_ms must be at least 1".into()));
+        }
+
+        let recorders = &self.recorders;
+        if recorders.scan_interval_ms == 0 {
+            return Err(ConfigError::Inconsistent("scan_interval_ms must be at least 1".into()));
+        }
+        if recorders.denylist.iter().any(|rule| rule.name.trim().is_empty()) {
+            return Err(ConfigError::Inconsistent("a recorder denylist entry has no name".into()));
+        }
+
+        self.policy.validate().map_err(ConfigError::Inconsistent)
+    }
+}
+
+/// Recursively merges `layer` into `base`; objects merge key by key, any
+/// other value replaces what was there.
+fn merge(base: &mut serde_json::Value, layer: serde_json::Value) {
+    match (base, layer) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
@@ -0,0 +1,121 @@
+//! Screen shown instead of the app when the environment check fails.
+//!
+//! The app's windows are closed, page and all, and a small window explains
+//! why the app will not run, with a support code pointing at the audit record
+//! of the decision. Closing it exits the process with the reason's exit code.
+
+use serde::Serialize;
+use tauri::webview::PageLoadEvent;
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder, WindowEvent};
+
+/// Label of the denial window.
+pub const DENIAL_LABEL: &str = "denied";
+
+/// Exit code when the host is a virtual machine and policy denies it.
+pub const EXIT_VIRTUAL_MACHINE: i32 = 10;
+
+/// Exit code when a screen recorder is running.
+pub const EXIT_SCREEN_RECORDER: i32 = 11;
+
+/// Exit code when the detector is missing from the installation.
+pub const EXIT_DETECTOR_MISSING: i32 = 12;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DenialReason {
+    VirtualMachine { confidence: f32 },
+    ScreenRecorder { names: Vec<String> },
+    DetectorMissing { detail: String },
+}
+
+impl DenialReason {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            DenialReason::VirtualMachine { .. } => EXIT_VIRTUAL_MACHINE,
+            DenialReason::ScreenRecorder { .. } => EXIT_SCREEN_RECORDER,
+            DenialReason::DetectorMissing { .. } => EXIT_DETECTOR_MISSING,
+        }
+    }
+
+    /// Explanation for the user.
+    pub fn message(&self) -> String {
+        match self {
+            DenialReason::VirtualMachine { .. } => {
+                "This device looks like a virtual machine. Protected content can only be shown on a physical computer.".to_string()
+            }
+            DenialReason::ScreenRecorder { names } => format!(
+                "Screen recording software is running ({}). Close it and start ShowTime again.",
+                names.join(", ")
+            ),
+            DenialReason::DetectorMissing { detail } => format!(
+                "The camera detector could not be found, so protected content cannot be shown. Reinstall ShowTime. ({})",
+                detail
+            ),
+        }
+    }
+}
+
+/// Replaces the app with the denial screen. The process exits with
+/// `reason.exit_code()` once the screen is closed.
+pub fn show(app_handle: &AppHandle, reason: &DenialReason, support_code: &str) -> tauri::Result<()> {
+    let script = format!(
+        r#"
+        document.documentElement.style.background = '#111';
+        document.body.style.cssText = 'margin: 0; height: 100vh; display: flex; flex-direction: column; align-items: center; justify-content: center; gap: 16px; padding: 0 32px; box-sizing: border-box; color: white; font: 16px sans-serif; text-align: center; background: #111;';
+        document.body.replaceChildren();
+        for (const [text, style] of [
+          ["ShowTime can't run here", 'font-size: 22px; font-weight: bold;'],
+          [{}, ''],
+          ['Support code: ' + {}, 'font-family: monospace; user-select: text;'],
+          ['Close this window to exit.', 'color: #999;'],
+        ]) {{
+          const line = document.createElement('div');
+          line.textContent = text;
+          line.style.cssText = style;
+          document.body.appendChild(line);
+        }}
+        "#,
+        serde_json::to_string(&reason.message()).unwrap_or_default(),
+        serde_json::to_string(support_code).unwrap_or_default(),
+    );
+
+    let window = WebviewWindowBuilder::new(
+        app_handle,
+        DENIAL_LABEL,
+        WebviewUrl::External("about:blank".parse().expect("valid URL")),
+    )
+    .title("showtime")
+    .inner_size(520.0, 320.0)
+    .resizable(false)
+    .center()
+    .always_on_top(true)
+    .on_page_load(move |window, payload| {
+        if let PageLoadEvent::Finished = payload.event() {
+            let _ = window.eval(&script);
+        }
+    })
+    .build()?;
+
+    let handle = app_handle.clone();
+    let code = reason.exit_code();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            handle.exit(code);
+        }
+    });
+
+    // A hidden window keeps its page loaded and can be shown again, so the
+    // others go for good. The denial window is up first so the app does not
+    // exit for want of windows.
+    for (label, other) in app_handle.webview_windows() {
+        if label == DENIAL_LABEL {
+            continue;
+        }
+        if let Err(err) = other.destroy() {
+            tracing::error!(%label, error = %err, "failed to close window, blanking it");
+            let _ = other.navigate("about:blank".parse().expect("valid URL"));
+            let _ = other.hide();
+        }
+    }
+    Ok(())
+}
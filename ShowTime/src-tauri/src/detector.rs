@@ -0,0 +1,82 @@
+//! Sources of detection events.
+//!
+//! The supervisor drives any [`Detector`] the same way: start a run, pull
+//! events until the run ends, reap it and start again. Besides the Python
+//! subprocess used in production, shipped as a frozen sidecar executable and
+//! found through [`Launch::locate`], there is an in-process ONNX backend
+//! behind the `onnx` feature, which reads frames through `capture` and so
+//! also runs on recorded video, a replay backend for recorded sessions and a
+//! scripted in-process mock, so the whole blackout pipeline can run without a
+//! camera.
+//!
+//! The Python detector also takes requests over a [`DetectorControl`]
+//! channel, so it can be paused or retuned without a restart.
+//!
+//! Reads are bounded by a timeout so that a detector which hangs without
+//! exiting is noticed instead of blocking the supervisor forever.
+
+use crate::protocol::{DetectionEvent, ProtocolError};
+use std::io;
+use std::time::Duration;
+
+mod control;
+mod launch;
+mod mock;
+#[cfg(feature = "onnx")]
+pub mod onnx;
+mod process;
+mod python;
+mod replay;
+
+pub use control::{
+    read_message, write_message, ControlCommand, ControlEndpoint, ControlError, ControlReader, ControlStatus,
+    ControlWriter, DetectorControl, CONTROL_VERSION, MAX_MESSAGE_LEN, REQUEST_TIMEOUT,
+};
+pub use launch::{find_program, Launch, LaunchError, SIDECAR_NAME};
+pub use mock::{MockDetector, Step};
+pub use process::ChildProcess;
+pub use python::PythonDetector;
+pub use replay::ReplayDetector;
+
+/// Outcome of waiting for the next event.
+#[derive(Debug)]
+pub enum Next {
+    /// An event, or a line that could not be parsed.
+    Event(Result<DetectionEvent, ProtocolError>),
+    /// Nothing arrived within the timeout; the run may still be alive.
+    Silent,
+    /// The run has ended.
+    Ended,
+}
+
+pub trait Detector: Send {
+    /// Starts a new run. Called again after every run has ended.
+    fn start(&mut self) -> io::Result<()>;
+
+    /// OS process id of the current run, for backends that have one.
+    fn pid(&self) -> Option<u32> {
+        None
+    }
+
+    /// Handle to the OS process behind the runs, for backends that have one.
+    /// It outlives the current run and can stop it from another thread.
+    fn process(&self) -> Option<ChildProcess> {
+        None
+    }
+
+    /// Channel to change the settings of a running detector, for backends
+    /// that take one. It outlives the current run.
+    fn control(&self) -> Option<DetectorControl> {
+        None
+    }
+
+    /// Blocks until the next event, for at most `timeout`.
+    fn next_event(&mut self, timeout: Duration) -> Next;
+
+    /// Ends the current run early, e.g. because it stopped responding.
+    /// [`Detector::wait`] is still called afterwards.
+    fn stop(&mut self) {}
+
+    /// Cleans up the ended run and describes why it ended.
+    fn wait(&mut self) -> String;
+}
@@ -0,0 +1,455 @@
+//! Control channel to a running detector.
+//!
+//! Next to the line protocol on stdout, `detector.py` listens on a local
+//! endpoint given with `--control`: a Unix domain socket in a directory only
+//! this user can enter, or on Windows a pair of named pipes, `<name>.in` for
+//! requests and `<name>.out` for everything the detector sends, since a
+//! synchronous pipe handle cannot be read and written at the same time.
+//!
+//! Every message is a JSON object preceded by its length as a 4-byte big
+//! endian integer. The app sends requests carrying an `id` and the detector
+//! answers each with a response carrying the same `id`, and pushes its
+//! status whenever it changes:
+//!
+//! ```text
+//! > {"v":1,"id":3,"command":"set_conf_threshold","value":0.7}
+//! < {"v":1,"type":"response","id":3,"ok":true,"status":{"paused":false,"conf_threshold":0.7,"frame_skip":2,"frame":812,"fps":29.8}}
+//! < {"v":1,"type":"status","status":{"paused":false,"conf_threshold":0.7,"frame_skip":2,"frame":812,"fps":29.8}}
+//! > {"v":1,"id":4,"command":"set_frame_skip","value":0}
+//! < {"v":1,"type":"response","id":4,"ok":false,"error":"frame_skip must be at least 1"}
+//! ```
+//!
+//! Settings changed here outlive the run: [`DetectorControl`] sends them
+//! again to every detector it connects to, so a restart does not silently
+//! undo a pause.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+/// Version of the control protocol this build speaks.
+pub const CONTROL_VERSION: u32 = 1;
+
+/// Largest message either side accepts.
+pub const MAX_MESSAGE_LEN: usize = 1 << 20;
+
+/// How long the app waits for the detector to answer a request.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Pause between attempts to reach a detector that is not listening yet.
+const CONNECT_RETRY: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlCommand {
+    /// Reports the status without changing anything.
+    Status,
+    /// Keeps reading frames and sending heartbeats but analyses none.
+    Pause,
+    Resume,
+    SetConfThreshold {
+        value: f32,
+    },
+    SetFrameSkip {
+        value: u32,
+    },
+    /// Asks the detector to release the camera and exit.
+    Shutdown,
+}
+
+/// What a detector reports about itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ControlStatus {
+    pub paused: bool,
+    pub conf_threshold: f32,
+    pub frame_skip: u32,
+    /// Frames read so far.
+    pub frame: u64,
+    pub fps: f32,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    v: u32,
+    id: u64,
+    #[serde(flatten)]
+    command: &'a ControlCommand,
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    v: u32,
+    #[serde(flatten)]
+    message: Message,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Response {
+        id: u64,
+        ok: bool,
+        #[serde(default)]
+        status: Option<ControlStatus>,
+        #[serde(default)]
+        error: Option<String>,
+    },
+    Status {
+        status: ControlStatus,
+    },
+}
+
+#[derive(Debug)]
+pub enum ControlError {
+    /// No detector is connected, or it went away before answering.
+    NotConnected,
+    /// The detector did not answer in time.
+    Timeout,
+    /// The detector refused the request.
+    Rejected(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for ControlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlError::NotConnected => write!(f, "detector is not connected"),
+            ControlError::Timeout => write!(f, "detector did not answer"),
+            ControlError::Rejected(reason) => write!(f, "detector refused: {}", reason),
+            ControlError::Io(err) => write!(f, "control channel failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ControlError {}
+
+/// Writes `message` with its length prefix.
+pub fn write_message(writer: &mut impl Write, message: &impl Serialize) -> io::Result<()> {
+    let body = serde_json::to_vec(message)?;
+    if body.len() > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "control message too long"));
+    }
+    writer.write_all(&(body.len() as u32).to_be_bytes())?;
+    writer.write_all(&body)?;
+    writer.flush()
+}
+
+/// Reads one message body. `None` when the other side closed the channel
+/// between messages.
+pub fn read_message(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut length = [0; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(ErrorKind::InvalidData, "control message too long"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+pub type ControlReader = Box<dyn Read + Send>;
+pub type ControlWriter = Box<dyn Write + Send>;
+
+/// Where a detector run listens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlEndpoint {
+    path: PathBuf,
+}
+
+impl ControlEndpoint {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// A fresh endpoint for run `run` of this app instance.
+    #[cfg(unix)]
+    pub fn for_run(run: u64) -> io::Result<Self> {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        let dir = std::env::temp_dir().join(format!("showtime-{}", std::process::id()));
+        std::fs::DirBuilder::new().recursive(true).mode(0o700).create(&dir)?;
+        // A directory left over by an earlier process with our pid.
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
+        let endpoint = Self::new(dir.join(format!("detector-{}.sock", run)));
+        endpoint.remove();
+        Ok(endpoint)
+    }
+
+    /// A fresh endpoint for run `run` of this app instance.
+    #[cfg(windows)]
+    pub fn for_run(run: u64) -> io::Result<Self> {
+        Ok(Self::new(format!(r"\\.\pipe\showtime-{}-{}", std::process::id(), run)))
+    }
+
+    /// The address `detector.py` takes with `--control`.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[cfg(unix)]
+    pub fn connect(&self) -> io::Result<(ControlReader, ControlWriter)> {
+        let stream = std::os::unix::net::UnixStream::connect(&self.path)?;
+        Ok((Box::new(stream.try_clone()?), Box::new(stream)))
+    }
+
+    #[cfg(windows)]
+    pub fn connect(&self) -> io::Result<(ControlReader, ControlWriter)> {
+        let pipe = |suffix: &str| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(suffix);
+            PathBuf::from(name)
+        };
+        // The detector waits for the request pipe first.
+        let writer = std::fs::OpenOptions::new().write(true).open(pipe(".in"))?;
+        let reader = std::fs::OpenOptions::new().read(true).open(pipe(".out"))?;
+        Ok((Box::new(reader), Box::new(writer)))
+    }
+
+    /// Deletes the socket, if the detector left it behind.
+    pub fn remove(&self) {
+        #[cfg(unix)]
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Settings changed through the channel, sent again on every connection.
+#[derive(Debug, Clone, Default)]
+struct Overrides {
+    paused: bool,
+    conf_threshold: Option<f32>,
+    frame_skip: Option<u32>,
+}
+
+impl Overrides {
+    fn apply(&mut self, command: &ControlCommand) {
+        match *command {
+            ControlCommand::Pause => self.paused = true,
+            ControlCommand::Resume => self.paused = false,
+            ControlCommand::SetConfThreshold { value } => self.conf_threshold = Some(value),
+            ControlCommand::SetFrameSkip { value } => self.frame_skip = Some(value),
+            ControlCommand::Status | ControlCommand::Shutdown => {}
+        }
+    }
+
+    fn commands(&self) -> Vec<ControlCommand> {
+        let mut commands = Vec::new();
+        if let Some(value) = self.conf_threshold {
+            commands.push(ControlCommand::SetConfThreshold { value });
+        }
+        if let Some(value) = self.frame_skip {
+            commands.push(ControlCommand::SetFrameSkip { value });
+        }
+        if self.paused {
+            commands.push(ControlCommand::Pause);
+        }
+        commands
+    }
+}
+
+type Reply = Result<ControlStatus, String>;
+type Listener = Box<dyn Fn(&ControlStatus) + Send + Sync>;
+
+#[derive(Default)]
+struct State {
+    writer: Option<ControlWriter>,
+    /// Counts connections, so a reader that outlives its connection cannot
+    /// tear down the next one.
+    connection: u64,
+    next_id: u64,
+    pending: HashMap<u64, Sender<Reply>>,
+    status: Option<ControlStatus>,
+    overrides: Overrides,
+}
+
+impl State {
+    fn send(&mut self, command: &ControlCommand) -> io::Result<u64> {
+        let writer = self.writer.as_mut().ok_or(ErrorKind::NotConnected)?;
+        self.next_id += 1;
+        let id = self.next_id;
+        write_message(
+            writer,
+            &Request {
+                v: CONTROL_VERSION,
+                id,
+                command,
+            },
+        )?;
+        Ok(id)
+    }
+
+    fn disconnect(&mut self) {
+        self.writer = None;
+        // Dropping the senders fails the waiting requests.
+        self.pending.clear();
+    }
+}
+
+/// The app's end of the control channel, shared between the detector that
+/// connects it for every run and whoever sends requests. Cloning is cheap.
+#[derive(Clone, Default)]
+pub struct DetectorControl {
+    state: Arc<Mutex<State>>,
+    listeners: Arc<Mutex<Vec<Listener>>>,
+}
+
+impl DetectorControl {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Calls `listener` with every status the detector pushes.
+    pub fn on_status(&self, listener: impl Fn(&ControlStatus) + Send + Sync + 'static) {
+        self.listeners
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(Box::new(listener));
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.lock().writer.is_some()
+    }
+
+    /// The last status the detector reported.
+    pub fn status(&self) -> Option<ControlStatus> {
+        self.lock().status.clone()
+    }
+
+    /// Sends `command` and waits up to `timeout` for the answer.
+    pub fn request(&self, command: ControlCommand, timeout: Duration) -> Result<ControlStatus, ControlError> {
+        let (sender, receiver) = mpsc::channel();
+        let id = {
+            let mut state = self.lock();
+            if state.writer.is_none() {
+                return Err(ControlError::NotConnected);
+            }
+            match state.send(&command) {
+                Ok(id) => {
+                    state.pending.insert(id, sender);
+                    id
+                }
+                Err(err) => {
+                    state.disconnect();
+                    return Err(ControlError::Io(err));
+                }
+            }
+        };
+
+        match receiver.recv_timeout(timeout) {
+            Ok(Ok(status)) => {
+                self.lock().overrides.apply(&command);
+                Ok(status)
+            }
+            Ok(Err(reason)) => Err(ControlError::Rejected(reason)),
+            Err(RecvTimeoutError::Timeout) => {
+                self.lock().pending.remove(&id);
+                Err(ControlError::Timeout)
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(ControlError::NotConnected),
+        }
+    }
+
+    /// Connects to `endpoint` as soon as the detector listens there, giving
+    /// up once `alive` says the run is over.
+    pub fn connect(&self, endpoint: &ControlEndpoint, alive: impl Fn() -> bool) {
+        loop {
+            match endpoint.connect() {
+                Ok((reader, writer)) => return self.attach(reader, writer),
+                Err(_) if alive() => thread::sleep(CONNECT_RETRY),
+                Err(err) => {
+                    tracing::debug!(error = %err, "detector ended before taking control connections");
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Takes over a connection to a detector, replacing any earlier one,
+    /// and sends it the settings changed so far.
+    pub fn attach(&self, mut reader: ControlReader, writer: ControlWriter) {
+        let connection = {
+            let mut state = self.lock();
+            state.disconnect();
+            state.writer = Some(writer);
+            state.connection += 1;
+            for command in state.overrides.commands() {
+                if let Err(err) = state.send(&command) {
+                    tracing::warn!(error = %err, ?command, "failed to restore detector setting");
+                }
+            }
+            state.connection
+        };
+        tracing::info!("detector control connected");
+
+        let control = self.clone();
+        thread::spawn(move || {
+            let result = loop {
+                match read_message(&mut reader) {
+                    Ok(Some(body)) => control.receive(&body),
+                    Ok(None) => break Ok(()),
+                    Err(err) => break Err(err),
+                }
+            };
+            if let Err(err) = result {
+                tracing::warn!(error = %err, "detector control channel failed");
+            }
+            let mut state = control.lock();
+            if state.connection == connection {
+                state.disconnect();
+            }
+        });
+    }
+
+    /// Drops the current connection.
+    pub fn detach(&self) {
+        self.lock().disconnect();
+    }
+
+    fn receive(&self, body: &[u8]) {
+        let envelope: Envelope = match serde_json::from_slice(body) {
+            Ok(envelope) => envelope,
+            Err(err) => {
+                tracing::warn!(error = %err, "rejected detector control message");
+                return;
+            }
+        };
+        if envelope.v != CONTROL_VERSION {
+            tracing::warn!(version = envelope.v, "unsupported detector control version");
+            return;
+        }
+
+        let pushed = match envelope.message {
+            Message::Response { id, ok, status, error } => {
+                let mut state = self.lock();
+                if let Some(status) = &status {
+                    state.status = Some(status.clone());
+                }
+                let reply = match (ok, status) {
+                    (true, Some(status)) => Ok(status),
+                    (true, None) => Err("response without status".to_string()),
+                    (false, _) => Err(error.unwrap_or_default()),
+                };
+                if let Some(sender) = state.pending.remove(&id) {
+                    let _ = sender.send(reply);
+                }
+                return;
+            }
+            Message::Status { status } => {
+                self.lock().status = Some(status.clone());
+                status
+            }
+        };
+        for listener in self.listeners.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).iter() {
+            listener(&pushed);
+        }
+    }
+}
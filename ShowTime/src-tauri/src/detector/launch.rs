@@ -0,0 +1,133 @@
+use crate::config::{DetectorBackend, DetectorConfig};
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Base name of the frozen detector, as listed under `bundle.externalBin`.
+/// Tauri installs it next to the app's executable, without the target
+/// triple the build expects on the source file.
+pub const SIDECAR_NAME: &str = "showtime-detector";
+
+/// Interpreters tried in order when none is configured.
+#[cfg(windows)]
+const INTERPRETERS: &[&str] = &["python", "python3"];
+#[cfg(not(windows))]
+const INTERPRETERS: &[&str] = &["python3", "python"];
+
+/// What runs the detector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Launch {
+    /// The frozen detector.
+    Sidecar(PathBuf),
+    /// `interpreter script`.
+    Script { interpreter: PathBuf, script: PathBuf },
+    /// The model run in process.
+    Model(PathBuf),
+}
+
+/// Why the detector cannot be started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LaunchError {
+    SidecarMissing(PathBuf),
+    ScriptMissing(PathBuf),
+    ModelMissing(PathBuf),
+    /// None of the named interpreters was found.
+    InterpreterMissing(Vec<String>),
+}
+
+impl fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LaunchError::SidecarMissing(path) => write!(
+                f,
+                "detector executable not found at {}; build it with detector/build_sidecar.py",
+                path.display()
+            ),
+            LaunchError::ScriptMissing(path) => write!(f, "detector script not found at {}", path.display()),
+            LaunchError::ModelMissing(path) => write!(f, "detector model not found at {}", path.display()),
+            LaunchError::InterpreterMissing(names) => {
+                write!(f, "no Python interpreter found on PATH (tried {})", names.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for LaunchError {}
+
+impl Launch {
+    /// Finds the detector for `config`, checking that it exists. The sidecar
+    /// is looked up in `exe_dir`, a relative script or model in
+    /// `resource_dir` and an interpreter given by name in `path_var`, the
+    /// value of `PATH`.
+    ///
+    /// `None` for the replay backend.
+    pub fn locate(
+        config: &DetectorConfig,
+        exe_dir: &Path,
+        resource_dir: &Path,
+        path_var: Option<OsString>,
+    ) -> Option<Result<Self, LaunchError>> {
+        match config.backend {
+            DetectorBackend::Sidecar => Some(locate_sidecar(exe_dir)),
+            DetectorBackend::Python => Some(locate_script(config, resource_dir, path_var)),
+            DetectorBackend::Onnx => Some(locate_model(config, resource_dir)),
+            DetectorBackend::Replay => None,
+        }
+    }
+}
+
+fn locate_sidecar(exe_dir: &Path) -> Result<Launch, LaunchError> {
+    let path = exe_dir.join(executable_name(SIDECAR_NAME));
+    if path.is_file() {
+        Ok(Launch::Sidecar(path))
+    } else {
+        Err(LaunchError::SidecarMissing(path))
+    }
+}
+
+fn locate_script(config: &DetectorConfig, resource_dir: &Path, path_var: Option<OsString>) -> Result<Launch, LaunchError> {
+    let script = resource_dir.join(&config.script);
+    if !script.is_file() {
+        return Err(LaunchError::ScriptMissing(script));
+    }
+
+    let names: Vec<String> = match &config.interpreter {
+        Some(interpreter) => vec![interpreter.clone()],
+        None => INTERPRETERS.iter().map(|name| name.to_string()).collect(),
+    };
+    let interpreter = names
+        .iter()
+        .find_map(|name| find_program(name, path_var.clone()))
+        .ok_or(LaunchError::InterpreterMissing(names))?;
+    Ok(Launch::Script { interpreter, script })
+}
+
+fn locate_model(config: &DetectorConfig, resource_dir: &Path) -> Result<Launch, LaunchError> {
+    let model = resource_dir.join(&config.model);
+    if !model.is_file() {
+        return Err(LaunchError::ModelMissing(model));
+    }
+    Ok(Launch::Model(model))
+}
+
+/// Resolves `name` like a shell would: a path is taken as is, a bare name is
+/// searched for in `path_var`.
+pub fn find_program(name: &str, path_var: Option<OsString>) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    env::split_paths(&path_var?)
+        .map(|dir| dir.join(executable_name(name)))
+        .find(|candidate| candidate.is_file())
+}
+
+/// `name` with the platform's executable suffix, unless it has one.
+fn executable_name(name: &str) -> String {
+    if env::consts::EXE_SUFFIX.is_empty() || name.ends_with(env::consts::EXE_SUFFIX) {
+        name.to_string()
+    } else {
+        format!("{}{}", name, env::consts::EXE_SUFFIX)
+    }
+}
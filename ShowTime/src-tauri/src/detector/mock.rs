@@ -0,0 +1,92 @@
+use super::{Detector, Next};
+use crate::protocol::{self, DetectionEvent};
+use std::collections::VecDeque;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+/// One scripted action of a [`MockDetector`] run.
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Emit a well-formed event.
+    Event(DetectionEvent),
+    /// Emit a raw protocol line, which may be malformed on purpose.
+    Line(String),
+    /// Pause before the next step.
+    Sleep(Duration),
+    /// End the run with the given reason.
+    Exit(String),
+}
+
+/// In-process detector that plays scripted runs.
+///
+/// Each call to `start` consumes the next run; once the script is exhausted
+/// `start` fails, like a detector that can no longer be launched.
+#[derive(Debug, Default)]
+pub struct MockDetector {
+    runs: VecDeque<Vec<Step>>,
+    current: VecDeque<Step>,
+    exit_reason: Option<String>,
+}
+
+impl MockDetector {
+    pub fn new(steps: Vec<Step>) -> Self {
+        Self::default().then(steps)
+    }
+
+    /// Appends another run, played after the detector is restarted.
+    pub fn then(mut self, steps: Vec<Step>) -> Self {
+        self.runs.push_back(steps);
+        self
+    }
+}
+
+impl Detector for MockDetector {
+    fn start(&mut self) -> io::Result<()> {
+        let run = self
+            .runs
+            .pop_front()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "mock script exhausted"))?;
+        self.current = run.into();
+        self.exit_reason = None;
+        Ok(())
+    }
+
+    fn next_event(&mut self, timeout: Duration) -> Next {
+        let mut left = timeout;
+        loop {
+            match self.current.pop_front() {
+                Some(Step::Event(event)) => return Next::Event(Ok(event)),
+                Some(Step::Line(line)) => return Next::Event(protocol::parse_line(&line)),
+                // Pausing past the timeout is a stall; the rest of the pause
+                // is still pending if the run is polled again.
+                Some(Step::Sleep(duration)) if duration > left => {
+                    thread::sleep(left);
+                    self.current.push_front(Step::Sleep(duration - left));
+                    return Next::Silent;
+                }
+                Some(Step::Sleep(duration)) => {
+                    thread::sleep(duration);
+                    left -= duration;
+                }
+                Some(Step::Exit(reason)) => {
+                    self.current.clear();
+                    self.exit_reason = Some(reason);
+                    return Next::Ended;
+                }
+                None => return Next::Ended,
+            }
+        }
+    }
+
+    fn stop(&mut self) {
+        self.current.clear();
+        self.exit_reason = Some("mock run stopped".to_string());
+    }
+
+    fn wait(&mut self) -> String {
+        self.exit_reason
+            .take()
+            .unwrap_or_else(|| "mock run finished".to_string())
+    }
+}
@@ -0,0 +1,406 @@
+use super::{Detector, Next};
+use crate::capture::Capture;
+use crate::protocol::{DetectionEvent, ProtocolError};
+use chrono::{Local, NaiveDateTime};
+use image::imageops::{self, FilterType};
+use image::{Rgb, RgbImage};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tract_onnx::prelude::*;
+
+/// Side of the square input YOLOv8 models are exported with.
+pub const INPUT_SIZE: u32 = 640;
+
+/// Score below which a candidate box is dropped before non-maximum
+/// suppression, as in ultralytics' `predict`.
+const CANDIDATE_THRESHOLD: f32 = 0.25;
+
+/// Overlap above which the weaker of two boxes of a class is suppressed, as
+/// in ultralytics' `predict`.
+const IOU_THRESHOLD: f32 = 0.7;
+
+/// Time a stopped run gets to finish its frame before it is abandoned.
+pub const STOP_GRACE: Duration = Duration::from_secs(2);
+
+/// Letterbox padding, the grey ultralytics pads with.
+const PAD: Rgb<u8> = Rgb([114, 114, 114]);
+
+/// Class names of the COCO-trained YOLOv8 models, by class index.
+pub const COCO_LABELS: [&str; 80] = [
+    "person", "bicycle", "car", "motorcycle", "airplane", "bus", "train", "truck", "boat", "traffic light",
+    "fire hydrant", "stop sign", "parking meter", "bench", "bird", "cat", "dog", "horse", "sheep", "cow",
+    "elephant", "bear", "zebra", "giraffe", "backpack", "umbrella", "handbag", "tie", "suitcase", "frisbee",
+    "skis", "snowboard", "sports ball", "kite", "baseball bat", "baseball glove", "skateboard", "surfboard",
+    "tennis racket", "bottle", "wine glass", "cup", "fork", "knife", "spoon", "bowl", "banana", "apple",
+    "sandwich", "orange", "broccoli", "carrot", "hot dog", "pizza", "donut", "cake", "chair", "couch",
+    "potted plant", "bed", "dining table", "toilet", "tv", "laptop", "mouse", "remote", "keyboard",
+    "cell phone", "microwave", "oven", "toaster", "sink", "refrigerator", "book", "clock", "vase",
+    "scissors", "teddy bear", "hair drier", "toothbrush",
+];
+
+/// One box the model found, in input pixel coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub class: usize,
+    pub confidence: f32,
+    /// Center x, center y, width, height.
+    pub bbox: [f32; 4],
+}
+
+impl Candidate {
+    pub fn label(&self) -> &'static str {
+        COCO_LABELS.get(self.class).copied().unwrap_or("unknown")
+    }
+
+    fn iou(&self, other: &Candidate) -> f32 {
+        let corners = |[cx, cy, w, h]: [f32; 4]| (cx - w / 2.0, cy - h / 2.0, cx + w / 2.0, cy + h / 2.0);
+        let (ax0, ay0, ax1, ay1) = corners(self.bbox);
+        let (bx0, by0, bx1, by1) = corners(other.bbox);
+        let overlap = (ax1.min(bx1) - ax0.max(bx0)).max(0.0) * (ay1.min(by1) - ay0.max(by0)).max(0.0);
+        let union = self.bbox[2] * self.bbox[3] + other.bbox[2] * other.bbox[3] - overlap;
+        if union > 0.0 {
+            overlap / union
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A YOLOv8 model exported to ONNX, optimized for the CPU.
+pub struct YoloModel {
+    plan: TypedRunnableModel<TypedModel>,
+}
+
+impl YoloModel {
+    pub fn load(path: &Path) -> TractResult<Self> {
+        let plan = tract_onnx::onnx()
+            .model_for_path(path)?
+            .with_input_fact(0, f32::fact([1, 3, INPUT_SIZE as usize, INPUT_SIZE as usize]).into())?
+            .into_optimized()?
+            .into_runnable()?;
+        Ok(Self { plan })
+    }
+
+    /// Boxes in `image` after non-maximum suppression, strongest first.
+    pub fn detect(&self, image: &RgbImage) -> TractResult<Vec<Candidate>> {
+        let outputs = self.plan.run(tvec!(letterbox(image).into()))?;
+        let output = outputs[0].to_array_view::<f32>()?;
+        let shape = output.shape();
+        if shape.len() != 3 || shape[1] < 5 {
+            return Err(TractError::msg(format!("unexpected output shape {:?}, expected [1, 84, anchors]", shape)));
+        }
+        let channels = shape[1];
+        let scores: Vec<f32> = output.iter().copied().collect();
+        Ok(decode(&scores, channels))
+    }
+}
+
+/// Scales `image` to fit the model input, keeping its aspect ratio, pads the
+/// rest like ultralytics does and converts it to a normalized NCHW tensor.
+pub fn letterbox(image: &RgbImage) -> Tensor {
+    let size = INPUT_SIZE;
+    let scale = (size as f32 / image.width() as f32).min(size as f32 / image.height() as f32);
+    let width = ((image.width() as f32 * scale).round() as u32).clamp(1, size);
+    let height = ((image.height() as f32 * scale).round() as u32).clamp(1, size);
+
+    let resized = imageops::resize(image, width, height, FilterType::Triangle);
+    let mut padded = RgbImage::from_pixel(size, size, PAD);
+    imageops::replace(&mut padded, &resized, ((size - width) / 2).into(), ((size - height) / 2).into());
+
+    tract_ndarray::Array4::from_shape_fn((1, 3, size as usize, size as usize), |(_, channel, y, x)| {
+        padded.get_pixel(x as u32, y as u32)[channel] as f32 / 255.0
+    })
+    .into()
+}
+
+/// Turns the raw `[channels, anchors]` output, row major, into boxes: the
+/// best class of each anchor, if it scores at least the candidate threshold,
+/// then non-maximum suppression per class.
+pub fn decode(output: &[f32], channels: usize) -> Vec<Candidate> {
+    let anchors = output.len() / channels;
+    let at = |channel: usize, anchor: usize| output[channel * anchors + anchor];
+
+    let mut candidates: Vec<Candidate> = (0..anchors)
+        .filter_map(|anchor| {
+            let (class, confidence) = (4..channels)
+                .map(|channel| (channel - 4, at(channel, anchor)))
+                .max_by(|a, b| a.1.total_cmp(&b.1))?;
+            (confidence >= CANDIDATE_THRESHOLD).then(|| Candidate {
+                class,
+                confidence,
+                bbox: [at(0, anchor), at(1, anchor), at(2, anchor), at(3, anchor)],
+            })
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    let mut kept: Vec<Candidate> = Vec::new();
+    for candidate in candidates {
+        if kept
+            .iter()
+            .all(|other| other.class != candidate.class || other.iou(&candidate) <= IOU_THRESHOLD)
+        {
+            kept.push(candidate);
+        }
+    }
+    kept
+}
+
+/// What the detector reports and how often, as `detector.py` takes it on its
+/// command line. Frame skip is up to the [`Capture`].
+#[derive(Debug, Clone)]
+pub struct OnnxSettings {
+    pub conf_threshold: f32,
+    pub target_labels: Vec<String>,
+    pub heartbeat_interval: Duration,
+}
+
+/// Runs YOLOv8 in process with tract on the frames of a [`Capture`] and
+/// reports like `detector.py` does.
+///
+/// Inference runs on a worker thread so a slow frame cannot block the
+/// supervisor past its timeout. The model is loaded on the first run, and a
+/// model that fails to load fails the run like a crashing process would.
+/// The capture is reopened for every run.
+///
+/// A thread cannot be killed, so a worker stuck in a frame read or a model
+/// run is abandoned after the stop grace period instead. Its capture comes
+/// back if it ever finishes; until then every start fails.
+pub struct OnnxDetector {
+    model_path: PathBuf,
+    settings: OnnxSettings,
+    model: Option<Arc<YoloModel>>,
+    /// Lent to the worker while a run is going.
+    capture: Option<Capture>,
+    stop: Arc<AtomicBool>,
+    stop_grace: Duration,
+    /// Where the worker hands back the capture and how its run ended.
+    worker: Option<Receiver<(Capture, Result<(), String>)>>,
+    /// Set while the worker of an abandoned run has not finished.
+    abandoned: bool,
+    events: Option<Receiver<Result<DetectionEvent, ProtocolError>>>,
+}
+
+impl OnnxDetector {
+    pub fn new(model: impl Into<PathBuf>, capture: Capture, settings: OnnxSettings) -> Self {
+        Self {
+            model_path: model.into(),
+            settings,
+            capture: Some(capture),
+            model: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            stop_grace: STOP_GRACE,
+            worker: None,
+            abandoned: false,
+            events: None,
+        }
+    }
+
+    /// Time a stopped run gets to finish before it is abandoned. Defaults to
+    /// [`STOP_GRACE`].
+    pub fn stop_grace(mut self, grace: Duration) -> Self {
+        self.stop_grace = grace;
+        self
+    }
+
+    /// Takes the capture back from an abandoned worker that has finished
+    /// since.
+    fn reclaim(&mut self) {
+        if !self.abandoned {
+            return;
+        }
+        let Some(worker) = &self.worker else {
+            return;
+        };
+        match worker.try_recv() {
+            Ok((capture, _)) => {
+                self.capture = Some(capture);
+                self.worker = None;
+                self.abandoned = false;
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => {
+                // It panicked and took the capture with it.
+                self.worker = None;
+                self.abandoned = false;
+            }
+        }
+    }
+
+    fn model(&mut self) -> io::Result<Arc<YoloModel>> {
+        if let Some(model) = &self.model {
+            return Ok(model.clone());
+        }
+        let model = YoloModel::load(&self.model_path).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("cannot load {}: {}", self.model_path.display(), err),
+            )
+        })?;
+        Ok(self.model.insert(Arc::new(model)).clone())
+    }
+}
+
+impl Detector for OnnxDetector {
+    fn start(&mut self) -> io::Result<()> {
+        let model = self.model()?;
+        self.reclaim();
+        if self.abandoned {
+            return Err(io::Error::other("the previous run is still stuck"));
+        }
+        let Some(mut capture) = self.capture.take() else {
+            return Err(io::Error::other("detector is already running"));
+        };
+        if let Err(err) = capture.open() {
+            self.capture = Some(capture);
+            return Err(err);
+        }
+
+        self.stop = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+        let worker = Worker {
+            model,
+            settings: self.settings.clone(),
+            stop: self.stop.clone(),
+            sender,
+        };
+        let (done, finished) = mpsc::channel();
+        thread::spawn(move || {
+            let result = worker.run(&mut capture);
+            let _ = done.send((capture, result));
+        });
+        self.worker = Some(finished);
+        self.events = Some(receiver);
+        Ok(())
+    }
+
+    fn next_event(&mut self, timeout: Duration) -> Next {
+        let Some(events) = &self.events else {
+            return Next::Ended;
+        };
+        match events.recv_timeout(timeout) {
+            Ok(event) => Next::Event(event),
+            Err(RecvTimeoutError::Timeout) => Next::Silent,
+            Err(RecvTimeoutError::Disconnected) => Next::Ended,
+        }
+    }
+
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    fn wait(&mut self) -> String {
+        self.stop.store(true, Ordering::Relaxed);
+        self.events = None;
+        if self.abandoned {
+            return "detector was not running".to_string();
+        }
+        let Some(worker) = self.worker.take() else {
+            return "detector was not running".to_string();
+        };
+        match worker.recv_timeout(self.stop_grace) {
+            Ok((capture, result)) => {
+                self.capture = Some(capture);
+                match result {
+                    Ok(()) => "frames exhausted".to_string(),
+                    Err(reason) => reason,
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                self.worker = Some(worker);
+                self.abandoned = true;
+                format!("detector did not stop within {} ms, abandoned it", self.stop_grace.as_millis())
+            }
+            Err(RecvTimeoutError::Disconnected) => "detector thread panicked".to_string(),
+        }
+    }
+}
+
+struct Worker {
+    model: Arc<YoloModel>,
+    settings: OnnxSettings,
+    stop: Arc<AtomicBool>,
+    sender: Sender<Result<DetectionEvent, ProtocolError>>,
+}
+
+impl Worker {
+    /// Ends with the reason the run failed, if it did.
+    fn run(&self, capture: &mut Capture) -> Result<(), String> {
+        let settings = &self.settings;
+        // Heartbeats come from the frame loop itself, as in detector.py.
+        self.send(DetectionEvent::Heartbeat {
+            frame: 0,
+            fps: 0.0,
+            timestamp: now(),
+        })?;
+        let mut last_beat = Instant::now();
+        let mut last_beat_frame = 0;
+
+        while !self.stop.load(Ordering::Relaxed) {
+            let Some(captured) = capture.next_frame().map_err(|err| err.to_string())? else {
+                break;
+            };
+            let captured_at = format(captured.captured_at);
+            let frame = captured.number;
+
+            let elapsed = last_beat.elapsed();
+            if elapsed >= settings.heartbeat_interval {
+                let fps = (frame - last_beat_frame) as f32 / elapsed.as_secs_f32();
+                self.send(DetectionEvent::Heartbeat {
+                    frame,
+                    fps: (fps * 10.0).round() / 10.0,
+                    timestamp: now(),
+                })?;
+                last_beat = Instant::now();
+                last_beat_frame = frame;
+            }
+
+            let candidates = self.model.detect(&captured.image).map_err(|err| format!("inference failed: {}", err))?;
+            let timestamp = now();
+            let mut detected = false;
+            for candidate in candidates {
+                let label = candidate.label();
+                if candidate.confidence >= settings.conf_threshold
+                    && settings.target_labels.iter().any(|target| target == label)
+                {
+                    detected = true;
+                    self.send(DetectionEvent::Detection {
+                        frame,
+                        label: label.to_string(),
+                        confidence: candidate.confidence,
+                        timestamp: timestamp.clone(),
+                        captured_at: Some(captured_at.clone()),
+                    })?;
+                }
+            }
+            if !detected {
+                self.send(DetectionEvent::Clear {
+                    frame,
+                    timestamp,
+                    captured_at: Some(captured_at),
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn send(&self, event: DetectionEvent) -> Result<(), String> {
+        self.sender
+            .send(Ok(event))
+            .map_err(|_| "detector was stopped".to_string())
+    }
+}
+
+/// Local time in the format Python's `datetime.now().isoformat()` uses.
+fn now() -> String {
+    format(Local::now().naive_local())
+}
+
+fn format(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%.6f").to_string()
+}
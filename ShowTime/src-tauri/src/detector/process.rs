@@ -0,0 +1,127 @@
+use std::io;
+use std::process::{Child, ChildStderr, ChildStdout, Command, ExitStatus};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often a process is polled while waiting for it to exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Detector child process, shared between the supervisor thread that reaps it
+/// and whoever has to stop it from outside, e.g. the app on exit.
+///
+/// On Unix the child leads its own process group, so terminating it also
+/// reaches anything it spawned and a Ctrl-C in the terminal is left to the
+/// app to handle.
+#[derive(Debug, Clone, Default)]
+pub struct ChildProcess {
+    child: Arc<Mutex<Option<Child>>>,
+}
+
+impl ChildProcess {
+    /// Spawns `command` as the current child and hands out whichever of its
+    /// stdout and stderr are piped.
+    pub fn spawn(&self, command: &mut Command) -> io::Result<(Option<ChildStdout>, Option<ChildStderr>)> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        let mut child = command.spawn()?;
+        let pipes = (child.stdout.take(), child.stderr.take());
+        *self.lock() = Some(child);
+        Ok(pipes)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Child>> {
+        self.child.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn id(&self) -> Option<u32> {
+        self.lock().as_ref().map(Child::id)
+    }
+
+    /// Kills the child, and on Unix its process group, without giving it a
+    /// chance to clean up.
+    pub fn kill(&self) {
+        if let Some(child) = self.lock().as_mut() {
+            #[cfg(unix)]
+            signal_group(child, libc::SIGKILL);
+            let _ = child.kill();
+        }
+    }
+
+    /// Waits for the child to exit and reaps it. `None` if there was no
+    /// child or someone else reaped it first.
+    pub fn wait(&self) -> io::Result<Option<ExitStatus>> {
+        loop {
+            if let Some(exited) = self.try_reap()? {
+                return Ok(exited);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Asks the child to exit (SIGTERM on Unix), gives it `grace` to do so
+    /// and kills it after that.
+    pub fn terminate(&self, grace: Duration) -> io::Result<Option<ExitStatus>> {
+        if !self.request_exit() {
+            self.kill();
+            return self.wait();
+        }
+        self.await_exit(grace)
+    }
+
+    /// Gives a child that was asked to exit some other way `grace` to do so
+    /// and kills it after that.
+    pub fn await_exit(&self, grace: Duration) -> io::Result<Option<ExitStatus>> {
+        let deadline = Instant::now() + grace;
+        while Instant::now() < deadline {
+            if let Some(exited) = self.try_reap()? {
+                return Ok(exited);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        self.kill();
+        self.wait()
+    }
+
+    /// `Some(status)` once the child is reaped, `Some(None)` if there is no
+    /// child and `None` while it is still running. The lock is only held for
+    /// the check, so the child can be killed while someone waits.
+    fn try_reap(&self) -> io::Result<Option<Option<ExitStatus>>> {
+        let mut slot = self.lock();
+        let Some(child) = slot.as_mut() else {
+            return Ok(Some(None));
+        };
+        match child.try_wait()? {
+            Some(status) => {
+                *slot = None;
+                Ok(Some(Some(status)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Sends SIGTERM to the child's process group. Returns whether there was
+    /// a way to ask politely, which there is not off Unix.
+    #[cfg(unix)]
+    fn request_exit(&self) -> bool {
+        match self.lock().as_ref() {
+            Some(child) => signal_group(child, libc::SIGTERM),
+            None => false,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn request_exit(&self) -> bool {
+        false
+    }
+}
+
+/// Signals the process group `child` leads.
+#[cfg(unix)]
+fn signal_group(child: &Child, signal: libc::c_int) -> bool {
+    // The child leads its group, so its pid is the group id.
+    unsafe { libc::kill(-(child.id() as libc::pid_t), signal) == 0 }
+}
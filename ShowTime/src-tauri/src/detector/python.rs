@@ -0,0 +1,175 @@
+use super::{ChildProcess, ControlEndpoint, Detector, DetectorControl, Next};
+use crate::logs::DetectorLogs;
+use crate::protocol::{self, DetectionEvent, ProtocolError};
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// Runs `detector.py`, through an interpreter or frozen into the sidecar,
+/// and reads the line protocol from its stdout.
+///
+/// Lines are read on a separate thread so a detector that hangs without
+/// closing stdout can be timed out and killed. The process itself is shared
+/// through [`Detector::process`] so the app can stop it on exit. Its stderr
+/// goes to [`DetectorLogs`] when one is attached and is inherited otherwise.
+/// With a [`DetectorControl`] attached, every run gets its own control
+/// endpoint and the control connects to it once the script listens.
+pub struct PythonDetector {
+    program: PathBuf,
+    script: Option<PathBuf>,
+    args: Vec<String>,
+    process: ChildProcess,
+    logs: Option<DetectorLogs>,
+    control: Option<DetectorControl>,
+    endpoint: Option<ControlEndpoint>,
+    runs: u64,
+    events: Option<Receiver<Result<DetectionEvent, ProtocolError>>>,
+}
+
+impl PythonDetector {
+    pub fn new(interpreter: impl Into<PathBuf>, script: impl Into<PathBuf>) -> Self {
+        Self::with_program(interpreter.into(), Some(script.into()))
+    }
+
+    /// Runs the frozen detector at `executable`.
+    pub fn frozen(executable: impl Into<PathBuf>) -> Self {
+        Self::with_program(executable.into(), None)
+    }
+
+    fn with_program(program: PathBuf, script: Option<PathBuf>) -> Self {
+        Self {
+            program,
+            script,
+            args: Vec::new(),
+            process: ChildProcess::default(),
+            logs: None,
+            control: None,
+            endpoint: None,
+            runs: 0,
+            events: None,
+        }
+    }
+
+    /// Extra command line arguments passed to the script.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Captures the script's stderr line by line into `logs`.
+    pub fn logs(mut self, logs: DetectorLogs) -> Self {
+        self.logs = Some(logs);
+        self
+    }
+
+    /// Lets `control` change the script's settings while it runs.
+    pub fn control(mut self, control: DetectorControl) -> Self {
+        self.control = Some(control);
+        self
+    }
+}
+
+impl Detector for PythonDetector {
+    fn start(&mut self) -> io::Result<()> {
+        let stderr = match self.logs {
+            Some(_) => Stdio::piped(),
+            None => Stdio::inherit(),
+        };
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.script)
+            .args(&self.args)
+            .stdout(Stdio::piped())
+            .stderr(stderr);
+        if self.control.is_some() {
+            self.runs += 1;
+            let endpoint = ControlEndpoint::for_run(self.runs)?;
+            command.arg("--control").arg(endpoint.path());
+            self.endpoint = Some(endpoint);
+        }
+        let (stdout, stderr) = self.process.spawn(&mut command)?;
+
+        if let (Some(control), Some(endpoint)) = (self.control.clone(), self.endpoint.clone()) {
+            let process = self.process.clone();
+            let pid = process.id();
+            thread::spawn(move || control.connect(&endpoint, || pid.is_some() && process.id() == pid));
+        }
+
+        if let (Some(stderr), Some(logs)) = (stderr, self.logs.clone()) {
+            thread::spawn(move || {
+                // Lossy, so a stray non-UTF-8 byte neither ends capture nor
+                // leaves the pipe to fill up and block the detector.
+                let mut stderr = BufReader::new(stderr);
+                let mut line = Vec::new();
+                while matches!(stderr.read_until(b'\n', &mut line), Ok(read) if read > 0) {
+                    logs.record(&String::from_utf8_lossy(&line));
+                    line.clear();
+                }
+            });
+        }
+
+        if let Some(stdout) = stdout {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                for line in BufReader::new(stdout).lines() {
+                    let Ok(line) = line else { break };
+                    if sender.send(protocol::parse_line(&line)).is_err() {
+                        break;
+                    }
+                }
+            });
+            self.events = Some(receiver);
+        }
+        Ok(())
+    }
+
+    fn pid(&self) -> Option<u32> {
+        self.process.id()
+    }
+
+    fn process(&self) -> Option<ChildProcess> {
+        Some(self.process.clone())
+    }
+
+    fn control(&self) -> Option<DetectorControl> {
+        self.control.clone()
+    }
+
+    fn next_event(&mut self, timeout: Duration) -> Next {
+        let Some(events) = &self.events else {
+            return Next::Ended;
+        };
+        match events.recv_timeout(timeout) {
+            Ok(event) => Next::Event(event),
+            Err(RecvTimeoutError::Timeout) => Next::Silent,
+            Err(RecvTimeoutError::Disconnected) => Next::Ended,
+        }
+    }
+
+    fn stop(&mut self) {
+        self.process.kill();
+    }
+
+    fn wait(&mut self) -> String {
+        self.events = None;
+        let exited = self.process.wait();
+        if let Some(control) = &self.control {
+            control.detach();
+        }
+        if let Some(endpoint) = self.endpoint.take() {
+            endpoint.remove();
+        }
+        match exited {
+            Ok(Some(status)) => format!("detector exited with {}", status),
+            Ok(None) => "detector was not running".to_string(),
+            Err(err) => format!("failed to wait for detector: {}", err),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use super::{Detector, Next};
+use crate::protocol::{self, DetectionEvent, ProtocolError};
+use chrono::NaiveDateTime;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Lines};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Plays back a recorded JSONL session, keeping the original spacing
+/// between events.
+pub struct ReplayDetector {
+    path: PathBuf,
+    speed: f64,
+    lines: Option<Lines<BufReader<File>>>,
+    last_timestamp: Option<NaiveDateTime>,
+    /// Next line and when it is due, while waiting for it outlasts a timeout.
+    pending: Option<(Instant, Result<DetectionEvent, ProtocolError>)>,
+}
+
+impl ReplayDetector {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            speed: 1.0,
+            lines: None,
+            last_timestamp: None,
+            pending: None,
+        }
+    }
+
+    /// Playback speed multiplier. `f64::INFINITY` replays without pauses.
+    pub fn speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Delay before `event`, scaled by the playback speed.
+    fn gap(&mut self, event: &DetectionEvent) -> Duration {
+        let timestamp = match event.timestamp().parse::<NaiveDateTime>() {
+            Ok(timestamp) => timestamp,
+            Err(_) => return Duration::ZERO,
+        };
+
+        match self
+            .last_timestamp
+            .replace(timestamp)
+            .and_then(|last| (timestamp - last).to_std().ok())
+        {
+            Some(gap) if self.speed.is_finite() && self.speed > 0.0 => gap.div_f64(self.speed),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+impl Detector for ReplayDetector {
+    fn start(&mut self) -> io::Result<()> {
+        let file = File::open(&self.path)?;
+        self.lines = Some(BufReader::new(file).lines());
+        self.last_timestamp = None;
+        self.pending = None;
+        Ok(())
+    }
+
+    fn next_event(&mut self, timeout: Duration) -> Next {
+        let (due, parsed) = match self.pending.take() {
+            Some(pending) => pending,
+            None => {
+                let Some(Ok(line)) = self.lines.as_mut().and_then(Iterator::next) else {
+                    return Next::Ended;
+                };
+                let parsed = protocol::parse_line(&line);
+                let gap = match &parsed {
+                    Ok(event) => self.gap(event),
+                    Err(_) => Duration::ZERO,
+                };
+                (Instant::now() + gap, parsed)
+            }
+        };
+
+        // Recordings with long pauses stall like a hanging detector would.
+        let wait = due.saturating_duration_since(Instant::now());
+        if wait > timeout {
+            thread::sleep(timeout);
+            self.pending = Some((due, parsed));
+            return Next::Silent;
+        }
+        thread::sleep(wait);
+        Next::Event(parsed)
+    }
+
+    fn stop(&mut self) {
+        self.lines = None;
+        self.pending = None;
+    }
+
+    fn wait(&mut self) -> String {
+        self.lines = None;
+        format!("replay of {} finished", self.path.display())
+    }
+}
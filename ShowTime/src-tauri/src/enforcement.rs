@@ -0,0 +1,214 @@
+//! Carries out [`Enforcement`] decisions on the protected windows.
+//!
+//! Blackout goes through the Rust-owned [`overlay`](crate::overlay). The
+//! lighter responses are page effects injected into every protected window;
+//! every element they add carries the `__showtime` prefix so lifting can find
+//! and remove them again. Loading a page discards whatever was injected into
+//! the previous one, so the registry sets every newly loaded page up again
+//! through [`Surface::eval_in`].
+//!
+//! Both go through a [`Surface`]: [`WindowSurface`] drives the real windows,
+//! [`RecordingSurface`] only records what would have happened, for tests.
+
+use crate::overlay;
+use crate::platform;
+use crate::policy::Action;
+use crate::protection::Enforcement;
+use crate::registry::ProtectionRegistry;
+use crate::watermark::Watermark;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, Runtime};
+
+const WARNING_SCRIPT: &str = r#"
+(() => {
+  if (document.getElementById('__showtime_warning')) return;
+  const banner = document.createElement('div');
+  banner.id = '__showtime_warning';
+  banner.textContent = 'A recording device was detected. Please put it away.';
+  banner.style.cssText = 'position: fixed; top: 0; left: 0; right: 0; padding: 12px; background: #b00020; color: white; font: 16px sans-serif; text-align: center; z-index: 2147483647;';
+  document.documentElement.appendChild(banner);
+})();
+"#;
+
+const PAUSE_MEDIA_SCRIPT: &str = r#"
+document.querySelectorAll('video, audio').forEach(media => media.pause());
+"#;
+
+const WATERMARK_SCRIPT: &str = r#"
+(() => {
+  if (document.getElementById('__showtime_watermark')) return;
+  const mark = document.createElement('div');
+  mark.id = '__showtime_watermark';
+  mark.textContent = 'CONFIDENTIAL - RECORDING DETECTED';
+  mark.style.cssText = 'position: fixed; inset: 0; display: flex; align-items: center; justify-content: center; pointer-events: none; color: rgba(255, 0, 0, 0.35); font: bold 48px sans-serif; transform: rotate(-30deg); z-index: 2147483646;';
+  document.documentElement.appendChild(mark);
+})();
+"#;
+
+const BLUR_SCRIPT: &str = r#"
+document.documentElement.style.filter = 'blur(24px)';
+"#;
+
+/// Suppresses the context menu in a protected page.
+pub const GUARD_SCRIPT: &str = r#"
+document.addEventListener('contextmenu', e => e.preventDefault());
+"#;
+
+/// Removes every page effect.
+pub const LIFT_SCRIPT: &str = r#"
+document.documentElement.style.filter = '';
+['__showtime_warning', '__showtime_watermark'].forEach(id => document.getElementById(id)?.remove());
+"#;
+
+/// Where enforcement becomes visible.
+pub trait Surface: Send + Sync {
+    /// Covers the protected windows, optionally with `message` on top.
+    fn show_overlay(&self, message: Option<&str>);
+
+    fn hide_overlay(&self);
+
+    /// Runs `script` in the protected pages.
+    fn eval(&self, script: &str);
+
+    /// Runs `script` in the page of the protected window labelled `label`
+    /// only.
+    fn eval_in(&self, label: &str, script: &str);
+
+    /// Brings the newly enrolled window labelled `label` under protection.
+    fn protect(&self, label: &str);
+}
+
+/// The overlays and protected windows of a running app.
+pub struct WindowSurface<R: Runtime> {
+    app_handle: AppHandle<R>,
+}
+
+impl<R: Runtime> WindowSurface<R> {
+    pub fn new(app_handle: AppHandle<R>) -> Self {
+        Self { app_handle }
+    }
+}
+
+impl<R: Runtime> Surface for WindowSurface<R> {
+    fn show_overlay(&self, message: Option<&str>) {
+        overlay::show(&self.app_handle, message);
+    }
+
+    fn hide_overlay(&self) {
+        overlay::hide(&self.app_handle);
+    }
+
+    fn eval(&self, script: &str) {
+        for label in self.app_handle.state::<ProtectionRegistry>().labels() {
+            if let Some(window) = self.app_handle.get_webview_window(&label) {
+                let _ = window.eval(script);
+            }
+        }
+    }
+
+    fn eval_in(&self, label: &str, script: &str) {
+        if let Some(window) = self.app_handle.get_webview_window(label) {
+            let _ = window.eval(script);
+        }
+    }
+
+    fn protect(&self, label: &str) {
+        let Some(window) = self.app_handle.get_webview_window(label) else {
+            return;
+        };
+        platform::block_capture(&window);
+        if let Err(err) = overlay::attach(&self.app_handle, &window) {
+            tracing::error!(%label, error = %err, "failed to create overlay");
+        }
+        if self.app_handle.state::<Watermark>().is_enabled() {
+            if let Err(err) = overlay::attach_watermark(&self.app_handle, &window) {
+                tracing::error!(%label, error = %err, "failed to create watermark");
+            }
+        }
+        let _ = window.eval(GUARD_SCRIPT);
+    }
+}
+
+/// One call made on a [`RecordingSurface`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    ShowOverlay(Option<String>),
+    HideOverlay,
+    Eval(String),
+    EvalIn(String, String),
+    Protect(String),
+}
+
+/// Records effects instead of showing them. Clones share the record.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingSurface {
+    effects: Arc<Mutex<Vec<Effect>>>,
+}
+
+impl RecordingSurface {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every effect so far, oldest first.
+    pub fn effects(&self) -> Vec<Effect> {
+        self.lock().clone()
+    }
+
+    /// Returns and forgets the effects so far.
+    pub fn take(&self) -> Vec<Effect> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Effect>> {
+        self.effects.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Surface for RecordingSurface {
+    fn show_overlay(&self, message: Option<&str>) {
+        self.lock().push(Effect::ShowOverlay(message.map(str::to_string)));
+    }
+
+    fn hide_overlay(&self) {
+        self.lock().push(Effect::HideOverlay);
+    }
+
+    fn eval(&self, script: &str) {
+        self.lock().push(Effect::Eval(script.to_string()));
+    }
+
+    fn eval_in(&self, label: &str, script: &str) {
+        self.lock().push(Effect::EvalIn(label.to_string(), script.to_string()));
+    }
+
+    fn protect(&self, label: &str) {
+        self.lock().push(Effect::Protect(label.to_string()));
+    }
+}
+
+pub fn enforce(surface: &dyn Surface, enforcement: Enforcement) {
+    match enforcement {
+        Enforcement::Apply(Action::Blackout) => surface.show_overlay(None),
+        Enforcement::Apply(action) => {
+            if let Some(script) = page_script(action) {
+                surface.eval(script);
+            }
+        }
+        Enforcement::Lift => {
+            surface.hide_overlay();
+            surface.eval(LIFT_SCRIPT);
+        }
+    }
+}
+
+/// Page script for `action`, `None` for actions that are not page effects.
+pub fn page_script(action: Action) -> Option<&'static str> {
+    match action {
+        Action::LogOnly | Action::Blackout => None,
+        Action::Warning => Some(WARNING_SCRIPT),
+        Action::PauseMedia => Some(PAUSE_MEDIA_SCRIPT),
+        Action::Watermark => Some(WATERMARK_SCRIPT),
+        Action::Blur => Some(BLUR_SCRIPT),
+    }
+}
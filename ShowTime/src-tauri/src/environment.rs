@@ -0,0 +1,245 @@
+//! Detection of the environment the app runs in.
+//!
+//! [`Facts`] holds raw observations about the machine: the CPUID hypervisor
+//! bit and vendor leaf, CPU vendor strings, firmware (DMI) fields and
+//! OS-specific hypervisor markers. [`EnvironmentReport::from_facts`] turns
+//! them into [`Signal`]s with a confidence each. Whether a virtual machine
+//! may run the app is not decided here but by the configured [`VmPolicy`].
+//!
+//! Facts are plain data, so tests feed fixtures instead of probing the host.
+
+use crate::platform;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// CPUID hypervisor vendor signatures of products that only run guests.
+/// `Microsoft Hv` is left out: Windows sets it on bare metal as soon as
+/// virtualization-based security is on.
+const HYPERVISOR_VENDORS: &[&str] = &[
+    "KVMKVMKVM",
+    "VMwareVMware",
+    "VBoxVBoxVBox",
+    "XenVMMXenVMM",
+    "TCGTCGTCGTCG",
+    "prl hyperv",
+    " lrpepyh  vr",
+    "bhyve bhyve",
+    "ACRNACRNACRN",
+    "QNXQVMBSQG",
+];
+
+/// Firmware vendor and product strings, lowercase, that only virtual
+/// hardware reports.
+const FIRMWARE_VENDORS: &[&str] = &[
+    "qemu",
+    "kvm",
+    "vmware",
+    "virtualbox",
+    "innotek",
+    "xen",
+    "bochs",
+    "parallels",
+    "bhyve",
+    "virtual machine",
+    "virtualmac",
+    "amazon ec2",
+    "google compute engine",
+];
+
+/// CPU model names that emulated CPUs report.
+const VIRTUAL_CPU_BRANDS: &[&str] = &["qemu virtual cpu", "common kvm processor", "kvm64"];
+
+/// Raw observations, as collected by [`Facts::collect`] or loaded from a
+/// fixture.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Facts {
+    /// CPUID leaf 1, ECX bit 31.
+    pub hypervisor_bit: bool,
+    /// Signature from CPUID leaf `0x4000_0000`, when the bit is set.
+    pub hypervisor_vendor: Option<String>,
+    pub cpu_vendor: Option<String>,
+    pub cpu_brand: Option<String>,
+    /// Firmware fields by name: DMI on Linux, the BIOS registry key on
+    /// Windows, `hw.model` on macOS.
+    pub firmware: BTreeMap<String, String>,
+    /// OS-specific evidence of a hypervisor, e.g. `/sys/hypervisor/type`.
+    pub markers: Vec<String>,
+}
+
+impl Facts {
+    /// Probes the host.
+    pub fn collect() -> Self {
+        let (hypervisor_bit, hypervisor_vendor) = cpuid_hypervisor();
+        let (cpu_vendor, cpu_brand) = cpu_strings();
+        Self {
+            hypervisor_bit,
+            hypervisor_vendor,
+            cpu_vendor,
+            cpu_brand,
+            firmware: platform::firmware(),
+            markers: platform::hypervisor_markers(),
+        }
+    }
+}
+
+#[cfg(any(all(target_arch = "x86", target_feature = "sse"), target_arch = "x86_64"))]
+fn cpuid_hypervisor() -> (bool, Option<String>) {
+    use raw_cpuid::{CpuId, Hypervisor};
+
+    let cpuid = CpuId::new();
+    let bit = cpuid
+        .get_feature_info()
+        .is_some_and(|features| features.has_hypervisor());
+    let vendor = cpuid.get_hypervisor_info().map(|info| match info.identify() {
+        Hypervisor::Xen => "XenVMMXenVMM".to_string(),
+        Hypervisor::VMware => "VMwareVMware".to_string(),
+        Hypervisor::HyperV => "Microsoft Hv".to_string(),
+        Hypervisor::KVM => "KVMKVMKVM".to_string(),
+        Hypervisor::QEMU => "TCGTCGTCGTCG".to_string(),
+        Hypervisor::Bhyve => "bhyve bhyve".to_string(),
+        Hypervisor::QNX => "QNXQVMBSQG".to_string(),
+        Hypervisor::ACRN => "ACRNACRNACRN".to_string(),
+        Hypervisor::Unknown(ebx, ecx, edx) => [ebx, ecx, edx]
+            .iter()
+            .flat_map(|register| register.to_le_bytes())
+            .filter(|byte| *byte != 0)
+            .map(char::from)
+            .collect(),
+    });
+    (bit, vendor)
+}
+
+#[cfg(not(any(all(target_arch = "x86", target_feature = "sse"), target_arch = "x86_64")))]
+fn cpuid_hypervisor() -> (bool, Option<String>) {
+    (false, None)
+}
+
+fn cpu_strings() -> (Option<String>, Option<String>) {
+    use sysinfo::{CpuRefreshKind, RefreshKind, System};
+
+    let system = System::new_with_specifics(RefreshKind::new().with_cpu(CpuRefreshKind::new()));
+    let non_empty = |value: &str| Some(value.trim().to_string()).filter(|value| !value.is_empty());
+    match system.cpus().first() {
+        Some(cpu) => (non_empty(cpu.vendor_id()), non_empty(cpu.brand())),
+        None => (None, None),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignalSource {
+    HypervisorBit,
+    HypervisorVendor,
+    CpuBrand,
+    Firmware,
+    Marker,
+}
+
+/// One piece of evidence for a virtual machine.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Signal {
+    pub source: SignalSource,
+    pub detail: String,
+    /// How likely this signal alone means a virtual machine, `0..=1`.
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvironmentReport {
+    pub facts: Facts,
+    pub signals: Vec<Signal>,
+    /// Combined confidence of all signals, treating them as independent.
+    pub confidence: f32,
+}
+
+impl EnvironmentReport {
+    /// Probes the host and evaluates what it found.
+    pub fn collect() -> Self {
+        Self::from_facts(Facts::collect())
+    }
+
+    pub fn from_facts(facts: Facts) -> Self {
+        let mut signals = Vec::new();
+        let mut signal = |source, detail: String, confidence| {
+            signals.push(Signal {
+                source,
+                detail,
+                confidence,
+            })
+        };
+
+        if facts.hypervisor_bit {
+            signal(SignalSource::HypervisorBit, "CPUID hypervisor bit set".to_string(), 0.5);
+        }
+        if let Some(vendor) = &facts.hypervisor_vendor {
+            let known = HYPERVISOR_VENDORS.iter().any(|known| vendor.trim() == known.trim());
+            signal(
+                SignalSource::HypervisorVendor,
+                format!("hypervisor vendor {:?}", vendor),
+                if known { 0.95 } else { 0.3 },
+            );
+        }
+        if let Some(brand) = &facts.cpu_brand {
+            let lower = brand.to_lowercase();
+            if VIRTUAL_CPU_BRANDS.iter().any(|virtual_cpu| lower.contains(virtual_cpu)) {
+                signal(SignalSource::CpuBrand, format!("CPU brand {:?}", brand), 0.8);
+            }
+        }
+        for (field, value) in &facts.firmware {
+            let lower = value.to_lowercase();
+            if FIRMWARE_VENDORS.iter().any(|vendor| lower.contains(vendor)) {
+                signal(SignalSource::Firmware, format!("{} {:?}", field, value), 0.9);
+            }
+        }
+        for marker in &facts.markers {
+            signal(SignalSource::Marker, marker.clone(), 0.7);
+        }
+
+        let confidence = 1.0 - signals.iter().map(|signal| 1.0 - signal.confidence).product::<f32>();
+        Self {
+            facts,
+            signals,
+            confidence,
+        }
+    }
+
+    /// Whether the evidence reaches `min_confidence`.
+    pub fn is_virtual_machine(&self, min_confidence: f32) -> bool {
+        !self.signals.is_empty() && self.confidence >= min_confidence
+    }
+}
+
+/// What to do when the app finds itself in a virtual machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VmPolicy {
+    Allow,
+    /// Run, but say so loudly.
+    Warn,
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Admission {
+    Allowed,
+    Warned,
+    Denied,
+}
+
+impl Admission {
+    pub fn is_allowed(self) -> bool {
+        self != Admission::Denied
+    }
+}
+
+impl VmPolicy {
+    pub fn admit(self, virtual_machine: bool) -> Admission {
+        match (virtual_machine, self) {
+            (false, _) | (true, VmPolicy::Allow) => Admission::Allowed,
+            (true, VmPolicy::Warn) => Admission::Warned,
+            (true, VmPolicy::Deny) => Admission::Denied,
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Events emitted to the frontend.
+//!
+//! | event              | payload                                      |
+//! |--------------------|----------------------------------------------|
+//! | `detection`        | [`Detection`], for every reported object     |
+//! | `blackout-changed` | [`ProtectionStatus`], whenever a response is applied or lifted, or detection pauses or resumes |
+//! | `detector-status`  | [`DetectorStatus`], on every supervisor state change |
+//! | `detector-control` | [`ControlStatus`], whenever the detector reports a settings change |
+
+use crate::detector::ControlStatus;
+use crate::status::{Detection, ProtectionStatus};
+use crate::supervisor::DetectorStatus;
+use tauri::{AppHandle, Emitter, Runtime};
+
+pub const DETECTION: &str = "detection";
+pub const BLACKOUT_CHANGED: &str = "blackout-changed";
+pub const DETECTOR_STATUS: &str = "detector-status";
+pub const DETECTOR_CONTROL: &str = "detector-control";
+
+pub fn detection<R: Runtime>(app_handle: &AppHandle<R>, detection: &Detection) {
+    let _ = app_handle.emit(DETECTION, detection);
+}
+
+pub fn blackout_changed<R: Runtime>(app_handle: &AppHandle<R>, status: &ProtectionStatus) {
+    let _ = app_handle.emit(BLACKOUT_CHANGED, status);
+}
+
+pub fn detector_status<R: Runtime>(app_handle: &AppHandle<R>, status: &DetectorStatus) {
+    let _ = app_handle.emit(DETECTOR_STATUS, status);
+}
+
+pub fn detector_control<R: Runtime>(app_handle: &AppHandle<R>, status: &ControlStatus) {
+    let _ = app_handle.emit(DETECTOR_CONTROL, status);
+}
@@ -0,0 +1,392 @@
+//! Debounces detector events into blackout/restore decisions.
+//!
+//! A single noisy frame must not flicker the window. Each analysed frame casts
+//! one vote (threat or clear); a blackout engages once `threshold` of the last
+//! `window` frames saw a threat. It is then held for at least `min_hold` and
+//! only lifted after no threat has been seen for `restore_cooldown`.
+
+use crate::protocol::DetectionEvent;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Source of the current time, so the state machine can be driven by a fake
+/// clock.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HysteresisConfig {
+    /// Number of recent frames that vote (M).
+    pub window: usize,
+    /// Threat votes within the window needed to black out (N).
+    pub threshold: usize,
+    /// Minimum time a blackout stays up once engaged.
+    pub min_hold: Duration,
+    /// Time without any threat frame before the window is restored.
+    pub restore_cooldown: Duration,
+}
+
+impl Default for HysteresisConfig {
+    fn default() -> Self {
+        Self {
+            window: 5,
+            threshold: 3,
+            min_hold: Duration::from_secs(3),
+            restore_cooldown: Duration::from_secs(2),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Blackout,
+    Restore,
+}
+
+#[derive(Debug)]
+pub struct Hysteresis<C: Clock = SystemClock> {
+    config: HysteresisConfig,
+    clock: C,
+    /// `(frame, saw_threat)` for the most recent frames, oldest first.
+    votes: VecDeque<(u64, bool)>,
+    engaged_at: Option<Instant>,
+    last_threat_at: Option<Instant>,
+}
+
+impl Hysteresis<SystemClock> {
+    pub fn new(config: HysteresisConfig) -> Self {
+        Self::with_clock(config, SystemClock)
+    }
+}
+
+impl<C: Clock> Hysteresis<C> {
+    pub fn with_clock(config: HysteresisConfig, clock: C) -> Self {
+        Self {
+            votes: VecDeque::with_capacity(config.window),
+            config,
+            clock,
+            engaged_at: None,
+            last_threat_at: None,
+        }
+    }
+
+    pub fn is_blacked_out(&self) -> bool {
+        self.engaged_at.is_some()
+    }
+
+    /// When the current blackout will be lifted if no further threat is seen.
+    pub fn earliest_restore(&self) -> Option<Instant> {
+        let engaged_at = self.engaged_at?;
+        let hold_until = engaged_at + self.config.min_hold;
+        let cool_until = self
+            .last_threat_at
+            .map_or(engaged_at, |at| at + self.config.restore_cooldown);
+        Some(hold_until.max(cool_until))
+    }
+
+    /// Time left until the current blackout can be lifted, assuming no new
+    /// threat. `None` if nothing is blacked out or no frame has arrived since
+    /// a forced blackout.
+    pub fn restore_in(&self) -> Option<Duration> {
+        if self.votes.is_empty() {
+            return None;
+        }
+        let restore_at = self.earliest_restore()?;
+        Some(restore_at.saturating_duration_since(self.clock.now()))
+    }
+
+    /// Feeds one detector event and returns the resulting transition, if any.
+    pub fn observe(&mut self, event: &DetectionEvent) -> Option<Decision> {
+        match event {
+            // Heartbeats say nothing about the frame, only that time passed.
+            DetectionEvent::Heartbeat { .. } => self.tick(),
+            _ => self.vote(event.frame(), event.is_threat()),
+        }
+    }
+
+    /// Records whether `frame` saw a threat and returns the resulting
+    /// transition, if any.
+    pub fn vote(&mut self, frame: u64, threat: bool) -> Option<Decision> {
+        let now = self.clock.now();
+
+        match self.votes.back_mut() {
+            // Several detections in one frame still count as a single vote.
+            Some((last, vote)) if *last == frame => *vote |= threat,
+            _ => {
+                self.votes.push_back((frame, threat));
+                while self.votes.len() > self.config.window {
+                    self.votes.pop_front();
+                }
+            }
+        }
+
+        if threat {
+            self.last_threat_at = Some(now);
+        }
+
+        if !self.is_blacked_out() && self.threat_votes() >= self.config.threshold {
+            self.engaged_at = Some(now);
+            return Some(Decision::Blackout);
+        }
+
+        self.evaluate_restore(now)
+    }
+
+    /// Re-evaluates time-based release without a new event.
+    pub fn tick(&mut self) -> Option<Decision> {
+        let now = self.clock.now();
+        self.evaluate_restore(now)
+    }
+
+    /// Engages a blackout regardless of votes, e.g. because the detector died.
+    /// It is lifted by the same rules as a detected one, but only once a new
+    /// frame has been observed.
+    pub fn force_blackout(&mut self) -> Option<Decision> {
+        let now = self.clock.now();
+        self.votes.clear();
+        self.last_threat_at = Some(now);
+        if self.is_blacked_out() {
+            return None;
+        }
+        self.engaged_at = Some(now);
+        Some(Decision::Blackout)
+    }
+
+    /// Drops all history and lifts any blackout.
+    pub fn reset(&mut self) -> Option<Decision> {
+        self.votes.clear();
+        self.last_threat_at = None;
+        self.engaged_at.take().map(|_| Decision::Restore)
+    }
+
+    /// Number of frames in the window that saw a threat.
+    pub fn threat_votes(&self) -> usize {
+        self.votes.iter().filter(|(_, threat)| *threat).count()
+    }
+
+    fn evaluate_restore(&mut self, now: Instant) -> Option<Decision> {
+        let restore_at = self.earliest_restore()?;
+        // Without any frame since a forced blackout there is nothing to say
+        // the threat is gone.
+        if self.votes.is_empty() || now < restore_at || self.threat_votes() >= self.config.threshold {
+            return None;
+        }
+        self.engaged_at = None;
+        Some(Decision::Restore)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// A clock that only moves when told to, in milliseconds since `start`.
+    #[derive(Clone)]
+    struct FakeClock {
+        start: Instant,
+        elapsed: Rc<Cell<Duration>>,
+    }
+
+    impl FakeClock {
+        fn new() -> Self {
+            Self {
+                start: Instant::now(),
+                elapsed: Rc::default(),
+            }
+        }
+
+        fn set(&self, ms: u64) {
+            self.elapsed.set(Duration::from_millis(ms));
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            self.start + self.elapsed.get()
+        }
+    }
+
+    /// One recorded input: a frame's vote, or time passing without one.
+    #[derive(Clone, Copy)]
+    enum Step {
+        Threat(u64),
+        Clear(u64),
+        Tick,
+    }
+
+    use Step::{Clear, Threat, Tick};
+
+    fn hysteresis() -> (Hysteresis<FakeClock>, FakeClock) {
+        let clock = FakeClock::new();
+        (Hysteresis::with_clock(HysteresisConfig::default(), clock.clone()), clock)
+    }
+
+    /// Feeds `(ms, step)` pairs in order and returns each transition with the
+    /// time it happened.
+    fn replay(
+        hysteresis: &mut Hysteresis<FakeClock>,
+        clock: &FakeClock,
+        steps: &[(u64, Step)],
+    ) -> Vec<(u64, Decision)> {
+        steps
+            .iter()
+            .filter_map(|&(ms, step)| {
+                clock.set(ms);
+                let decision = match step {
+                    Threat(frame) => hysteresis.vote(frame, true),
+                    Clear(frame) => hysteresis.vote(frame, false),
+                    Tick => hysteresis.tick(),
+                };
+                decision.map(|decision| (ms, decision))
+            })
+            .collect()
+    }
+
+    /// One frame every 100 ms from `first`, threat or not as `pattern` says.
+    fn frames(first: u64, pattern: &str) -> Vec<(u64, Step)> {
+        pattern
+            .chars()
+            .enumerate()
+            .map(|(i, vote)| {
+                let frame = first + i as u64;
+                (frame * 100, if vote == 'T' { Threat(frame) } else { Clear(frame) })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn blacks_out_once_n_of_the_last_m_frames_saw_a_threat() {
+        let (mut hysteresis, clock) = hysteresis();
+        assert_eq!(replay(&mut hysteresis, &clock, &frames(0, "TCCT")), []);
+        assert_eq!(hysteresis.threat_votes(), 2);
+        assert_eq!(replay(&mut hysteresis, &clock, &frames(4, "T")), [(400, Decision::Blackout)]);
+        assert!(hysteresis.is_blacked_out());
+    }
+
+    #[test]
+    fn threats_spread_wider_than_the_window_never_black_out() {
+        let (mut hysteresis, clock) = hysteresis();
+        assert_eq!(replay(&mut hysteresis, &clock, &frames(0, "TCCTCCCTCCCCTCCCT")), []);
+        assert!(!hysteresis.is_blacked_out());
+    }
+
+    #[test]
+    fn detections_in_the_same_frame_are_one_vote() {
+        let (mut hysteresis, clock) = hysteresis();
+        let steps = [(0, Threat(7)), (10, Threat(7)), (20, Threat(7)), (30, Clear(7))];
+        assert_eq!(replay(&mut hysteresis, &clock, &steps), []);
+        assert_eq!(hysteresis.threat_votes(), 1);
+    }
+
+    #[test]
+    fn blackout_is_held_for_min_hold_after_the_threat_is_gone() {
+        let (mut hysteresis, clock) = hysteresis();
+        // Engaged at 200 ms, last threat then; cooldown ends at 2.2 s, the
+        // hold only at 3.2 s.
+        let mut steps = frames(0, "TTTCCCCC");
+        steps.extend([(2300, Tick), (3100, Tick), (3200, Tick), (3300, Tick)]);
+        assert_eq!(
+            replay(&mut hysteresis, &clock, &steps),
+            [(200, Decision::Blackout), (3200, Decision::Restore)]
+        );
+    }
+
+    #[test]
+    fn restore_waits_for_the_cooldown_after_the_last_threat() {
+        let (mut hysteresis, clock) = hysteresis();
+        // Threats keep coming until 4.9 s, past the hold.
+        let mut steps = frames(0, &"T".repeat(50));
+        steps.extend(frames(50, "CCCCC"));
+        steps.extend([(6800, Tick), (6899, Tick), (6900, Tick)]);
+        assert_eq!(
+            replay(&mut hysteresis, &clock, &steps),
+            [(200, Decision::Blackout), (6900, Decision::Restore)]
+        );
+        assert_eq!(hysteresis.restore_in(), None);
+    }
+
+    #[test]
+    fn restore_in_counts_down_to_the_later_of_hold_and_cooldown() {
+        let (mut hysteresis, clock) = hysteresis();
+        replay(&mut hysteresis, &clock, &frames(0, "TTT"));
+        assert_eq!(hysteresis.restore_in(), Some(Duration::from_millis(3000)));
+        clock.set(2000);
+        assert_eq!(hysteresis.restore_in(), Some(Duration::from_millis(1200)));
+        replay(&mut hysteresis, &clock, &[(3000, Threat(3))]);
+        assert_eq!(hysteresis.restore_in(), Some(Duration::from_millis(2000)));
+    }
+
+    #[test]
+    fn a_flickering_detection_blacks_out_once_and_stays_out() {
+        let (mut hysteresis, clock) = hysteresis();
+        let mut steps = frames(0, &"TC".repeat(40));
+        steps.extend(frames(80, &"C".repeat(30)));
+        // One blackout at the third threat, no restore while the detection
+        // keeps coming back, one restore 2 s after it finally stops.
+        assert_eq!(
+            replay(&mut hysteresis, &clock, &steps),
+            [(400, Decision::Blackout), (9800, Decision::Restore)]
+        );
+    }
+
+    #[test]
+    fn a_single_spurious_frame_delays_restore_but_does_not_re_engage() {
+        let (mut hysteresis, clock) = hysteresis();
+        let mut steps = frames(0, "TTT");
+        steps.extend(frames(3, &"C".repeat(27)));
+        steps.extend(frames(30, "T"));
+        steps.extend(frames(31, &"C".repeat(30)));
+        assert_eq!(
+            replay(&mut hysteresis, &clock, &steps),
+            [(200, Decision::Blackout), (5000, Decision::Restore)]
+        );
+    }
+
+    #[test]
+    fn heartbeats_only_let_time_pass() {
+        let (mut hysteresis, clock) = hysteresis();
+        replay(&mut hysteresis, &clock, &frames(0, "TTTCCCCC"));
+        clock.set(3200);
+        let heartbeat = DetectionEvent::Heartbeat {
+            frame: 7,
+            fps: 10.0,
+            timestamp: "2024-05-01T10:00:00".to_string(),
+        };
+        assert_eq!(hysteresis.observe(&heartbeat), Some(Decision::Restore));
+    }
+
+    #[test]
+    fn a_forced_blackout_waits_for_a_frame_before_restoring() {
+        let (mut hysteresis, clock) = hysteresis();
+        clock.set(0);
+        assert_eq!(hysteresis.force_blackout(), Some(Decision::Blackout));
+        assert_eq!(hysteresis.force_blackout(), None);
+        assert_eq!(replay(&mut hysteresis, &clock, &[(10_000, Tick)]), []);
+        assert_eq!(hysteresis.restore_in(), None);
+        assert_eq!(
+            replay(&mut hysteresis, &clock, &[(10_100, Clear(0))]),
+            [(10_100, Decision::Restore)]
+        );
+    }
+
+    #[test]
+    fn reset_lifts_the_blackout_and_forgets_the_votes() {
+        let (mut hysteresis, clock) = hysteresis();
+        replay(&mut hysteresis, &clock, &frames(0, "TTT"));
+        assert_eq!(hysteresis.reset(), Some(Decision::Restore));
+        assert_eq!(hysteresis.reset(), None);
+        assert_eq!(replay(&mut hysteresis, &clock, &frames(3, "TT")), []);
+    }
+}
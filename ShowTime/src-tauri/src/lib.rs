@@ -1,184 +1,195 @@
-use tauri::Manager;
-use std::process::{Command, Stdio};
-use std::thread;
-use std::sync::{Arc, Mutex};
-
-fn start_detector(app_handle: tauri::AppHandle) {
-    thread::spawn(move || {
-        let mut child = Command::new("python")
-            .arg("detector/detector.py") 
-            .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
-            .spawn()
-            .expect("Failed to start Python detector");
-
-        if let Some(stdout) = child.stdout.take() {
-            use std::io::{BufRead, BufReader};
-            let reader = BufReader::new(stdout);
-            let mut blackout_triggered = false;
-
-            for line in reader.lines() {
-                if let Ok(l) = line {
-                    let lower = l.to_lowercase();
-
-                    if (lower.contains("phone detected") || lower.contains("camera detected"))
-                        && !blackout_triggered
-                    {
-                        println!("🔴 {} - Triggering blackout...", l.trim());
-                        blackout_triggered = true;
-
-                        // Block the main Tauri window instead of creating a new one
-                        if let Some(window) = app_handle.get_webview_window("main") {
-                            // Method 1: Hide the window content by injecting CSS
-                            let _ = window.eval(
-                                r#"
-                                document.body.style.background = 'black';
-                                document.body.innerHTML = '<div style="position: fixed; top: 0; left: 0; width: 100vw; height: 100vh; background: black; z-index: 999999;"></div>';
-                                "#,
-                            );
-
-                            // Method 2: Additionally hide the window itself (optional)
-                            // let _ = window.hide();
-                        }
-                    } else if lower.contains("no phone detected") || lower.contains("no camera detected") {
-                        // Re-enable the window when threat is gone
-                        if blackout_triggered {
-                            println!("✅ {} - Restoring window...", l.trim());
-                            blackout_triggered = false;
-                            
-                            if let Some(window) = app_handle.get_webview_window("main") {
-                                // Reload the window to restore original content
-                                let _ = window.eval("window.location.reload()");
-                                // Or show the window again if it was hidden
-                                // let _ = window.show();
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        let _ = child.wait();
-    });
-}
-
-#[cfg(target_os = "windows")]
-fn is_virtual_machine() -> bool {
-    use std::process::Command;
-
-    if let Ok(output) = Command::new("wmic")
-        .args(["computersystem", "get", "model,manufacturer"])
-        .output()
-    {
-        let data = String::from_utf8_lossy(&output.stdout).to_lowercase();
-        return data.contains("virtual")
-            || data.contains("vmware")
-            || data.contains("vbox")
-            || data.contains("qemu")
-            || data.contains("kvm");
-    }
-    false
-}
-
-#[cfg(target_os = "macos")]
-fn is_virtual_machine() -> bool {
-    use std::process::Command;
-
-    if let Ok(output) = Command::new("sysctl")
-        .arg("hw.model")
-        .output()
-    {
-        let data = String::from_utf8_lossy(&output.stdout).to_lowercase();
-        return data.contains("virtual")
-            || data.contains("vmware")
-            || data.contains("vbox")
-            || data.contains("qemu")
-            || data.contains("parallels");
-    }
-    false
-}
-
-#[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
-}
-
-#[cfg(target_os = "windows")]
-fn block_capture(window: &tauri::WebviewWindow) {
-    use windows::Win32::Foundation::HWND;
-    use windows::Win32::UI::WindowsAndMessaging::{SetWindowDisplayAffinity, WDA_MONITOR};
-
-    if let Ok(hwnd) = window.hwnd() {
-        unsafe {
-            let _ = SetWindowDisplayAffinity(
-                HWND(hwnd.0 as *mut core::ffi::c_void),
-                WDA_MONITOR,
-            );
+use tauri::{Manager, RunEvent};
+
+pub mod audit;
+#[cfg(feature = "capture")]
+pub mod capture;
+pub mod commands;
+pub mod config;
+pub mod denial;
+pub mod detector;
+pub mod enforcement;
+pub mod environment;
+pub mod events;
+pub mod hysteresis;
+pub mod logs;
+pub mod metrics;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod overlay;
+pub mod pipeline;
+pub mod platform;
+pub mod policy;
+pub mod protection;
+pub mod protocol;
+pub mod recorders;
+pub mod registry;
+pub mod status;
+pub mod supervisor;
+pub mod watermark;
+
+use audit::{AuditEvent, AuditLog};
+use config::{DetectorConfig, ShowTimeConfig};
+use denial::DenialReason;
+use detector::{Detector, DetectorControl, Launch, PythonDetector, ReplayDetector};
+use enforcement::WindowSurface;
+use environment::{Admission, EnvironmentReport};
+use logs::DetectorLogs;
+use recorders::{RecorderScanner, SystemProcesses};
+use watermark::Watermark;
+
+/// `launch` is `None` for the replay backend, which validation guarantees a
+/// file.
+fn build_detector(
+    config: &DetectorConfig,
+    launch: Option<Launch>,
+    logs: DetectorLogs,
+    control: DetectorControl,
+) -> Box<dyn Detector> {
+    let python = match launch {
+        Some(Launch::Sidecar(executable)) => PythonDetector::frozen(executable),
+        Some(Launch::Script { interpreter, script }) => PythonDetector::new(interpreter, script),
+        #[cfg(feature = "onnx")]
+        Some(Launch::Model(model)) => {
+            let capture = capture::Capture::from_config(config);
+            return Box::new(detector::onnx::OnnxDetector::new(model, capture, config.onnx_settings()));
         }
-    }
-}
-
-#[cfg(target_os = "macos")]
-fn block_capture(window: &tauri::WebviewWindow) {
-    use cocoa::appkit::NSWindow;
-    use cocoa::base::id;
-    use cocoa::foundation::NSUInteger;
-
-    const NSWindowSharingNone: NSUInteger = 0;
-    unsafe {
-        if let Some(ns_window) = window.ns_window() {
-            let ns_window: id = ns_window as id;
-            ns_window.setSharingType_(NSWindowSharingNone);
-        }
-    }
-}
-
-// Alternative approach: Create a Tauri command that can be called from the detector
-#[tauri::command]
-fn trigger_blackout(app_handle: tauri::AppHandle) {
-    if let Some(window) = app_handle.get_webview_window("main") {
-        let _ = window.eval(
-            r#"
-            document.body.style.background = 'black';
-            document.body.innerHTML = '<div style="position: fixed; top: 0; left: 0; width: 100vw; height: 100vh; background: black; z-index: 999999; display: flex; align-items: center; justify-content: center; color: white; font-size: 24px;">ACCESS BLOCKED</div>';
-            "#,
-        );
-    }
-}
-
-#[tauri::command]
-fn restore_window(app_handle: tauri::AppHandle) {
-    if let Some(window) = app_handle.get_webview_window("main") {
-        let _ = window.eval("window.location.reload()");
-    }
+        #[cfg(not(feature = "onnx"))]
+        Some(Launch::Model(_)) => unreachable!("validation rejects the onnx backend without the onnx feature"),
+        None => return Box::new(ReplayDetector::new(config.replay_file.clone().unwrap_or_default())),
+    };
+    Box::new(python.args(config.script_args()).logs(logs).control(control))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    if is_virtual_machine() {
-        eprintln!("Blocked: Running inside a virtual machine is not allowed.");
-        std::process::exit(0);
-    }
-
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![greet, trigger_blackout, restore_window])
+        .invoke_handler(commands::handler())
+        .on_page_load(registry::on_page_load)
         .setup(|app| {
             let _ = app.remove_menu();
             
-            // Pass the app handle to the detector
-            start_detector(app.handle().clone());
+            let settings = ShowTimeConfig::load(
+                app.config().plugins.0.get("showtime"),
+                ShowTimeConfig::locate(app.path().app_config_dir().ok()).as_deref(),
+            )?;
+
+            let log_dir = match &settings.logging.directory {
+                Some(path) => path.clone(),
+                None => app.path().app_log_dir()?,
+            };
+            logs::init(&settings.logging, &log_dir)?;
+
+            let audit_path = match &settings.audit.path {
+                Some(path) => path.clone(),
+                None => app.path().app_data_dir()?.join(audit::AUDIT_FILE_NAME),
+            };
+            let audit_log = AuditLog::open(audit_path)?;
+
+            let report = EnvironmentReport::collect();
+            let admission = settings.environment.admit(&report);
+            audit_log.record(AuditEvent::EnvironmentCheck {
+                virtual_machine: report.is_virtual_machine(settings.environment.min_confidence),
+                allowed: admission.is_allowed(),
+            });
+            for signal in &report.signals {
+                tracing::info!(detail = %signal.detail, confidence = signal.confidence, "environment signal");
+            }
+            pipeline::manage(app, &settings, audit_log, WindowSurface::new(app.handle().clone()));
+            let watermark = app.state::<Watermark>();
+            app.state::<AuditLog>().record(AuditEvent::SessionStarted {
+                session: watermark.session().to_string(),
+                user: watermark.user().to_string(),
+            });
+
+            if let Some(addr) = settings.metrics.prometheus_addr {
+                let app_handle = app.handle().clone();
+                metrics::serve_prometheus(addr, move || app_handle.state::<metrics::Metrics>().render_prometheus())?;
+                tracing::info!(%addr, "serving Prometheus metrics");
+            }
+
+            // The sidecar is installed next to our executable, the script
+            // and its resources in the resource directory.
+            let exe_dir = tauri::utils::platform::current_exe()?
+                .parent()
+                .map(std::path::Path::to_path_buf)
+                .unwrap_or_default();
+            let launch = Launch::locate(
+                &settings.detector,
+                &exe_dir,
+                &app.path().resource_dir()?,
+                std::env::var_os("PATH"),
+            )
+            .transpose();
+
+            if admission == Admission::Warned {
+                tracing::warn!(confidence = report.confidence, "running inside a virtual machine");
+            }
+            let denial = if !admission.is_allowed() {
+                Some(DenialReason::VirtualMachine {
+                    confidence: report.confidence,
+                })
+            } else if let Err(err) = &launch {
+                Some(DenialReason::DetectorMissing {
+                    detail: err.to_string(),
+                })
+            } else if settings.recorders.enabled && !platform::EXCLUDES_CAPTURE {
+                let recorders = RecorderScanner::new(settings.recorders.denylist.clone(), SystemProcesses::new()).scan();
+                (!recorders.is_empty()).then_some(DenialReason::ScreenRecorder { names: recorders })
+            } else {
+                None
+            };
+
+            if let Some(reason) = denial {
+                tracing::error!(reason = %reason.message(), "blocked");
+                let support_code = match app.state::<AuditLog>().append(AuditEvent::AccessDenied {
+                    reason: reason.message(),
+                    exit_code: reason.exit_code(),
+                }) {
+                    Ok(record) => record.support_code(),
+                    Err(err) => {
+                        tracing::error!(error = %err, "failed to write audit record");
+                        "unavailable".to_string()
+                    }
+                };
+                denial::show(app.handle(), &reason, &support_code)?;
+                return Ok(());
+            }
 
-            if let Some(window) = app.get_webview_window("main") {
-                block_capture(&window);
-                
-                let _ = window.eval(
-                    r#"document.addEventListener('contextmenu', e => e.preventDefault());"#,
+            registry::watch(app.handle());
+            if app.state::<Watermark>().is_enabled() {
+                overlay::refresh_watermarks(app.handle().clone());
+            }
+            if settings.recorders.enabled {
+                pipeline::start_recorder_scan(
+                    app.handle().clone(),
+                    RecorderScanner::new(settings.recorders.denylist.clone(), SystemProcesses::new()),
+                    settings.recorders.scan_interval(),
                 );
             }
 
+            // Pass the app handle to the detector
+            app.manage(pipeline::start_detector(
+                app.handle().clone(),
+                build_detector(
+                    &settings.detector,
+                    launch.ok().flatten(),
+                    app.state::<DetectorLogs>().inner().clone(),
+                    app.state::<DetectorControl>().inner().clone(),
+                ),
+                settings.protection.supervisor(),
+            ));
+
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            // Don't leave the detector holding the camera after we are gone.
+            if let RunEvent::Exit = event {
+                if let Some(detector) = app_handle.try_state::<pipeline::DetectorHandle>() {
+                    detector.shutdown();
+                }
+            }
+        });
 }
\ No newline at end of file
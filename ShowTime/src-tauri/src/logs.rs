@@ -0,0 +1,209 @@
+//! Log output, and what the detector writes to stderr.
+//!
+//! [`init`] installs the `tracing` subscriber: output on stderr plus a daily
+//! rotated file in the log directory, which is the only place logs survive in
+//! release builds without a console. Both use the configured [`LogFormat`]
+//! and report how long each span took when it closes. With the `otel`
+//! feature spans can also be exported to an OTLP file.
+//!
+//! The detector's stderr is fed line by line into [`DetectorLogs`], which
+//! guesses a [`Severity`] for each line, forwards it to `tracing` under the
+//! `detector` target and keeps the most recent lines for the support screen.
+
+use crate::config::LoggingConfig;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer, Registry};
+
+/// File name prefix of the rotated log files, e.g. `showtime.2024-05-01.log`.
+pub const LOG_FILE_PREFIX: &str = "showtime";
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// How log lines are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Multi-line, human-readable output for development.
+    Pretty,
+    /// One JSON object per line, with the enclosing spans, for production.
+    Json,
+}
+
+impl Default for LogFormat {
+    fn default() -> Self {
+        if cfg!(debug_assertions) {
+            LogFormat::Pretty
+        } else {
+            LogFormat::Json
+        }
+    }
+}
+
+impl LogFormat {
+    fn layer<W>(self, writer: W, ansi: bool) -> BoxedLayer
+    where
+        W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+    {
+        let layer = fmt::layer()
+            .with_writer(writer)
+            .with_ansi(ansi)
+            .with_span_events(FmtSpan::CLOSE);
+        match self {
+            LogFormat::Pretty => layer.pretty().boxed(),
+            LogFormat::Json => layer.json().with_current_span(true).with_span_list(true).boxed(),
+        }
+    }
+}
+
+/// Installs the global subscriber, writing to stderr and to a log file in
+/// `directory` that rotates daily. Verbosity follows `RUST_LOG` and defaults
+/// to `info`.
+pub fn init(config: &LoggingConfig, directory: &Path) -> Result<(), Box<dyn Error>> {
+    let file = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(config.max_files)
+        .build(directory)?;
+
+    #[cfg(feature = "otel")]
+    let otel = config.otlp_file.as_deref().map(crate::otel::layer).transpose()?;
+    #[cfg(not(feature = "otel"))]
+    let otel: Option<tracing_subscriber::layer::Identity> = None;
+
+    let _ = tracing_subscriber::registry()
+        .with(vec![
+            config.format.layer(std::io::stderr, true),
+            config.format.layer(file, false),
+        ])
+        .with(otel)
+        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .try_init();
+
+    #[cfg(not(feature = "otel"))]
+    if let Some(path) = &config.otlp_file {
+        tracing::warn!(path = %path.display(), "OTLP export needs the otel feature; not exporting spans");
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Severity {
+    /// Severity named by the first word of `line`, as in Python's
+    /// `ERROR:root:...`, ultralytics' `WARNING ⚠️ ...` or the detector's own
+    /// `Error: Cannot open camera`.
+    fn from_prefix(line: &str) -> Option<Self> {
+        let word = line
+            .trim_start_matches('[')
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .next()?
+            .to_ascii_uppercase();
+        match word.as_str() {
+            "ERROR" | "CRITICAL" | "FATAL" | "EXCEPTION" => Some(Severity::Error),
+            "WARNING" | "WARN" => Some(Severity::Warn),
+            "INFO" => Some(Severity::Info),
+            "DEBUG" | "TRACE" => Some(Severity::Debug),
+            _ => None,
+        }
+    }
+}
+
+/// One line the detector wrote to stderr.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogLine {
+    pub timestamp: DateTime<Utc>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+struct Inner {
+    lines: VecDeque<LogLine>,
+    /// Inside a Python traceback, whose lines are all part of one error.
+    in_traceback: bool,
+}
+
+/// Recent detector stderr, shared between the thread reading it and the
+/// `get_detector_logs` command.
+#[derive(Clone)]
+pub struct DetectorLogs {
+    capacity: usize,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl DetectorLogs {
+    /// Keeps the last `capacity` lines.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Arc::new(Mutex::new(Inner {
+                lines: VecDeque::with_capacity(capacity),
+                in_traceback: false,
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Classifies and stores one stderr line and forwards it to `tracing`.
+    pub fn record(&self, line: &str) -> Severity {
+        let message = line.trim_end().to_string();
+        let mut inner = self.lock();
+
+        let severity = if message.starts_with("Traceback") {
+            inner.in_traceback = true;
+            Severity::Error
+        } else if inner.in_traceback {
+            // The first unindented line after the frames names the exception.
+            if !message.starts_with(char::is_whitespace) {
+                inner.in_traceback = false;
+            }
+            Severity::Error
+        } else {
+            Severity::from_prefix(&message).unwrap_or(Severity::Info)
+        };
+
+        match severity {
+            Severity::Error => tracing::error!(target: "detector", "{}", message),
+            Severity::Warn => tracing::warn!(target: "detector", "{}", message),
+            Severity::Info => tracing::info!(target: "detector", "{}", message),
+            Severity::Debug => tracing::debug!(target: "detector", "{}", message),
+        }
+
+        if self.capacity > 0 {
+            while inner.lines.len() >= self.capacity {
+                inner.lines.pop_front();
+            }
+            inner.lines.push_back(LogLine {
+                timestamp: Utc::now(),
+                severity,
+                message,
+            });
+        }
+        severity
+    }
+
+    /// Stored lines, oldest first.
+    pub fn lines(&self) -> Vec<LogLine> {
+        self.lock().lines.iter().cloned().collect()
+    }
+}
@@ -0,0 +1,285 @@
+//! In-process metrics, above all how long a phone in front of the camera
+//! takes to turn into a black screen.
+//!
+//! Every frame event is timed at three points:
+//!
+//! 1. capture, `captured_at` in the event, stamped by the detector,
+//! 2. receipt, when the event reaches the Rust side,
+//! 3. enforcement, once the overlay or page script has been handed to the
+//!    window. Webviews report no completion for either, so this is as close
+//!    as the app can see.
+//!
+//! [`Metrics`] keeps counters and latency histograms. They are read through
+//! the `get_metrics` command or, when configured, scraped in the Prometheus
+//! text format from [`serve_prometheus`] on a loopback address.
+
+use crate::policy::Action;
+use crate::protection::Enforcement;
+use crate::protocol::DetectionEvent;
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::{Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Histogram bucket upper bounds, in seconds.
+pub const LATENCY_BUCKETS: &[f64] = &[0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Bucket {
+    /// Upper bound in seconds.
+    pub le: f64,
+    /// Observations at or below `le`, including those of smaller buckets.
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Histogram {
+    pub buckets: Vec<Bucket>,
+    pub count: u64,
+    /// Sum of all observations in seconds.
+    pub sum: f64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: LATENCY_BUCKETS.iter().map(|&le| Bucket { le, count: 0 }).collect(),
+            count: 0,
+            sum: 0.0,
+        }
+    }
+
+    pub fn observe(&mut self, value: Duration) {
+        let seconds = value.as_secs_f64();
+        for bucket in self.buckets.iter_mut().filter(|bucket| seconds <= bucket.le) {
+            bucket.count += 1;
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+/// Point-in-time copy of all metrics, as returned by `get_metrics`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricsSnapshot {
+    /// Analysed frames, with or without a detection.
+    pub frames: u64,
+    /// Reported objects by label.
+    pub detections: BTreeMap<String, u64>,
+    /// Applied responses by action, plus `lift` for restores.
+    pub enforcements: BTreeMap<String, u64>,
+    pub blackouts: u64,
+    /// Capture to receipt.
+    pub detector_latency: Histogram,
+    /// Receipt to enforcement, for frames that triggered one.
+    pub enforcement_latency: Histogram,
+    /// Capture to enforcement, for frames that triggered a blackout.
+    pub blackout_latency: Histogram,
+}
+
+impl Default for MetricsSnapshot {
+    fn default() -> Self {
+        Self {
+            frames: 0,
+            detections: BTreeMap::new(),
+            enforcements: BTreeMap::new(),
+            blackouts: 0,
+            detector_latency: Histogram::new(),
+            enforcement_latency: Histogram::new(),
+            blackout_latency: Histogram::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    snapshot: MetricsSnapshot,
+    /// Last frame counted, so the detections of one frame count it once.
+    last_frame: Option<u64>,
+}
+
+/// Shared metrics, managed by the app and fed by the detector thread.
+#[derive(Default)]
+pub struct Metrics {
+    inner: Mutex<Inner>,
+}
+
+impl Metrics {
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Counts a frame event received at `received`, in the detector's local
+    /// time, and returns how long ago its frame was captured.
+    pub fn record_frame(&self, event: &DetectionEvent, received: NaiveDateTime) -> Option<Duration> {
+        let mut guard = self.lock();
+        let frame = match event {
+            DetectionEvent::Detection { frame, label, .. } => {
+                *guard.snapshot.detections.entry(label.clone()).or_default() += 1;
+                *frame
+            }
+            DetectionEvent::Clear { frame, .. } => *frame,
+            DetectionEvent::Heartbeat { .. } => return None,
+        };
+
+        let captured = event
+            .captured_at()
+            .and_then(|captured| captured.parse::<NaiveDateTime>().ok());
+        let latency = captured.and_then(|captured| (received - captured).to_std().ok());
+
+        // Several detections of one frame share a capture; only the first
+        // counts as a frame.
+        if guard.last_frame != Some(frame) {
+            guard.last_frame = Some(frame);
+            guard.snapshot.frames += 1;
+            if let Some(latency) = latency {
+                guard.snapshot.detector_latency.observe(latency);
+            }
+        }
+        latency
+    }
+
+    /// Counts an applied or lifted response, whatever caused it.
+    pub fn record_enforcement(&self, response: Enforcement) {
+        let inner = &mut self.lock().snapshot;
+        let name = match response {
+            Enforcement::Apply(action) => action.as_str(),
+            Enforcement::Lift => "lift",
+        };
+        *inner.enforcements.entry(name.to_string()).or_default() += 1;
+        if response == Enforcement::Apply(Action::Blackout) {
+            inner.blackouts += 1;
+        }
+    }
+
+    /// Times a response a frame triggered: `since_receipt` from receipt to
+    /// enforcement and `since_capture` from capture, when known.
+    pub fn record_latency(&self, response: Enforcement, since_receipt: Duration, since_capture: Option<Duration>) {
+        let inner = &mut self.lock().snapshot;
+        inner.enforcement_latency.observe(since_receipt);
+        if let (Enforcement::Apply(Action::Blackout), Some(since_capture)) = (response, since_capture) {
+            inner.blackout_latency.observe(since_capture);
+        }
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.lock().snapshot.clone()
+    }
+
+    /// All metrics in the Prometheus text exposition format.
+    pub fn render_prometheus(&self) -> String {
+        let snapshot = self.snapshot();
+        let mut out = String::new();
+
+        counter(&mut out, "showtime_frames_total", "Analysed frames.", [(None, snapshot.frames)]);
+        counter(
+            &mut out,
+            "showtime_detections_total",
+            "Reported objects by label.",
+            snapshot
+                .detections
+                .iter()
+                .map(|(label, count)| (Some(("label", label.as_str())), *count)),
+        );
+        counter(
+            &mut out,
+            "showtime_enforcements_total",
+            "Applied and lifted responses by action.",
+            snapshot
+                .enforcements
+                .iter()
+                .map(|(action, count)| (Some(("action", action.as_str())), *count)),
+        );
+        counter(&mut out, "showtime_blackouts_total", "Blackouts applied.", [(None, snapshot.blackouts)]);
+
+        histogram(
+            &mut out,
+            "showtime_detector_latency_seconds",
+            "Frame capture to receipt by the app.",
+            &snapshot.detector_latency,
+        );
+        histogram(
+            &mut out,
+            "showtime_enforcement_latency_seconds",
+            "Receipt of a frame to enforcement of the response it triggered.",
+            &snapshot.enforcement_latency,
+        );
+        histogram(
+            &mut out,
+            "showtime_blackout_latency_seconds",
+            "Frame capture to enforcement of the blackout it triggered.",
+            &snapshot.blackout_latency,
+        );
+        out
+    }
+}
+
+fn counter<'a, I>(out: &mut String, name: &str, help: &str, samples: I)
+where
+    I: IntoIterator<Item = (Option<(&'a str, &'a str)>, u64)>,
+{
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    for (label, value) in samples {
+        match label {
+            Some((key, label)) => {
+                let _ = writeln!(out, "{}{{{}=\"{}\"}} {}", name, key, escape(label), value);
+            }
+            None => {
+                let _ = writeln!(out, "{} {}", name, value);
+            }
+        }
+    }
+}
+
+fn histogram(out: &mut String, name: &str, help: &str, histogram: &Histogram) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} histogram", name);
+    for bucket in &histogram.buckets {
+        let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bucket.le, bucket.count);
+    }
+    let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count);
+    let _ = writeln!(out, "{}_sum {}", name, histogram.sum);
+    let _ = writeln!(out, "{}_count {}", name, histogram.count);
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Serves `GET /metrics` in the Prometheus text format on `addr` until the
+/// process exits. `metrics` yields the current rendering.
+pub fn serve_prometheus<F>(addr: SocketAddr, metrics: F) -> io::Result<JoinHandle<()>>
+where
+    F: Fn() -> String + Send + 'static,
+{
+    let listener = TcpListener::bind(addr)?;
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+
+            let mut request_line = String::new();
+            if BufReader::new(&stream).read_line(&mut request_line).is_err() {
+                continue;
+            }
+            let response = match request_line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["GET", "/metrics", ..] => {
+                    let body = metrics();
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                }
+                _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    }))
+}
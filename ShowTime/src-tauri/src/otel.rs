@@ -0,0 +1,72 @@
+//! OpenTelemetry export of spans to a local file (`otel` feature).
+//!
+//! Finished spans are written as OTLP/JSON `ExportTraceServiceRequest`s, one
+//! per line, which is the OpenTelemetry file exporter format. A collector's
+//! `otlpjsonfile` receiver, or a script, can read them later to study how
+//! long detections take to turn into blackouts.
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+use opentelemetry_proto::transform::common::tonic::ResourceAttributesWithSchema;
+use opentelemetry_proto::transform::trace::tonic::group_spans_by_resource_and_scope;
+use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider, SpanData, SpanExporter};
+use opentelemetry_sdk::Resource;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+#[derive(Debug)]
+struct OtlpFileExporter {
+    file: Mutex<File>,
+    resource: ResourceAttributesWithSchema,
+}
+
+impl SpanExporter for OtlpFileExporter {
+    async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        let request = ExportTraceServiceRequest {
+            resource_spans: group_spans_by_resource_and_scope(batch, &self.resource),
+        };
+        let mut line =
+            serde_json::to_vec(&request).map_err(|err| OTelSdkError::InternalFailure(err.to_string()))?;
+        line.push(b'\n');
+        self.file
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .write_all(&line)
+            .map_err(|err| OTelSdkError::InternalFailure(err.to_string()))
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        self.resource = resource.into();
+    }
+}
+
+/// Layer exporting every span to the OTLP/JSON file at `path`, appending if
+/// it exists.
+pub fn layer<S>(path: &Path) -> io::Result<OpenTelemetryLayer<S, SdkTracer>>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    // The simple processor writes each span as it ends, so nothing is lost
+    // when the app exits without flushing.
+    let provider = SdkTracerProvider::builder()
+        .with_resource(Resource::builder().with_service_name("showtime").build())
+        .with_simple_exporter(OtlpFileExporter {
+            file: Mutex::new(file),
+            resource: ResourceAttributesWithSchema::default(),
+        })
+        .build();
+    let tracer = provider.tracer("showtime");
+    opentelemetry::global::set_tracer_provider(provider);
+
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
@@ -0,0 +1,180 @@
+//! Overlays owned by the Rust side.
+//!
+//! Instead of rewriting the protected page, blackout shows a separate,
+//! undecorated, always-on-top window that covers the protected window and
+//! follows it around. The page underneath keeps its state, input to it is
+//! disabled while the overlay is up, and since the overlay is its own webview
+//! the page's scripts have no way to reach into it.
+//!
+//! The [`Watermark`] is drawn the same way, in a transparent overlay that
+//! lets clicks through and is always shown.
+//!
+//! Every window in the [`ProtectionRegistry`] has overlays of its own.
+
+use crate::registry::ProtectionRegistry;
+use crate::watermark::Watermark;
+use std::thread;
+use tauri::window::Color;
+use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, Runtime, WebviewUrl, WebviewWindow, WebviewWindowBuilder, WindowEvent};
+
+/// Prefix of overlay window labels, followed by the protected window's.
+pub const OVERLAY_PREFIX: &str = "blackout-";
+
+/// Prefix of watermark window labels, followed by the protected window's.
+pub const WATERMARK_PREFIX: &str = "watermark-";
+
+/// Label of the overlay covering the window labelled `protected`.
+pub fn overlay_label(protected: &str) -> String {
+    format!("{}{}", OVERLAY_PREFIX, protected)
+}
+
+/// Label of the watermark over the window labelled `protected`.
+pub fn watermark_label(protected: &str) -> String {
+    format!("{}{}", WATERMARK_PREFIX, protected)
+}
+
+/// Creates the hidden overlay for `main` and keeps it, and the watermark
+/// once there is one, glued to it.
+pub fn attach<R: Runtime>(app_handle: &AppHandle<R>, main: &WebviewWindow<R>) -> tauri::Result<WebviewWindow<R>> {
+    let label = main.label().to_string();
+    let overlay = WebviewWindowBuilder::new(
+        app_handle,
+        overlay_label(&label),
+        WebviewUrl::External("about:blank".parse().expect("valid URL")),
+    )
+    .title("showtime")
+    .background_color(Color(0, 0, 0, 255))
+    .decorations(false)
+    .resizable(false)
+    .always_on_top(true)
+    .skip_taskbar(true)
+    .focused(false)
+    .visible(false)
+    .parent(main)?
+    .build()?;
+
+    sync_geometry(main, &overlay);
+
+    let handle = app_handle.clone();
+    main.on_window_event(move |event| {
+        if let WindowEvent::Moved(_) | WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } = event {
+            let Some(main) = handle.get_webview_window(&label) else {
+                return;
+            };
+            for overlay in [overlay_label(&label), watermark_label(&label)] {
+                if let Some(overlay) = handle.get_webview_window(&overlay) {
+                    sync_geometry(&main, &overlay);
+                }
+            }
+        }
+    });
+
+    Ok(overlay)
+}
+
+/// Creates the watermark over `main`. It follows `main` once [`attach`] has
+/// run for it.
+pub fn attach_watermark<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    main: &WebviewWindow<R>,
+) -> tauri::Result<WebviewWindow<R>> {
+    let watermark = WebviewWindowBuilder::new(
+        app_handle,
+        watermark_label(main.label()),
+        WebviewUrl::External("about:blank".parse().expect("valid URL")),
+    )
+    .title("showtime")
+    .transparent(true)
+    .background_color(Color(0, 0, 0, 0))
+    .decorations(false)
+    .shadow(false)
+    .resizable(false)
+    .always_on_top(true)
+    .skip_taskbar(true)
+    .focused(false)
+    .parent(main)?
+    .build()?;
+    watermark.set_ignore_cursor_events(true)?;
+
+    sync_geometry(main, &watermark);
+    paint_watermark(&watermark, &app_handle.state::<Watermark>());
+    Ok(watermark)
+}
+
+/// Closes the overlays of the window labelled `protected`, once that window
+/// is gone.
+pub fn detach<R: Runtime>(app_handle: &AppHandle<R>, protected: &str) {
+    for label in [overlay_label(protected), watermark_label(protected)] {
+        if let Some(overlay) = app_handle.get_webview_window(&label) {
+            let _ = overlay.destroy();
+        }
+    }
+}
+
+/// Redraws every watermark at the configured interval, for as long as the
+/// app runs.
+pub fn refresh_watermarks<R: Runtime>(app_handle: AppHandle<R>) {
+    thread::spawn(move || {
+        let watermark = app_handle.state::<Watermark>();
+        loop {
+            thread::sleep(watermark.refresh_interval());
+            for label in app_handle.state::<ProtectionRegistry>().labels() {
+                if let Some(overlay) = app_handle.get_webview_window(&watermark_label(&label)) {
+                    paint_watermark(&overlay, &watermark);
+                }
+            }
+        }
+    });
+}
+
+fn paint_watermark<R: Runtime>(overlay: &WebviewWindow<R>, watermark: &Watermark) {
+    let _ = overlay.eval(&watermark.script(chrono::Local::now()));
+}
+
+/// Covers every protected window, optionally showing `message` on the
+/// overlays.
+pub fn show<R: Runtime>(app_handle: &AppHandle<R>, message: Option<&str>) {
+    let text = serde_json::to_string(message.unwrap_or("")).unwrap_or_default();
+    for label in app_handle.state::<ProtectionRegistry>().labels() {
+        let Some(overlay) = app_handle.get_webview_window(&overlay_label(&label)) else {
+            continue;
+        };
+        if let Some(main) = app_handle.get_webview_window(&label) {
+            sync_geometry(&main, &overlay);
+            let _ = main.set_enabled(false);
+        }
+        cover(&overlay, &text);
+    }
+}
+
+fn cover<R: Runtime>(overlay: &WebviewWindow<R>, text: &str) {
+    let _ = overlay.eval(&format!(
+        r#"
+        document.documentElement.style.background = 'black';
+        document.body.style.cssText = 'margin: 0; height: 100vh; display: flex; align-items: center; justify-content: center; color: white; font: 24px sans-serif; background: black;';
+        document.body.textContent = {};
+        "#,
+        text
+    ));
+    let _ = overlay.show();
+    let _ = overlay.set_always_on_top(true);
+}
+
+/// Removes the overlays and gives input back to the protected windows.
+pub fn hide<R: Runtime>(app_handle: &AppHandle<R>) {
+    for label in app_handle.state::<ProtectionRegistry>().labels() {
+        if let Some(overlay) = app_handle.get_webview_window(&overlay_label(&label)) {
+            let _ = overlay.hide();
+        }
+        if let Some(main) = app_handle.get_webview_window(&label) {
+            let _ = main.set_enabled(true);
+        }
+    }
+}
+
+fn sync_geometry<R: Runtime>(main: &WebviewWindow<R>, overlay: &WebviewWindow<R>) {
+    if let (Ok(position), Ok(size)) = (main.outer_position(), main.outer_size()) {
+        let _ = overlay.set_position(PhysicalPosition::new(position.x, position.y));
+        let _ = overlay.set_size(PhysicalSize::new(size.width, size.height));
+    }
+}
@@ -0,0 +1,321 @@
+//! Wiring between the detector, the protection state and the frontend.
+//!
+//! Everything here is generic over the Tauri [`Runtime`], so the app and the
+//! tests under `tauri::test`'s mock runtime run the same code. Enforcement
+//! goes through the managed [`Surface`].
+
+use crate::audit::{AuditEvent, AuditLog};
+use crate::config::ShowTimeConfig;
+use crate::detector::{ChildProcess, ControlCommand, Detector, DetectorControl, REQUEST_TIMEOUT};
+use crate::enforcement::{self, Surface};
+use crate::events;
+use crate::logs::DetectorLogs;
+use crate::metrics::Metrics;
+use crate::policy::Action;
+use crate::protection::Enforcement;
+use crate::protocol::{DetectionEvent, ProtocolError};
+use crate::recorders::RecorderScanner;
+use crate::registry::ProtectionRegistry;
+use crate::status::ProtectionState;
+use crate::supervisor::{self, DetectorStatus, FailurePolicy, Shutdown, SupervisorConfig};
+use crate::watermark::Watermark;
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Puts everything the commands and the detector loop rely on under the
+/// app's management.
+pub fn manage<R, M, S>(app: &M, settings: &ShowTimeConfig, audit_log: AuditLog, surface: S)
+where
+    R: Runtime,
+    M: Manager<R>,
+    S: Surface + 'static,
+{
+    app.manage(audit_log);
+    app.manage(ProtectionState::new(
+        settings.policy.clone(),
+        settings.protection.hysteresis(),
+        settings.protection.failure_policy,
+    ));
+    app.manage::<Box<dyn Surface>>(Box::new(surface));
+    app.manage(ProtectionRegistry::new(settings.protection.windows.clone()));
+    app.manage(DetectorLogs::new(settings.logging.detector_lines));
+    app.manage(DetectorControl::default());
+    app.manage(Metrics::default());
+    app.manage(Watermark::start(&settings.watermark));
+}
+
+/// Carries out `response`, records it and tells the frontend.
+pub fn respond<R: Runtime>(app_handle: &AppHandle<R>, response: Enforcement) {
+    let _span = tracing::info_span!("enforce", ?response).entered();
+    enforce(app_handle, response);
+    announce(app_handle, response);
+}
+
+/// Puts `response` on screen and nothing else.
+fn enforce<R: Runtime>(app_handle: &AppHandle<R>, response: Enforcement) {
+    let surface = app_handle.state::<Box<dyn Surface>>();
+    enforcement::enforce(&**surface, response);
+}
+
+/// Records `response`, once on screen, and tells the frontend.
+fn announce<R: Runtime>(app_handle: &AppHandle<R>, response: Enforcement) {
+    app_handle.state::<AuditLog>().record(match response {
+        Enforcement::Apply(action) => AuditEvent::Enforced { action },
+        Enforcement::Lift => AuditEvent::Restored,
+    });
+    app_handle.state::<Metrics>().record_enforcement(response);
+    events::blackout_changed(app_handle, &app_handle.state::<ProtectionState>().status());
+    tracing::info!(?response, "enforced");
+}
+
+/// Takes note of whether the detector reports itself paused and tells the
+/// frontend when that changes.
+pub fn detector_paused<R: Runtime>(app_handle: &AppHandle<R>, paused: bool) {
+    let protection = app_handle.state::<ProtectionState>();
+    if protection.status().paused == paused {
+        return;
+    }
+    match protection.pause(paused) {
+        Some(response) => {
+            tracing::warn!("detection paused, applying the failure policy");
+            respond(app_handle, response);
+        }
+        None => events::blackout_changed(app_handle, &protection.status()),
+    }
+}
+
+/// Runs `scanner` every `interval` on its own thread, for as long as the app
+/// runs, feeding changes in the running screen recorders into the managed
+/// [`ProtectionState`].
+pub fn start_recorder_scan<R: Runtime>(app_handle: AppHandle<R>, mut scanner: RecorderScanner, interval: Duration) {
+    thread::spawn(move || {
+        let mut running = Vec::new();
+        loop {
+            let found = scanner.scan();
+            if found != running {
+                let verdict = app_handle.state::<ProtectionState>().recorders(&found);
+                if found.is_empty() {
+                    tracing::info!(stopped = ?running, "screen recorders gone");
+                } else {
+                    tracing::warn!(names = ?found, action = ?verdict.action, "screen recorders running");
+                }
+                app_handle.state::<AuditLog>().record(AuditEvent::ScreenRecorders {
+                    names: found.clone(),
+                    action: verdict.action,
+                });
+                if let Some(response) = verdict.enforcement {
+                    respond(&app_handle, response);
+                }
+                running = found;
+            }
+            thread::sleep(interval);
+        }
+    });
+}
+
+/// The detector thread started by [`start_detector`], and the means to stop
+/// it.
+pub struct DetectorHandle {
+    shutdown: Shutdown,
+    process: Option<ChildProcess>,
+    control: Option<DetectorControl>,
+    grace: Duration,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl DetectorHandle {
+    /// Stops restarting the detector, asks its process to exit, over the
+    /// control channel if it is connected, kills it after the grace period
+    /// and waits for the detector thread to finish.
+    pub fn shutdown(&self) {
+        self.shutdown.request();
+        if let Some(process) = &self.process {
+            let asked = self
+                .control
+                .as_ref()
+                .is_some_and(|control| control.request(ControlCommand::Shutdown, REQUEST_TIMEOUT).is_ok());
+            let exited = if asked {
+                process.await_exit(self.grace)
+            } else {
+                process.terminate(self.grace)
+            };
+            match exited {
+                Ok(Some(status)) => tracing::info!(%status, "detector stopped"),
+                Ok(None) => {}
+                Err(err) => tracing::warn!(error = %err, "failed to stop detector"),
+            }
+        }
+        let thread = self.thread.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take();
+        if let Some(thread) = thread {
+            let _ = thread.join();
+        }
+    }
+
+    /// Waits for the detector thread, which ends when the supervisor gives
+    /// up.
+    pub fn join(self) -> thread::Result<()> {
+        match self.thread.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner()) {
+            Some(thread) => thread.join(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Runs `detector` under the supervisor on its own thread, feeding its events
+/// into the managed [`ProtectionState`]. The thread ends when the supervisor
+/// gives up or the returned handle shuts it down.
+pub fn start_detector<R: Runtime>(
+    app_handle: AppHandle<R>,
+    mut detector: Box<dyn Detector>,
+    config: SupervisorConfig,
+) -> DetectorHandle {
+    let shutdown = Shutdown::default();
+    let process = detector.process();
+    let control = detector.control();
+    if let Some(control) = &control {
+        let app_handle = app_handle.clone();
+        control.on_status(move |status| {
+            events::detector_control(&app_handle, status);
+            detector_paused(&app_handle, status.paused);
+        });
+    }
+    let grace = config.shutdown_grace;
+    let supervisor_shutdown = shutdown.clone();
+
+    let thread = thread::spawn(move || {
+        let audit_log = app_handle.state::<AuditLog>();
+        let protection = app_handle.state::<ProtectionState>();
+        let metrics = app_handle.state::<Metrics>();
+
+        // Labels and actions audited since the camera last saw nothing, so a
+        // threat is recorded when it shows up rather than on every frame.
+        let mut in_view: Vec<(String, Action)> = Vec::new();
+
+        let on_event = |event: Result<DetectionEvent, ProtocolError>| {
+            let received = Instant::now();
+            let received_at = chrono::Local::now().naive_local();
+            let event = match event {
+                Ok(event) => event,
+                Err(err) => {
+                    tracing::warn!(error = %err, "rejected detector line");
+                    return;
+                }
+            };
+
+            let span = match &event {
+                DetectionEvent::Detection {
+                    frame,
+                    label,
+                    confidence,
+                    ..
+                } => tracing::info_span!("detection", frame, %label, confidence, action = tracing::field::Empty),
+                _ => tracing::Span::none(),
+            };
+            let _span = span.enter();
+
+            let capture_to_receipt = metrics.record_frame(&event, received_at);
+            let (verdict, detection) = protection.observe(&event);
+            if let Some(detection) = &detection {
+                span.record("action", tracing::field::debug(verdict.action));
+                if verdict.action == Action::LogOnly {
+                    tracing::info!(label = %detection.label, confidence = detection.confidence, "detected, logging only");
+                }
+                events::detection(&app_handle, detection);
+            }
+
+            match verdict.enforcement {
+                Some(Enforcement::Apply(action)) => {
+                    if let Some(detection) = &detection {
+                        tracing::info!(label = %detection.label, confidence = detection.confidence, ?action, "threat confirmed");
+                    }
+                }
+                Some(Enforcement::Lift) => {
+                    // Re-enable the window when threat is gone
+                    tracing::info!(frame = event.frame(), "threat gone, restoring window");
+                }
+                None => {}
+            }
+            // The latency ends on screen. Auditing waits for the disk, so it
+            // comes after.
+            if let Some(response) = verdict.enforcement {
+                let _span = tracing::info_span!("enforce", ?response).entered();
+                enforce(&app_handle, response);
+                let since_receipt = received.elapsed();
+                let since_capture = capture_to_receipt.map(|latency| latency + since_receipt);
+                metrics.record_latency(response, since_receipt, since_capture);
+                tracing::info!(
+                    ?response,
+                    since_receipt_ms = since_receipt.as_secs_f64() * 1000.0,
+                    since_capture_ms = since_capture.map(|latency| latency.as_secs_f64() * 1000.0),
+                    "enforcement latency"
+                );
+                announce(&app_handle, response);
+            }
+
+            match &detection {
+                Some(detection) => {
+                    let seen = (detection.label.clone(), verdict.action);
+                    let new = !in_view.contains(&seen);
+                    if new || verdict.enforcement.is_some() {
+                        audit_log.record(AuditEvent::Detection {
+                            frame: detection.frame,
+                            label: detection.label.clone(),
+                            confidence: detection.confidence,
+                            action: verdict.action,
+                        });
+                    }
+                    if new {
+                        in_view.push(seen);
+                    }
+                }
+                None if matches!(event, DetectionEvent::Clear { .. }) => in_view.clear(),
+                None => {}
+            }
+        };
+
+        let report = |status: &DetectorStatus| {
+            match status {
+                DetectorStatus::Stalled { silent_ms } => {
+                    tracing::error!(silent_ms, "detector stalled");
+                    audit_log.record(AuditEvent::DetectorStalled { silent_ms: *silent_ms });
+                }
+                DetectorStatus::Crashed { crashes, reason, retry_in_ms } => {
+                    tracing::error!(crashes, %reason, retry_in_ms, "detector down");
+                    audit_log.record(AuditEvent::DetectorCrashed {
+                        crashes: *crashes,
+                        reason: reason.clone(),
+                    });
+                }
+                DetectorStatus::GaveUp { crashes, reason } => {
+                    tracing::error!(crashes, %reason, "detector down, giving up");
+                    audit_log.record(AuditEvent::DetectorGaveUp {
+                        crashes: *crashes,
+                        reason: reason.clone(),
+                    });
+                }
+                _ => tracing::info!(?status, "detector status"),
+            }
+            events::detector_status(&app_handle, status);
+
+            if let Some(response) = protection.detector_status(status) {
+                match config.failure_policy {
+                    FailurePolicy::FailClosed => tracing::warn!("detector down, failing closed"),
+                    FailurePolicy::FailOpen => tracing::warn!("detector down, failing open"),
+                }
+                respond(&app_handle, response);
+            }
+        };
+
+        supervisor::supervise(&config, detector.as_mut(), &supervisor_shutdown, on_event, report);
+    });
+
+    DetectorHandle {
+        shutdown,
+        process,
+        control,
+        grace,
+        thread: Mutex::new(Some(thread)),
+    }
+}
@@ -0,0 +1,48 @@
+//! Per-OS capture protection and raw environment probes.
+//!
+//! Windows and macOS can exclude a window from screen capture outright.
+//! Linux has no such API under X11 or Wayland, so there protection is best
+//! effort: the app refuses to start while a known screen recorder runs.
+//! Every other target gets a fallback that does nothing, so the crate builds
+//! everywhere.
+
+use std::collections::BTreeMap;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+mod fallback;
+
+#[cfg(target_os = "linux")]
+use linux as imp;
+#[cfg(target_os = "macos")]
+use macos as imp;
+#[cfg(target_os = "windows")]
+use windows as imp;
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+use fallback as imp;
+
+/// Firmware vendor and product fields, keyed by field name. What is
+/// available differs per OS; see [`crate::environment::Facts::firmware`].
+pub fn firmware() -> BTreeMap<String, String> {
+    imp::firmware()
+}
+
+/// OS-specific evidence of running under a hypervisor.
+pub fn hypervisor_markers() -> Vec<String> {
+    imp::hypervisor_markers()
+}
+
+/// Keeps `window` out of screenshots and screen recordings where the
+/// platform allows it.
+pub fn block_capture<R: tauri::Runtime>(window: &tauri::WebviewWindow<R>) {
+    imp::block_capture(window)
+}
+
+/// Whether [`block_capture`] is enforced by the OS. Where it is not, the app
+/// refuses to start while a screen recorder runs.
+pub const EXCLUDES_CAPTURE: bool = cfg!(any(target_os = "windows", target_os = "macos"));
@@ -0,0 +1,13 @@
+use std::collections::BTreeMap;
+
+pub fn firmware() -> BTreeMap<String, String> {
+    BTreeMap::new()
+}
+
+pub fn hypervisor_markers() -> Vec<String> {
+    Vec::new()
+}
+
+pub fn block_capture<R: tauri::Runtime>(_window: &tauri::WebviewWindow<R>) {
+    tracing::warn!("screen capture protection is not available on this platform");
+}
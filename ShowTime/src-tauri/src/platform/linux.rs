@@ -0,0 +1,46 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// DMI fields exported under /sys/class/dmi/id.
+const DMI_FIELDS: &[&str] = &["sys_vendor", "product_name", "product_version", "board_vendor", "bios_vendor"];
+
+pub fn firmware() -> BTreeMap<String, String> {
+    DMI_FIELDS
+        .iter()
+        .filter_map(|field| {
+            let value = fs::read_to_string(Path::new("/sys/class/dmi/id").join(field)).ok()?;
+            let value = value.trim();
+            (!value.is_empty()).then(|| (field.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// The kernel interfaces `systemd-detect-virt` falls back on when neither
+/// DMI nor CPUID give an answer, e.g. Xen PV guests and ARM boards.
+pub fn hypervisor_markers() -> Vec<String> {
+    let mut markers = Vec::new();
+    if let Ok(kind) = fs::read_to_string("/sys/hypervisor/type") {
+        if !kind.trim().is_empty() {
+            markers.push(format!("/sys/hypervisor/type is {}", kind.trim()));
+        }
+    }
+    for path in ["/proc/xen", "/proc/device-tree/hypervisor/compatible"] {
+        if Path::new(path).exists() {
+            markers.push(format!("{} exists", path));
+        }
+    }
+    markers
+}
+
+/// Neither X11 nor Wayland lets a client keep itself out of captures, so
+/// protection falls back on refusing to start while a recorder from the
+/// [`crate::recorders`] denylist runs.
+pub fn block_capture<R: tauri::Runtime>(_window: &tauri::WebviewWindow<R>) {
+    let session = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        "Wayland"
+    } else {
+        "X11"
+    };
+    tracing::warn!(session, "windows cannot be excluded from screen capture, relying on recorder checks");
+}
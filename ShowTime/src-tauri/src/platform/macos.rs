@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::ptr;
+
+pub fn firmware() -> BTreeMap<String, String> {
+    sysctl_string("hw.model")
+        .map(|model| BTreeMap::from([("hw.model".to_string(), model)]))
+        .unwrap_or_default()
+}
+
+/// `kern.hv_vmm_present` is set by the kernel when it runs as a guest,
+/// including on Apple silicon where there is no CPUID.
+pub fn hypervisor_markers() -> Vec<String> {
+    match sysctl_int("kern.hv_vmm_present") {
+        Some(value) if value != 0 => vec!["kern.hv_vmm_present is set".to_string()],
+        _ => Vec::new(),
+    }
+}
+
+fn sysctl_string(name: &str) -> Option<String> {
+    let name = CString::new(name).ok()?;
+    let mut size = 0usize;
+    unsafe {
+        if libc::sysctlbyname(name.as_ptr(), ptr::null_mut(), &mut size, ptr::null_mut(), 0) != 0 {
+            return None;
+        }
+        let mut buffer = vec![0u8; size];
+        if libc::sysctlbyname(name.as_ptr(), buffer.as_mut_ptr().cast(), &mut size, ptr::null_mut(), 0) != 0 {
+            return None;
+        }
+        buffer.truncate(size);
+        let value = String::from_utf8_lossy(&buffer);
+        Some(value.trim_end_matches('\0').trim().to_string())
+    }
+}
+
+fn sysctl_int(name: &str) -> Option<i32> {
+    let name = CString::new(name).ok()?;
+    let mut value: i32 = 0;
+    let mut size = std::mem::size_of::<i32>();
+    let status = unsafe {
+        libc::sysctlbyname(
+            name.as_ptr(),
+            (&mut value as *mut i32).cast(),
+            &mut size,
+            ptr::null_mut(),
+            0,
+        )
+    };
+    (status == 0).then_some(value)
+}
+
+pub fn block_capture<R: tauri::Runtime>(window: &tauri::WebviewWindow<R>) {
+    use cocoa::appkit::NSWindow;
+    use cocoa::base::id;
+    use cocoa::foundation::NSUInteger;
+
+    const NS_WINDOW_SHARING_NONE: NSUInteger = 0;
+    unsafe {
+        if let Ok(ns_window) = window.ns_window() {
+            let ns_window: id = ns_window as id;
+            ns_window.setSharingType_(NS_WINDOW_SHARING_NONE);
+        }
+    }
+}
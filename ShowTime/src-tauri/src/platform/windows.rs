@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+/// Value names under `HKLM\HARDWARE\DESCRIPTION\System\BIOS`, the same
+/// SMBIOS strings Linux exposes as DMI.
+const BIOS_VALUES: &[&str] = &[
+    "SystemManufacturer",
+    "SystemProductName",
+    "SystemVersion",
+    "BaseBoardManufacturer",
+    "BIOSVendor",
+];
+
+pub fn firmware() -> BTreeMap<String, String> {
+    BIOS_VALUES
+        .iter()
+        .filter_map(|name| Some((name.to_string(), read_bios_value(name)?)))
+        .filter(|(_, value)| !value.is_empty())
+        .collect()
+}
+
+fn read_bios_value(name: &str) -> Option<String> {
+    use windows::core::{w, HSTRING};
+    use windows::Win32::System::Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_SZ};
+
+    let mut buffer = [0u16; 256];
+    let mut size = (buffer.len() * 2) as u32;
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            w!(r"HARDWARE\DESCRIPTION\System\BIOS"),
+            &HSTRING::from(name),
+            RRF_RT_REG_SZ,
+            None,
+            Some(buffer.as_mut_ptr().cast()),
+            Some(&mut size),
+        )
+    };
+    if status.is_err() {
+        return None;
+    }
+    // `size` is in bytes and includes the terminating NUL.
+    let len = (size as usize / 2).saturating_sub(1);
+    Some(String::from_utf16_lossy(&buffer[..len]).trim().to_string())
+}
+
+pub fn hypervisor_markers() -> Vec<String> {
+    Vec::new()
+}
+
+pub fn block_capture<R: tauri::Runtime>(window: &tauri::WebviewWindow<R>) {
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::WindowsAndMessaging::{SetWindowDisplayAffinity, WDA_MONITOR};
+
+    if let Ok(hwnd) = window.hwnd() {
+        unsafe {
+            let _ = SetWindowDisplayAffinity(
+                HWND(hwnd.0 as *mut core::ffi::c_void),
+                WDA_MONITOR,
+            );
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Maps detections to responses.
+//!
+//! A [`PolicyTable`] is a list of rules, each matching a set of labels and a
+//! confidence band. When several rules match a detection the most severe
+//! action wins; when none match the table's default applies.
+//!
+//! ```toml
+//! [policy]
+//! default_action = "log_only"
+//!
+//! [[policy.rules]]
+//! labels = ["cell phone", "phone", "mobile"]
+//! min_confidence = 0.7
+//! action = "blackout"
+//!
+//! [[policy.rules]]
+//! labels = ["cell phone", "phone", "mobile"]
+//! min_confidence = 0.5
+//! max_confidence = 0.7
+//! action = "blur"
+//! ```
+
+use crate::protocol::{CAMERA_LABELS, PHONE_LABELS};
+use crate::recorders::RECORDER_LABEL;
+use serde::{Deserialize, Serialize};
+
+/// Response to a detection, ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Record the detection, change nothing on screen.
+    LogOnly,
+    /// Show a warning banner over the page.
+    Warning,
+    /// Pause any audio or video on the page.
+    PauseMedia,
+    /// Stamp a watermark over the page.
+    Watermark,
+    /// Blur the page content.
+    Blur,
+    /// Cover the window completely.
+    Blackout,
+}
+
+impl Action {
+    /// Name as used in configuration, e.g. `pause_media`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Action::LogOnly => "log_only",
+            Action::Warning => "warning",
+            Action::PauseMedia => "pause_media",
+            Action::Watermark => "watermark",
+            Action::Blur => "blur",
+            Action::Blackout => "blackout",
+        }
+    }
+
+    /// Whether this action changes what is on screen.
+    pub fn is_enforcing(self) -> bool {
+        self != Action::LogOnly
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    /// Labels this rule applies to. Empty matches every label.
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub min_confidence: f32,
+    #[serde(default = "full_confidence")]
+    pub max_confidence: f32,
+    pub action: Action,
+}
+
+fn full_confidence() -> f32 {
+    1.0
+}
+
+impl PolicyRule {
+    pub fn matches(&self, label: &str, confidence: f32) -> bool {
+        let label_matches =
+            self.labels.is_empty() || self.labels.iter().any(|l| l.eq_ignore_ascii_case(label));
+        label_matches && confidence >= self.min_confidence && confidence <= self.max_confidence
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyTable {
+    pub default_action: Action,
+    pub rules: Vec<PolicyRule>,
+}
+
+impl Default for PolicyTable {
+    /// Blacks out for every default target label, as the detector always did,
+    /// and for screen recorders.
+    fn default() -> Self {
+        Self {
+            default_action: Action::LogOnly,
+            rules: vec![PolicyRule {
+                labels: PHONE_LABELS
+                    .iter()
+                    .chain(CAMERA_LABELS)
+                    .chain(&[RECORDER_LABEL])
+                    .map(|label| label.to_string())
+                    .collect(),
+                min_confidence: 0.0,
+                max_confidence: 1.0,
+                action: Action::Blackout,
+            }],
+        }
+    }
+}
+
+impl PolicyTable {
+    pub fn evaluate(&self, label: &str, confidence: f32) -> Action {
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(label, confidence))
+            .map(|rule| rule.action)
+            .max()
+            .unwrap_or(self.default_action)
+    }
+
+    /// Rejects rules whose confidence band can never match.
+    pub fn validate(&self) -> Result<(), String> {
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !(0.0..=1.0).contains(&rule.min_confidence)
+                || !(0.0..=1.0).contains(&rule.max_confidence)
+                || rule.min_confidence > rule.max_confidence
+            {
+                return Err(format!(
+                    "policy rule {} has an invalid confidence band {}..={}",
+                    index, rule.min_confidence, rule.max_confidence
+                ));
+            }
+        }
+        Ok(())
+    }
+}
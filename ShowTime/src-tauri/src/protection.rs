@@ -0,0 +1,242 @@
+//! Turns detector events into enforcement decisions.
+//!
+//! [`Protection`] combines the [`PolicyTable`] (what to do about a detection)
+//! with [`Hysteresis`] (when to do it). It is pure state: callers feed it
+//! events and carry out the [`Enforcement`] it returns.
+//!
+//! Escalating a response already in force is debounced the same way as
+//! engaging one: `threshold` of the last `window` frames must call for
+//! something more severe, and the worst of them is applied.
+//!
+//! Running screen recorders are not debounced: a process is either running
+//! or not, so their response holds exactly as long as they do.
+
+use crate::hysteresis::{Clock, Decision, Hysteresis, HysteresisConfig, SystemClock};
+use crate::policy::{Action, PolicyTable};
+use crate::protocol::DetectionEvent;
+use crate::recorders::RECORDER_LABEL;
+use crate::supervisor::FailurePolicy;
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Enforcement {
+    /// Put `Action` in place, on top of anything already applied.
+    Apply(Action),
+    /// Remove every applied action.
+    Lift,
+}
+
+/// What [`Protection::observe`] made of one event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Verdict {
+    /// Policy action for the event itself, `LogOnly` for clear frames and
+    /// heartbeats.
+    pub action: Action,
+    pub enforcement: Option<Enforcement>,
+}
+
+#[derive(Debug)]
+pub struct Protection<C: Clock = SystemClock> {
+    policy: PolicyTable,
+    hysteresis: Hysteresis<C>,
+    /// Most severe action seen since the vote window was last clear.
+    peak: Option<Action>,
+    /// `(frame, action)` for the frames in the vote window, oldest first.
+    recent: VecDeque<(u64, Action)>,
+    window: usize,
+    threshold: usize,
+    active: Option<Action>,
+    /// Set by a manual blackout, which only [`Protection::release`] lifts.
+    pinned: bool,
+    /// Action called for by running screen recorders, held until they exit.
+    recorders: Option<Action>,
+}
+
+impl Protection<SystemClock> {
+    pub fn new(policy: PolicyTable, hysteresis: HysteresisConfig) -> Self {
+        Self::with_clock(policy, hysteresis, SystemClock)
+    }
+}
+
+impl<C: Clock> Protection<C> {
+    pub fn with_clock(policy: PolicyTable, hysteresis: HysteresisConfig, clock: C) -> Self {
+        Self {
+            policy,
+            peak: None,
+            recent: VecDeque::with_capacity(hysteresis.window),
+            window: hysteresis.window,
+            threshold: hysteresis.threshold,
+            hysteresis: Hysteresis::with_clock(hysteresis, clock),
+            active: None,
+            pinned: false,
+            recorders: None,
+        }
+    }
+
+    /// The action currently in force, if any.
+    pub fn active(&self) -> Option<Action> {
+        self.active
+    }
+
+    pub fn hysteresis(&self) -> &Hysteresis<C> {
+        &self.hysteresis
+    }
+
+    /// Whether a manual blackout is in force.
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// Whether the detector side still calls for protection: a detected or
+    /// forced response is engaged, recent frames saw a threat or a screen
+    /// recorder runs. A manual blackout on its own does not count.
+    pub fn threat_active(&self) -> bool {
+        self.hysteresis.is_blacked_out() || self.hysteresis.threat_votes() > 0 || self.recorders.is_some()
+    }
+
+    /// Time left until the active response is lifted, if it is on a timer.
+    pub fn restore_in(&self) -> Option<std::time::Duration> {
+        if self.pinned {
+            return None;
+        }
+        self.active.and(self.hysteresis.restore_in())
+    }
+
+    pub fn observe(&mut self, event: &DetectionEvent) -> Verdict {
+        let action = match event {
+            DetectionEvent::Detection { label, confidence, .. } => self.policy.evaluate(label, *confidence),
+            DetectionEvent::Clear { .. } => Action::LogOnly,
+            DetectionEvent::Heartbeat { .. } => {
+                return Verdict {
+                    action: Action::LogOnly,
+                    enforcement: self.tick(),
+                }
+            }
+        };
+        let threat = action.is_enforcing();
+        if threat {
+            self.peak = self.peak.max(Some(action));
+        }
+        self.remember(event.frame(), action);
+
+        let decision = self.hysteresis.vote(event.frame(), threat);
+        if self.hysteresis.threat_votes() == 0 && self.active.is_none() {
+            self.peak = None;
+        }
+
+        let enforcement = match decision {
+            Some(Decision::Blackout) => self.engage(self.peak.unwrap_or(action)),
+            Some(Decision::Restore) => self.lift(),
+            // Already engaged and something worse keeps showing up.
+            None if threat && self.active.is_some() => self.escalate(),
+            None => None,
+        };
+
+        Verdict { action, enforcement }
+    }
+
+    /// Takes note of the screen recorders running now, `running` being empty
+    /// once they have all exited. Whatever is in force stays until then,
+    /// even if the camera side would lift it.
+    pub fn recorders(&mut self, running: &[String]) -> Verdict {
+        let action = if running.is_empty() {
+            Action::LogOnly
+        } else {
+            self.policy.evaluate(RECORDER_LABEL, 1.0)
+        };
+        self.recorders = action.is_enforcing().then_some(action);
+
+        let enforcement = match self.recorders {
+            Some(action) => self.engage(action),
+            None if !self.hysteresis.is_blacked_out() => self.lift(),
+            None => None,
+        };
+        Verdict { action, enforcement }
+    }
+
+    /// Re-evaluates time-based release without a new event.
+    pub fn tick(&mut self) -> Option<Enforcement> {
+        match self.hysteresis.tick() {
+            Some(Decision::Restore) => self.lift(),
+            _ => None,
+        }
+    }
+
+    /// Applies the configured failure policy while the detector is down.
+    pub fn detector_down(&mut self, policy: FailurePolicy) -> Option<Enforcement> {
+        match policy {
+            FailurePolicy::FailClosed => {
+                self.hysteresis.force_blackout();
+                self.recent.clear();
+                self.engage(Action::Blackout)
+            }
+            FailurePolicy::FailOpen => {
+                self.hysteresis.reset();
+                self.recent.clear();
+                self.lift()
+            }
+        }
+    }
+
+    /// Blacks out until [`Protection::release`] is called, whatever the
+    /// detector reports in the meantime.
+    pub fn pin(&mut self) -> Option<Enforcement> {
+        self.pinned = true;
+        self.engage(Action::Blackout)
+    }
+
+    /// Drops a manual blackout together with any detected threat history.
+    pub fn release(&mut self) -> Option<Enforcement> {
+        self.pinned = false;
+        self.hysteresis.reset();
+        self.recent.clear();
+        self.lift()
+    }
+
+    /// Keeps the action for `frame` within the vote window, the most severe
+    /// one if the frame had several detections.
+    fn remember(&mut self, frame: u64, action: Action) {
+        match self.recent.back_mut() {
+            Some((last, worst)) if *last == frame => *worst = (*worst).max(action),
+            _ => {
+                self.recent.push_back((frame, action));
+                while self.recent.len() > self.window {
+                    self.recent.pop_front();
+                }
+            }
+        }
+    }
+
+    /// Applies the worst action of the recent frames once `threshold` of
+    /// them called for more than is in force.
+    fn escalate(&mut self) -> Option<Enforcement> {
+        let active = self.active?;
+        let worse: Vec<Action> = self
+            .recent
+            .iter()
+            .map(|&(_, action)| action)
+            .filter(|&action| action > active)
+            .collect();
+        if worse.len() < self.threshold {
+            return None;
+        }
+        self.engage(worse.into_iter().max()?)
+    }
+
+    /// Puts `action` in force unless something at least as severe already is.
+    fn engage(&mut self, action: Action) -> Option<Enforcement> {
+        if self.active.is_some_and(|active| active >= action) {
+            return None;
+        }
+        self.active = Some(action);
+        Some(Enforcement::Apply(action))
+    }
+
+    fn lift(&mut self) -> Option<Enforcement> {
+        self.peak = None;
+        if self.pinned || self.recorders.is_some() {
+            return None;
+        }
+        self.active.take().map(|_| Enforcement::Lift)
+    }
+}
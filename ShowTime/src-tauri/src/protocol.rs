@@ -0,0 +1,261 @@
+//! Line protocol spoken by the detector on stdout.
+//!
+//! Every line is one JSON object carrying the protocol version in `v` and the
+//! event kind in `type`, e.g.
+//!
+//! ```text
+//! {"v":2,"type":"detection","frame":42,"label":"cell phone","confidence":0.91,"timestamp":"2024-05-01T10:00:00.123456","captured_at":"2024-05-01T10:00:00.071020"}
+//! {"v":2,"type":"clear","frame":44,"timestamp":"2024-05-01T10:00:00.190012","captured_at":"2024-05-01T10:00:00.140377"}
+//! {"v":2,"type":"heartbeat","frame":45,"fps":29.7,"timestamp":"2024-05-01T10:00:01.002311"}
+//! ```
+//!
+//! `timestamp` is when the frame was analysed and the optional `captured_at`
+//! when it was read from the camera, both in the detector's local time.
+//!
+//! Heartbeats arrive at a fixed interval whether or not frames are analysed,
+//! so a detector that stops sending them is considered stalled.
+//!
+//! Anything that does not parse into a [`DetectionEvent`] of the expected
+//! version is rejected rather than guessed at.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Version of the line protocol this build understands.
+///
+/// Version 2 added heartbeats. A version 1 detector never sends them and
+/// would look stalled, so its lines are rejected instead.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Default target labels that count as a phone-like device.
+pub const PHONE_LABELS: &[&str] = &["cell phone", "phone", "mobile"];
+
+/// Default target labels that count as a dedicated camera.
+pub const CAMERA_LABELS: &[&str] = &["camera", "camcorder"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DetectionEvent {
+    /// A target object was seen in `frame`. One event is emitted per box.
+    Detection {
+        frame: u64,
+        label: String,
+        confidence: f32,
+        timestamp: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        captured_at: Option<String>,
+    },
+    /// `frame` was analysed and contained no target object.
+    Clear {
+        frame: u64,
+        timestamp: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        captured_at: Option<String>,
+    },
+    /// Liveness signal: `frame` frames read so far, at `fps` since the last
+    /// heartbeat.
+    Heartbeat { frame: u64, fps: f32, timestamp: String },
+}
+
+impl DetectionEvent {
+    pub fn frame(&self) -> u64 {
+        match self {
+            DetectionEvent::Detection { frame, .. }
+            | DetectionEvent::Clear { frame, .. }
+            | DetectionEvent::Heartbeat { frame, .. } => *frame,
+        }
+    }
+
+    pub fn timestamp(&self) -> &str {
+        match self {
+            DetectionEvent::Detection { timestamp, .. }
+            | DetectionEvent::Clear { timestamp, .. }
+            | DetectionEvent::Heartbeat { timestamp, .. } => timestamp,
+        }
+    }
+
+    /// When the analysed frame was captured, for frame events that say so.
+    pub fn captured_at(&self) -> Option<&str> {
+        match self {
+            DetectionEvent::Detection { captured_at, .. } | DetectionEvent::Clear { captured_at, .. } => {
+                captured_at.as_deref()
+            }
+            DetectionEvent::Heartbeat { .. } => None,
+        }
+    }
+
+    /// Whether this event reports a target object. The detector only emits
+    /// detections for its configured target labels.
+    pub fn is_threat(&self) -> bool {
+        matches!(self, DetectionEvent::Detection { .. })
+    }
+}
+
+#[derive(Debug)]
+pub enum ProtocolError {
+    /// The line was empty or only whitespace.
+    Empty,
+    /// The line did not carry a `v` field.
+    MissingVersion,
+    /// The line declared a protocol version this build does not speak.
+    UnsupportedVersion(u64),
+    /// The line was not a well-formed event.
+    Malformed(serde_json::Error),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::Empty => write!(f, "empty line"),
+            ProtocolError::MissingVersion => write!(f, "missing protocol version"),
+            ProtocolError::UnsupportedVersion(v) => write!(
+                f,
+                "unsupported protocol version {} (expected {})",
+                v, PROTOCOL_VERSION
+            ),
+            ProtocolError::Malformed(err) => write!(f, "malformed event: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+/// Parses one line of detector output.
+pub fn parse_line(line: &str) -> Result<DetectionEvent, ProtocolError> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Err(ProtocolError::Empty);
+    }
+
+    let value: serde_json::Value = serde_json::from_str(line).map_err(ProtocolError::Malformed)?;
+    match value.get("v").and_then(serde_json::Value::as_u64) {
+        Some(v) if v == u64::from(PROTOCOL_VERSION) => {}
+        Some(v) => return Err(ProtocolError::UnsupportedVersion(v)),
+        None => return Err(ProtocolError::MissingVersion),
+    }
+
+    serde_json::from_value(value).map_err(ProtocolError::Malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_event_type() {
+        let detection = parse_line(
+            r#"{"v":2,"type":"detection","frame":42,"label":"cell phone","confidence":0.91,"timestamp":"t","captured_at":"c"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            detection,
+            DetectionEvent::Detection {
+                frame: 42,
+                label: "cell phone".to_string(),
+                confidence: 0.91,
+                timestamp: "t".to_string(),
+                captured_at: Some("c".to_string()),
+            }
+        );
+        assert!(detection.is_threat());
+
+        let clear = parse_line(r#"  {"v":2,"type":"clear","frame":44,"timestamp":"t"}  "#).unwrap();
+        assert_eq!(clear.captured_at(), None);
+        assert!(!clear.is_threat());
+
+        let heartbeat = parse_line(r#"{"v":2,"type":"heartbeat","frame":45,"fps":29.5,"timestamp":"t"}"#).unwrap();
+        assert_eq!(
+            heartbeat,
+            DetectionEvent::Heartbeat {
+                frame: 45,
+                fps: 29.5,
+                timestamp: "t".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn events_round_trip_with_the_version() {
+        let event = DetectionEvent::Clear {
+            frame: 3,
+            timestamp: "t".to_string(),
+            captured_at: None,
+        };
+        let mut value = serde_json::to_value(&event).unwrap();
+        value["v"] = PROTOCOL_VERSION.into();
+        assert_eq!(parse_line(&value.to_string()).unwrap(), event);
+    }
+
+    #[test]
+    fn rejects_blank_lines() {
+        assert!(matches!(parse_line(""), Err(ProtocolError::Empty)));
+        assert!(matches!(parse_line(" \t\r\n"), Err(ProtocolError::Empty)));
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        for line in [
+            "not json",
+            r#"{"v":2,"type":"clear","frame":1"#,
+            "Traceback (most recent call last):",
+        ] {
+            assert!(matches!(parse_line(line), Err(ProtocolError::Malformed(_))), "{}", line);
+        }
+    }
+
+    #[test]
+    fn rejects_a_missing_version() {
+        for line in [
+            r#"{"type":"clear","frame":1,"timestamp":"t"}"#,
+            r#"{"v":"1","type":"clear","frame":1,"timestamp":"t"}"#,
+            r#"{"v":null,"type":"clear","frame":1,"timestamp":"t"}"#,
+            "[1]",
+        ] {
+            assert!(matches!(parse_line(line), Err(ProtocolError::MissingVersion)), "{}", line);
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        // Version 1 predates heartbeats.
+        for v in [0, 1, u64::from(PROTOCOL_VERSION) + 1, 99] {
+            let line = format!(r#"{{"v":{},"type":"clear","frame":1,"timestamp":"t"}}"#, v);
+            match parse_line(&line) {
+                Err(err @ ProtocolError::UnsupportedVersion(got)) => {
+                    assert_eq!(got, v);
+                    assert!(err.to_string().contains(&PROTOCOL_VERSION.to_string()), "{}", err);
+                }
+                other => panic!("{}: {:?}", line, other),
+            }
+        }
+    }
+
+    #[test]
+    fn bundled_detector_speaks_this_version() {
+        let script = include_str!("../detector/detector.py");
+        let declared = format!("\nPROTOCOL_VERSION = {}", PROTOCOL_VERSION);
+        assert!(script.contains(&declared), "detector.py does not declare {}", declared.trim());
+    }
+
+    #[test]
+    fn rejects_unknown_event_types() {
+        for line in [
+            r#"{"v":2,"type":"shutdown","frame":1,"timestamp":"t"}"#,
+            r#"{"v":2,"frame":1,"timestamp":"t"}"#,
+            r#"{"v":2,"type":"Detection","frame":1,"label":"phone","confidence":0.9,"timestamp":"t"}"#,
+        ] {
+            assert!(matches!(parse_line(line), Err(ProtocolError::Malformed(_))), "{}", line);
+        }
+    }
+
+    #[test]
+    fn rejects_events_missing_fields() {
+        for line in [
+            r#"{"v":2,"type":"detection","frame":1,"confidence":0.9,"timestamp":"t"}"#,
+            r#"{"v":2,"type":"heartbeat","frame":1,"timestamp":"t"}"#,
+            r#"{"v":2,"type":"clear","frame":-1,"timestamp":"t"}"#,
+        ] {
+            assert!(matches!(parse_line(line), Err(ProtocolError::Malformed(_))), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Screen recorders and other capture tools among the running processes.
+//!
+//! A [`RecorderScanner`] checks the process list against a denylist of
+//! [`RecorderRule`]s. The pipeline runs one periodically and hands what it
+//! finds to the policy table under [`RECORDER_LABEL`], the same way camera
+//! detections are handled; the response stays in force for as long as a
+//! listed tool runs.
+//!
+//! ```toml
+//! [[recorders.denylist]]
+//! name = "ffmpeg"
+//! args = ["x11grab", "kmsgrab"]
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use sysinfo::System;
+
+/// Label running recorders are evaluated under by the policy table.
+pub const RECORDER_LABEL: &str = "screen recorder";
+
+/// Dedicated recording and streaming tools, by executable name.
+const RECORDERS: &[&str] = &[
+    "obs",
+    "obs64",
+    "obs32",
+    "simplescreenrecorder",
+    "kazam",
+    "vokoscreen",
+    "vokoscreenng",
+    "peek",
+    "recordmydesktop",
+    "gtk-recordmydesktop",
+    "gpu-screen-recorder",
+    "wf-recorder",
+    "kooha",
+    "blue-recorder",
+    "bandicam",
+    "sharex",
+];
+
+/// Screen grabbing inputs that make ffmpeg a recorder.
+const FFMPEG_GRABS: &[&str] = &["x11grab", "kmsgrab", "pipewiregrab", "gdigrab", "ddagrab"];
+
+/// Screen sources that make a GStreamer pipeline a recorder.
+const GSTREAMER_GRABS: &[&str] = &["ximagesrc", "pipewiresrc", "d3d11screencapturesrc"];
+
+/// One denylisted tool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecorderRule {
+    /// Executable name, compared without case or an `.exe` suffix.
+    pub name: String,
+    /// If set, the tool only counts while one of its arguments is one of
+    /// these, for general purpose tools like ffmpeg.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl RecorderRule {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn matches(&self, process: &ProcessInfo) -> bool {
+        let name = normalize(&self.name);
+        process.names().any(|candidate| candidate == name)
+            && (self.args.is_empty()
+                || process
+                    .cmd
+                    .iter()
+                    .skip(1)
+                    .any(|arg| self.args.iter().any(|wanted| arg.eq_ignore_ascii_case(wanted))))
+    }
+}
+
+/// The built-in denylist.
+pub fn default_denylist() -> Vec<RecorderRule> {
+    RECORDERS
+        .iter()
+        .map(|&name| RecorderRule::new(name))
+        .chain([
+            RecorderRule::new("ffmpeg").args(FFMPEG_GRABS.iter().copied()),
+            RecorderRule::new("gst-launch-1.0").args(GSTREAMER_GRABS.iter().copied()),
+        ])
+        .collect()
+}
+
+/// What the scanner knows about a process.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessInfo {
+    /// Name as the OS reports it. Linux cuts it to 15 bytes.
+    pub name: String,
+    pub exe: Option<PathBuf>,
+    pub cmd: Vec<String>,
+}
+
+impl ProcessInfo {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn cmd<I, S>(mut self, cmd: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.cmd = cmd.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The names the process goes by: the reported one and those of its
+    /// executable, which are not truncated but not always readable.
+    fn names(&self) -> impl Iterator<Item = String> + '_ {
+        let files = [self.exe.as_deref(), self.cmd.first().map(Path::new)]
+            .into_iter()
+            .flatten()
+            .filter_map(|path| path.file_name()?.to_str());
+        std::iter::once(self.name.as_str()).chain(files).map(normalize)
+    }
+}
+
+fn normalize(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    match name.strip_suffix(".exe") {
+        Some(stem) => stem.to_string(),
+        None => name,
+    }
+}
+
+/// Where the scanner gets the process list from.
+pub trait ProcessSource: Send {
+    fn processes(&mut self) -> Vec<ProcessInfo>;
+}
+
+impl<F> ProcessSource for F
+where
+    F: FnMut() -> Vec<ProcessInfo> + Send,
+{
+    fn processes(&mut self) -> Vec<ProcessInfo> {
+        self()
+    }
+}
+
+/// The processes actually running, as `sysinfo` sees them.
+#[derive(Default)]
+pub struct SystemProcesses {
+    system: System,
+}
+
+impl SystemProcesses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ProcessSource for SystemProcesses {
+    fn processes(&mut self) -> Vec<ProcessInfo> {
+        self.system.refresh_processes();
+        self.system
+            .processes()
+            .values()
+            .map(|process| ProcessInfo {
+                name: process.name().to_string(),
+                exe: process.exe().map(Path::to_path_buf),
+                cmd: process.cmd().to_vec(),
+            })
+            .collect()
+    }
+}
+
+/// Checks the process list against a denylist.
+pub struct RecorderScanner {
+    denylist: Vec<RecorderRule>,
+    source: Box<dyn ProcessSource>,
+}
+
+impl RecorderScanner {
+    pub fn new(denylist: Vec<RecorderRule>, source: impl ProcessSource + 'static) -> Self {
+        Self {
+            denylist,
+            source: Box::new(source),
+        }
+    }
+
+    /// Names of the denylisted tools running now, sorted, each once.
+    pub fn scan(&mut self) -> Vec<String> {
+        let mut found: Vec<String> = self
+            .source
+            .processes()
+            .iter()
+            .flat_map(|process| self.denylist.iter().filter(|rule| rule.matches(process)))
+            .map(|rule| rule.name.clone())
+            .collect();
+        found.sort();
+        found.dedup();
+        found
+    }
+}
@@ -0,0 +1,169 @@
+//! Which windows are protected.
+//!
+//! Every webview window the app creates is checked against the configured
+//! label patterns as it appears. A matching window is enrolled: it is kept
+//! out of screen captures, gets its own blackout [`overlay`] and watermark,
+//! and receives the page effects, and whatever response is in force when it
+//! appears is applied to it straight away. Each overlay follows its window,
+//! so windows spread over several monitors are all covered. A navigation or
+//! reload loses what was injected into the old page, so [`on_page_load`] sets
+//! every page a protected window loads up again.
+//!
+//! The app's own overlay, watermark and denial windows are never enrolled.
+
+use crate::denial::DENIAL_LABEL;
+use crate::enforcement::{self, Surface};
+use crate::overlay;
+use crate::protection::Enforcement;
+use crate::status::ProtectionState;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::sync::{Mutex, MutexGuard};
+use tauri::webview::{PageLoadEvent, PageLoadPayload};
+use tauri::{AppHandle, Listener, Manager, Runtime, Webview, WebviewWindow, WindowEvent};
+
+/// Event Tauri emits for every new webview.
+const WEBVIEW_CREATED: &str = "tauri://webview-created";
+
+/// Labels of the enrolled windows and the patterns deciding enrollment.
+pub struct ProtectionRegistry {
+    patterns: Vec<String>,
+    windows: Mutex<BTreeSet<String>>,
+}
+
+impl ProtectionRegistry {
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self {
+            patterns,
+            windows: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeSet<String>> {
+        self.windows.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Whether a window labelled `label` should be protected.
+    pub fn matches(&self, label: &str) -> bool {
+        !is_internal(label) && self.patterns.iter().any(|pattern| label_matches(pattern, label))
+    }
+
+    /// Enrolls `label` if it matches. Returns whether it was newly enrolled.
+    pub fn enroll(&self, label: &str) -> bool {
+        self.matches(label) && self.lock().insert(label.to_string())
+    }
+
+    /// Whether the window labelled `label` is enrolled.
+    pub fn contains(&self, label: &str) -> bool {
+        self.lock().contains(label)
+    }
+
+    pub fn remove(&self, label: &str) {
+        self.lock().remove(label);
+    }
+
+    /// Labels of the enrolled windows, in order.
+    pub fn labels(&self) -> Vec<String> {
+        self.lock().iter().cloned().collect()
+    }
+}
+
+/// Windows the app creates for itself.
+fn is_internal(label: &str) -> bool {
+    label.starts_with(overlay::OVERLAY_PREFIX) || label.starts_with(overlay::WATERMARK_PREFIX) || label == DENIAL_LABEL
+}
+
+/// Matches `label` against `pattern`, where `*` stands for any run of
+/// characters, including none.
+pub fn label_matches(pattern: &str, label: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = label.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*` at all.
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Enrolls `window` if it matches and protects it through the managed
+/// [`Surface`].
+pub fn protect<R: Runtime>(app_handle: &AppHandle<R>, window: &WebviewWindow<R>) {
+    let label = window.label().to_string();
+    if !app_handle.state::<ProtectionRegistry>().enroll(&label) {
+        return;
+    }
+    tracing::info!(%label, "protecting window");
+    let surface = app_handle.state::<Box<dyn Surface>>();
+    surface.protect(&label);
+
+    let handle = app_handle.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            handle.state::<ProtectionRegistry>().remove(&label);
+            overlay::detach(&handle, &label);
+        }
+    });
+
+    // A window opened during a blackout must not show what the others hide.
+    if let Some(action) = app_handle.state::<ProtectionState>().status().action {
+        enforcement::enforce(&**surface, Enforcement::Apply(action));
+    }
+}
+
+#[derive(Deserialize)]
+struct Created {
+    label: String,
+}
+
+/// Protects every window that exists now or is created later and matches.
+pub fn watch<R: Runtime>(app_handle: &AppHandle<R>) {
+    let handle = app_handle.clone();
+    app_handle.listen_any(WEBVIEW_CREATED, move |event| {
+        let Ok(created) = serde_json::from_str::<Created>(event.payload()) else {
+            return;
+        };
+        if let Some(window) = handle.get_webview_window(&created.label) {
+            protect(&handle, &window);
+        }
+    });
+
+    for window in app_handle.webview_windows().values() {
+        protect(app_handle, window);
+    }
+}
+
+/// Page load hook for [`tauri::Builder::on_page_load`].
+pub fn on_page_load<R: Runtime>(webview: &Webview<R>, payload: &PageLoadPayload<'_>) {
+    if payload.event() == PageLoadEvent::Finished {
+        page_loaded(webview.app_handle(), webview.label());
+    }
+}
+
+/// Sets up the page the window labelled `label` has just loaded, if the
+/// window is protected, and puts back the page effect in force, which the
+/// old page took with it.
+pub fn page_loaded<R: Runtime>(app_handle: &AppHandle<R>, label: &str) {
+    // Windows from tauri.conf.json may load before setup manages the state.
+    let Some(registry) = app_handle.try_state::<ProtectionRegistry>() else {
+        return;
+    };
+    if !registry.contains(label) {
+        return;
+    }
+    let surface = app_handle.state::<Box<dyn Surface>>();
+    surface.eval_in(label, enforcement::GUARD_SCRIPT);
+    let action = app_handle.state::<ProtectionState>().status().action;
+    if let Some(script) = action.and_then(enforcement::page_script) {
+        surface.eval_in(label, script);
+    }
+}
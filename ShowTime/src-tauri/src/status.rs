@@ -0,0 +1,260 @@
+//! Shared protection state.
+//!
+//! [`ProtectionState`] wraps [`Protection`] together with what the frontend
+//! needs to explain a blackout: why it happened, since when, when it may be
+//! lifted and what the detector is doing. The detector thread feeds it and
+//! the `get_protection_status` command reads it.
+//!
+//! A paused detector still sends heartbeats but no longer looks, so a pause
+//! is treated like a detector that went down: the failure policy applies
+//! until detection resumes.
+
+use crate::hysteresis::HysteresisConfig;
+use crate::policy::{Action, PolicyTable};
+use crate::protection::{Enforcement, Protection, Verdict};
+use crate::protocol::DetectionEvent;
+use crate::supervisor::{DetectorStatus, FailurePolicy};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::{Mutex, MutexGuard};
+
+/// Payload of the `detection` event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Detection {
+    pub frame: u64,
+    pub label: String,
+    pub confidence: f32,
+    pub timestamp: String,
+    pub action: Action,
+}
+
+/// Latest liveness report from the detector.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Heartbeat {
+    pub frame: u64,
+    pub fps: f32,
+    pub timestamp: String,
+}
+
+/// Why the current response is in force.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Reason {
+    Detection { label: String, confidence: f32 },
+    DetectorDown { reason: String },
+    Paused,
+    ScreenRecorder { names: Vec<String> },
+    Manual,
+}
+
+/// Snapshot returned by `get_protection_status` and carried by the
+/// `blackout-changed` event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProtectionStatus {
+    /// Response currently in force, `None` when the page is untouched.
+    pub action: Option<Action>,
+    pub reason: Option<Reason>,
+    pub since: Option<DateTime<Utc>>,
+    /// Milliseconds until the response can be lifted if no new threat shows
+    /// up; `None` when it is not on a timer.
+    pub restore_in_ms: Option<u64>,
+    pub detector: Option<DetectorStatus>,
+    /// Whether detection is paused on request.
+    pub paused: bool,
+    pub last_detection: Option<Detection>,
+    pub last_heartbeat: Option<Heartbeat>,
+}
+
+struct Inner {
+    protection: Protection,
+    failure_policy: FailurePolicy,
+    reason: Option<Reason>,
+    since: Option<DateTime<Utc>>,
+    detector: Option<DetectorStatus>,
+    paused: bool,
+    last_detection: Option<Detection>,
+    last_heartbeat: Option<Heartbeat>,
+}
+
+pub struct ProtectionState {
+    inner: Mutex<Inner>,
+}
+
+impl ProtectionState {
+    pub fn new(policy: PolicyTable, hysteresis: HysteresisConfig, failure_policy: FailurePolicy) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                protection: Protection::new(policy, hysteresis),
+                failure_policy,
+                reason: None,
+                since: None,
+                detector: None,
+                paused: false,
+                last_detection: None,
+                last_heartbeat: None,
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn status(&self) -> ProtectionStatus {
+        let inner = self.lock();
+        ProtectionStatus {
+            action: inner.protection.active(),
+            reason: inner.reason.clone(),
+            since: inner.since,
+            restore_in_ms: inner.protection.restore_in().map(|left| left.as_millis() as u64),
+            detector: inner.detector.clone(),
+            paused: inner.paused,
+            last_detection: inner.last_detection.clone(),
+            last_heartbeat: inner.last_heartbeat.clone(),
+        }
+    }
+
+    /// Feeds one detector event. Detections are also returned as the
+    /// `detection` event payload.
+    pub fn observe(&self, event: &DetectionEvent) -> (Verdict, Option<Detection>) {
+        let mut inner = self.lock();
+        let verdict = inner.protection.observe(event);
+
+        let detection = match event {
+            DetectionEvent::Detection {
+                frame,
+                label,
+                confidence,
+                timestamp,
+                ..
+            } => Some(Detection {
+                frame: *frame,
+                label: label.clone(),
+                confidence: *confidence,
+                timestamp: timestamp.clone(),
+                action: verdict.action,
+            }),
+            DetectionEvent::Clear { .. } => None,
+            DetectionEvent::Heartbeat { frame, fps, timestamp } => {
+                inner.last_heartbeat = Some(Heartbeat {
+                    frame: *frame,
+                    fps: *fps,
+                    timestamp: timestamp.clone(),
+                });
+                None
+            }
+        };
+        if let Some(detection) = &detection {
+            inner.last_detection = Some(detection.clone());
+        }
+
+        let reason = detection.as_ref().map(|detection| Reason::Detection {
+            label: detection.label.clone(),
+            confidence: detection.confidence,
+        });
+        inner.track(verdict.enforcement, reason);
+        (verdict, detection)
+    }
+
+    /// Records a detector status change and applies the failure policy if
+    /// the detector went down.
+    pub fn detector_status(&self, status: &DetectorStatus) -> Option<Enforcement> {
+        let mut inner = self.lock();
+        inner.detector = Some(status.clone());
+        if !status.is_down() {
+            return None;
+        }
+
+        let policy = inner.failure_policy;
+        let enforcement = inner.protection.detector_down(policy);
+        let reason = match status {
+            DetectorStatus::Stalled { silent_ms } => Some(Reason::DetectorDown {
+                reason: format!("no heartbeat for {} ms", silent_ms),
+            }),
+            DetectorStatus::Crashed { reason, .. } | DetectorStatus::GaveUp { reason, .. } => {
+                Some(Reason::DetectorDown { reason: reason.clone() })
+            }
+            _ => None,
+        };
+        inner.track(enforcement, reason);
+        enforcement
+    }
+
+    /// Records whether detection is paused, applying the failure policy when
+    /// it pauses. Resuming changes nothing on screen: the frames that follow
+    /// lift whatever the pause left in force.
+    pub fn pause(&self, paused: bool) -> Option<Enforcement> {
+        let mut inner = self.lock();
+        if inner.paused == paused {
+            return None;
+        }
+        inner.paused = paused;
+        if !paused {
+            return None;
+        }
+
+        let policy = inner.failure_policy;
+        let enforcement = inner.protection.detector_down(policy);
+        inner.track(enforcement, Some(Reason::Paused));
+        enforcement
+    }
+
+    /// Feeds the screen recorders found running, all of them each time.
+    pub fn recorders(&self, running: &[String]) -> Verdict {
+        let mut inner = self.lock();
+        let verdict = inner.protection.recorders(running);
+        inner.track(
+            verdict.enforcement,
+            Some(Reason::ScreenRecorder {
+                names: running.to_vec(),
+            }),
+        );
+        verdict
+    }
+
+    /// Manual blackout, held until [`ProtectionState::release`].
+    pub fn pin(&self) -> Option<Enforcement> {
+        let mut inner = self.lock();
+        let enforcement = inner.protection.pin();
+        inner.track(enforcement, Some(Reason::Manual));
+        // Pinning an existing blackout still makes it a manual one.
+        inner.reason = Some(Reason::Manual);
+        enforcement
+    }
+
+    /// Lifts a manual blackout. Refused, with the reason, while the detector
+    /// still calls for protection.
+    pub fn release(&self) -> Result<Option<Enforcement>, String> {
+        let mut inner = self.lock();
+        if inner.protection.threat_active() {
+            return Err(match &inner.reason {
+                Some(Reason::DetectorDown { .. }) => "the detector is down and protection fails closed".to_string(),
+                Some(Reason::Paused) => "detection is paused and protection fails closed".to_string(),
+                Some(Reason::ScreenRecorder { names }) => format!("{} is still running", names.join(", ")),
+                _ => "the detector still reports a threat".to_string(),
+            });
+        }
+
+        let enforcement = inner.protection.release();
+        inner.track(enforcement, None);
+        Ok(enforcement)
+    }
+}
+
+impl Inner {
+    fn track(&mut self, enforcement: Option<Enforcement>, reason: Option<Reason>) {
+        match enforcement {
+            Some(Enforcement::Apply(_)) => {
+                if self.since.is_none() {
+                    self.since = Some(Utc::now());
+                }
+                self.reason = reason;
+            }
+            Some(Enforcement::Lift) => {
+                self.since = None;
+                self.reason = None;
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Keeps the detector process alive.
+//!
+//! The supervisor starts the detector, forwards its events and restarts it
+//! with exponential backoff whenever a run ends. Every state change is handed
+//! to a reporter so the frontend can show what the detector is doing.
+//!
+//! A run that stays silent for longer than the heartbeat timeout (or the
+//! startup timeout, before its first line) is reported as stalled, killed and
+//! treated like a crash.
+//!
+//! Once [`Shutdown::request`] is called the supervisor lets the current run
+//! end without restarting it; stopping the process itself is up to the
+//! caller.
+
+use crate::detector::{Detector, Next};
+use crate::protocol::{DetectionEvent, ProtocolError};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// What protection does while the detector is not running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Black the window out until the detector reports a clear frame again.
+    FailClosed,
+    /// Leave the window usable while the detector is down.
+    FailOpen,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum DetectorStatus {
+    Starting {
+        attempt: u32,
+    },
+    Running {
+        pid: Option<u32>,
+    },
+    /// The run stopped sending anything, heartbeats included, and is being
+    /// killed.
+    Stalled {
+        silent_ms: u64,
+    },
+    Crashed {
+        crashes: u32,
+        reason: String,
+        retry_in_ms: u64,
+    },
+    GaveUp {
+        crashes: u32,
+        reason: String,
+    },
+}
+
+impl DetectorStatus {
+    /// Whether the detector is currently unable to report detections.
+    pub fn is_down(&self) -> bool {
+        matches!(
+            self,
+            DetectorStatus::Stalled { .. } | DetectorStatus::Crashed { .. } | DetectorStatus::GaveUp { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SupervisorConfig {
+    /// Delay before the first restart.
+    pub initial_backoff: Duration,
+    /// Upper bound for the restart delay.
+    pub max_backoff: Duration,
+    /// Consecutive crashes tolerated before giving up.
+    pub max_restarts: u32,
+    /// A run at least this long resets the backoff and the consecutive
+    /// crash counter.
+    pub stable_after: Duration,
+    /// Longest silence tolerated from a running detector. It sends heartbeats
+    /// well within this.
+    pub heartbeat_timeout: Duration,
+    /// Longest silence tolerated before the first line of a run, which
+    /// covers opening the camera and loading the model.
+    pub startup_timeout: Duration,
+    /// Time a detector gets to exit on its own when the app shuts down
+    /// before it is killed.
+    pub shutdown_grace: Duration,
+    pub failure_policy: FailurePolicy,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            max_restarts: 10,
+            stable_after: Duration::from_secs(30),
+            heartbeat_timeout: Duration::from_secs(5),
+            startup_timeout: Duration::from_secs(30),
+            shutdown_grace: Duration::from_secs(3),
+            failure_policy: FailurePolicy::FailClosed,
+        }
+    }
+}
+
+/// Exponential backoff, doubling on every call to [`Backoff::next_delay`].
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            next: initial,
+        }
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.next = self.initial;
+    }
+}
+
+/// Tells a running [`supervise`] loop to stop restarting the detector.
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    requested: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl Shutdown {
+    pub fn request(&self) {
+        let (requested, wake) = &*self.requested;
+        *requested.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = true;
+        wake.notify_all();
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.requested.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Sleeps for `duration` or until shutdown is requested. Returns whether
+    /// it was.
+    fn sleep(&self, duration: Duration) -> bool {
+        let (requested, wake) = &*self.requested;
+        let guard = requested.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (guard, _) = wake
+            .wait_timeout_while(guard, duration, |requested| !*requested)
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *guard
+    }
+}
+
+/// Runs the detector until it has crashed `max_restarts` times in a row.
+///
+/// `on_event` receives every event (or rejected line) of every run and
+/// `report` every status change. Returns once the supervisor gives up or,
+/// after `shutdown` was requested, once the current run has ended.
+pub fn supervise<D, E, R>(
+    config: &SupervisorConfig,
+    detector: &mut D,
+    shutdown: &Shutdown,
+    mut on_event: E,
+    mut report: R,
+) where
+    D: Detector + ?Sized,
+    E: FnMut(Result<DetectionEvent, ProtocolError>),
+    R: FnMut(&DetectorStatus),
+{
+    let mut backoff = Backoff::new(config.initial_backoff, config.max_backoff);
+    let mut attempt = 0;
+    let mut crashes = 0;
+    let mut consecutive = 0;
+
+    while !shutdown.is_requested() {
+        attempt += 1;
+        report(&DetectorStatus::Starting { attempt });
+
+        let run = tracing::info_span!("detector_run", attempt, pid = tracing::field::Empty).entered();
+        let started = Instant::now();
+        let reason = match detector.start() {
+            Ok(()) => {
+                let pid = detector.pid();
+                if let Some(pid) = pid {
+                    run.record("pid", pid);
+                }
+                report(&DetectorStatus::Running { pid });
+
+                let mut timeout = config.startup_timeout;
+                let stalled = loop {
+                    match detector.next_event(timeout) {
+                        Next::Event(event) => {
+                            on_event(event);
+                            timeout = config.heartbeat_timeout;
+                        }
+                        Next::Silent if shutdown.is_requested() => break None,
+                        Next::Silent => break Some(timeout),
+                        Next::Ended => break None,
+                    }
+                };
+
+                match stalled {
+                    Some(silence) => {
+                        let silent_ms = silence.as_millis() as u64;
+                        report(&DetectorStatus::Stalled { silent_ms });
+                        detector.stop();
+                        let exit = detector.wait();
+                        format!("detector stalled: no output for {} ms ({})", silent_ms, exit)
+                    }
+                    None => detector.wait(),
+                }
+            }
+            Err(err) => format!("failed to start detector: {}", err),
+        };
+        drop(run);
+        if shutdown.is_requested() {
+            return;
+        }
+
+        if started.elapsed() >= config.stable_after {
+            backoff.reset();
+            consecutive = 0;
+        }
+        crashes += 1;
+        consecutive += 1;
+
+        if consecutive > config.max_restarts {
+            report(&DetectorStatus::GaveUp { crashes, reason });
+            return;
+        }
+
+        let delay = backoff.next_delay();
+        report(&DetectorStatus::Crashed {
+            crashes,
+            reason,
+            retry_in_ms: delay.as_millis() as u64,
+        });
+        if shutdown.sleep(delay) {
+            return;
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Forensic watermark.
+//!
+//! Once enabled with `watermark.enabled` or `SHOWTIME_WATERMARK`, every
+//! protected window carries a faint, tiled watermark naming the user, this
+//! session and the current time, so a photo of the screen can be traced back
+//! to who took it and when. The session is recorded in the audit log when the
+//! app starts either way.
+//!
+//! The watermark is drawn in a click-through overlay window of its own (see
+//! [`overlay::attach_watermark`](crate::overlay::attach_watermark)), so it
+//! survives page navigations and the page's scripts cannot reach it. This
+//! module decides what the overlay shows; the Rust side re-renders it on
+//! every refresh, updating the time and drifting the pattern slowly so it
+//! cannot be cropped or painted out at a fixed spot.
+
+use crate::config::WatermarkConfig;
+use chrono::{DateTime, Local};
+use sha2::{Digest, Sha256};
+use std::f64::consts::TAU;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How far the pattern drifts from its resting place, in CSS pixels.
+const DRIFT: f64 = 120.0;
+
+/// Periods of the horizontal and vertical drift. Being coprime they only
+/// repeat together after almost 100 minutes.
+const DRIFT_PERIODS: (f64, f64) = (97.0, 61.0);
+
+/// Copies of the text tiled across the overlay.
+const TILES: usize = 80;
+
+/// Who is looking and since when.
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    config: WatermarkConfig,
+    user: String,
+    session: String,
+    started: Instant,
+}
+
+impl Watermark {
+    pub fn new(config: WatermarkConfig, user: impl Into<String>, session: impl Into<String>) -> Self {
+        Self {
+            config,
+            user: user.into(),
+            session: session.into(),
+            started: Instant::now(),
+        }
+    }
+
+    /// A new session for the configured user, or the OS account.
+    pub fn start(config: &WatermarkConfig) -> Self {
+        let user = config.user.clone().unwrap_or_else(os_user);
+        Self::new(config.clone(), user, new_session_id())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    pub fn refresh_interval(&self) -> Duration {
+        Duration::from_millis(self.config.refresh_ms)
+    }
+
+    /// The configured text with `{user}`, `{session}` and `{time}` filled in.
+    pub fn text(&self, now: DateTime<Local>) -> String {
+        self.config
+            .text
+            .replace("{user}", &self.user)
+            .replace("{session}", &self.session)
+            .replace("{time}", &now.format("%Y-%m-%d %H:%M:%S").to_string())
+    }
+
+    /// Where the pattern has drifted to `elapsed` into the session, in CSS
+    /// pixels.
+    pub fn offset(elapsed: Duration) -> (f64, f64) {
+        let t = elapsed.as_secs_f64();
+        (
+            (DRIFT * (TAU * t / DRIFT_PERIODS.0).sin()).round(),
+            (DRIFT * (TAU * t / DRIFT_PERIODS.1).sin()).round(),
+        )
+    }
+
+    /// Script drawing the watermark as of `now` into an overlay, whatever
+    /// the overlay currently shows.
+    pub fn script(&self, now: DateTime<Local>) -> String {
+        let text = serde_json::to_string(&self.text(now)).unwrap_or_default();
+        let (x, y) = Self::offset(self.started.elapsed());
+        format!(
+            r#"(() => {{
+  document.documentElement.style.cssText = 'background: transparent;';
+  document.body.style.cssText = 'margin: 0; overflow: hidden; background: transparent; user-select: none; pointer-events: none;';
+  let layer = document.getElementById('showtime-watermark');
+  if (!layer) {{
+    layer = document.createElement('div');
+    layer.id = 'showtime-watermark';
+    document.body.replaceChildren(layer);
+  }}
+  layer.style.cssText = 'position: fixed; inset: -50%; display: flex; flex-wrap: wrap; align-content: space-around; justify-content: space-around; gap: 96px 160px; color: rgb(128, 128, 128); font: 16px sans-serif; white-space: nowrap; opacity: {opacity}; transform: translate({x}px, {y}px) rotate(-30deg);';
+  const text = {text};
+  if (layer.childElementCount !== {tiles} || layer.firstChild.textContent !== text) {{
+    layer.replaceChildren(...Array.from({{ length: {tiles} }}, () => Object.assign(document.createElement('span'), {{ textContent: text }})));
+  }}
+}})();"#,
+            opacity = self.config.opacity,
+            x = x,
+            y = y,
+            text = text,
+            tiles = TILES,
+        )
+    }
+}
+
+/// A short identifier no other session is likely to share.
+pub fn new_session_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let digest = Sha256::new()
+        .chain_update(std::process::id().to_be_bytes())
+        .chain_update(nanos.to_be_bytes())
+        .chain_update(COUNTER.fetch_add(1, Ordering::Relaxed).to_be_bytes())
+        .finalize();
+    digest[..6].iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Name of the OS account running the app.
+fn os_user() -> String {
+    ["USER", "USERNAME", "LOGNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|name| !name.is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
@@ -0,0 +1,70 @@
+//! The command ACL as tauri-build generated it from build.rs, permissions/
+//! and capabilities/. The mock runtime used by tests/commands.rs skips the
+//! ACL, so this is where a command nobody may call shows up.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tauri::utils::acl::capability::Capability;
+use tauri::utils::acl::manifest::Manifest;
+use tauri::utils::acl::resolved::Resolved;
+use tauri::utils::acl::{ExecutionContext, APP_ACL_KEY};
+use tauri::utils::platform::Target;
+
+/// Every app command, whether local app content may call it and whether the
+/// trusted remote site may.
+const COMMANDS: &[(&str, bool, bool)] = &[
+    ("greet", true, false),
+    ("trigger_blackout", false, true),
+    ("restore_window", false, true),
+    ("get_protection_status", true, true),
+    ("verify_audit_log", true, false),
+    ("export_audit_log", true, false),
+    ("get_detector_logs", true, false),
+    ("get_metrics", true, false),
+    ("control_detector", true, false),
+];
+
+fn generated<T: serde::de::DeserializeOwned>(name: &str) -> T {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("gen/schemas").join(name);
+    let json = fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+    serde_json::from_str(&json).unwrap()
+}
+
+fn resolved() -> Resolved {
+    let manifests: BTreeMap<String, Manifest> = generated("acl-manifests.json");
+    let capabilities: BTreeMap<String, Capability> = generated("capabilities.json");
+    Resolved::resolve(&manifests, capabilities, Target::current()).unwrap()
+}
+
+#[test]
+fn commands_are_granted_where_they_are_meant_to_be() {
+    let resolved = resolved();
+    assert!(resolved.has_app_acl);
+
+    for &(command, local, remote) in COMMANDS {
+        let grants = resolved.allowed_commands.get(command).map(Vec::as_slice).unwrap_or_default();
+        let in_main = grants
+            .iter()
+            .filter(|grant| grant.windows.iter().any(|pattern| pattern.matches("main")));
+        let (mut granted_local, mut granted_remote) = (false, false);
+        for grant in in_main {
+            match &grant.context {
+                ExecutionContext::Local => granted_local = true,
+                ExecutionContext::Remote { .. } => granted_remote = true,
+            }
+        }
+        assert_eq!(granted_local, local, "{} for local app content", command);
+        assert_eq!(granted_remote, remote, "{} for the remote site", command);
+    }
+}
+
+#[test]
+fn every_command_is_in_the_app_manifest() {
+    let manifests: BTreeMap<String, Manifest> = generated("acl-manifests.json");
+    let app = &manifests[APP_ACL_KEY];
+    for &(command, _, _) in COMMANDS {
+        let permission = format!("allow-{}", command.replace('_', "-"));
+        assert!(app.permissions.contains_key(&permission), "{} is missing", permission);
+    }
+}
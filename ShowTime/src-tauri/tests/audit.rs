@@ -0,0 +1,180 @@
+use showtime_lib::audit::{self, AuditError, AuditEvent, AuditLog};
+use showtime_lib::policy::Action;
+use std::fs;
+use std::path::PathBuf;
+
+fn scratch_log(name: &str) -> PathBuf {
+    let path = std::env::temp_dir()
+        .join(format!("showtime-audit-{}-{}", name, std::process::id()))
+        .join("audit.jsonl");
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn write_sample(path: &PathBuf) {
+    let log = AuditLog::open(path).unwrap();
+    log.append(AuditEvent::Detection {
+        frame: 4,
+        label: "cell phone".to_string(),
+        confidence: 0.91,
+        action: Action::Blackout,
+    })
+    .unwrap();
+    log.append(AuditEvent::Enforced {
+        action: Action::Blackout,
+    })
+    .unwrap();
+    log.append(AuditEvent::Restored).unwrap();
+}
+
+#[test]
+fn appended_records_verify() {
+    let path = scratch_log("verify");
+    write_sample(&path);
+
+    let report = audit::verify(&path).unwrap();
+    assert_eq!(report.records, 3);
+}
+
+#[test]
+fn reopening_continues_the_chain() {
+    let path = scratch_log("reopen");
+    write_sample(&path);
+    let record = AuditLog::open(&path).unwrap().append(AuditEvent::Restored).unwrap();
+
+    assert_eq!(record.seq, 4);
+    assert_eq!(audit::verify(&path).unwrap().records, 4);
+}
+
+#[test]
+fn edited_record_breaks_the_chain() {
+    let path = scratch_log("tamper");
+    write_sample(&path);
+
+    let tampered = fs::read_to_string(&path).unwrap().replace("0.91", "0.11");
+    fs::write(&path, tampered).unwrap();
+
+    match audit::verify(&path) {
+        Err(AuditError::Broken { seq, .. }) => assert_eq!(seq, 1),
+        other => panic!("expected a broken chain, got {:?}", other),
+    }
+}
+
+#[test]
+fn dropped_record_breaks_the_chain() {
+    let path = scratch_log("drop");
+    write_sample(&path);
+
+    let remaining: Vec<_> = fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .enumerate()
+        .filter(|(index, _)| *index != 1)
+        .map(|(_, line)| format!("{}\n", line))
+        .collect();
+    fs::write(&path, remaining.concat()).unwrap();
+
+    assert!(matches!(audit::verify(&path), Err(AuditError::Broken { seq: 3, .. })));
+}
+
+#[test]
+fn export_filters_by_date() {
+    let path = scratch_log("export");
+    write_sample(&path);
+
+    let everything = audit::export(&path, None, None).unwrap();
+    assert_eq!(everything.len(), 3);
+
+    let past = audit::parse_bound("2000-01-01", true).unwrap();
+    assert!(audit::export(&path, None, Some(past)).unwrap().is_empty());
+
+    let from = audit::parse_bound("2000-01-01", false).unwrap();
+    assert_eq!(audit::export(&path, Some(from), None).unwrap().len(), 3);
+}
+
+#[test]
+fn bounds_accept_dates_and_timestamps() {
+    let start = audit::parse_bound("2024-05-01", false).unwrap();
+    let end = audit::parse_bound("2024-05-01", true).unwrap();
+    assert_eq!(start.to_rfc3339(), "2024-05-01T00:00:00+00:00");
+    assert!(end > start);
+
+    let exact = audit::parse_bound("2024-05-01T10:00:00+02:00", false).unwrap();
+    assert_eq!(exact.to_rfc3339(), "2024-05-01T08:00:00+00:00");
+
+    assert!(audit::parse_bound("yesterday", false).is_err());
+}
+
+#[test]
+fn support_code_points_at_the_record() {
+    let path = scratch_log("support");
+    let log = AuditLog::open(&path).unwrap();
+    log.append(AuditEvent::Restored).unwrap();
+    let record = log
+        .append(AuditEvent::AccessDenied {
+            reason: "virtual machine".to_string(),
+            exit_code: 10,
+        })
+        .unwrap();
+
+    let code = record.support_code();
+    assert_eq!(code, format!("ST-2-{}", record.hash[..8].to_uppercase()));
+    let found: Vec<_> = audit::read_verified(&path)
+        .unwrap()
+        .into_iter()
+        .filter(|record| record.support_code() == code)
+        .collect();
+    assert_eq!(found, [record]);
+}
+
+#[test]
+fn torn_last_record_is_set_aside() {
+    let path = scratch_log("torn");
+    write_sample(&path);
+    let whole = fs::read(&path).unwrap();
+    // A crash in the middle of appending a fourth record.
+    let mut torn = whole.clone();
+    torn.extend_from_slice(br#"{"seq":4,"time":"2024-05-01T"#);
+    fs::write(&path, &torn).unwrap();
+    assert!(matches!(audit::verify(&path), Err(AuditError::Malformed { line: 4, .. })));
+
+    let log = AuditLog::open(&path).unwrap();
+    let moved_to = PathBuf::from(format!("{}.torn-4", path.display()));
+    assert_eq!(fs::read(&moved_to).unwrap(), &torn[whole.len()..]);
+
+    let records = audit::read_verified(&path).unwrap();
+    assert_eq!(records.len(), 4);
+    assert_eq!(
+        records[3].event,
+        AuditEvent::TornRecordSetAside {
+            line: 4,
+            bytes: (torn.len() - whole.len()) as u64,
+            moved_to: moved_to.display().to_string(),
+        }
+    );
+    assert_eq!(log.append(AuditEvent::Restored).unwrap().seq, 5);
+    assert_eq!(audit::verify(&path).unwrap().records, 5);
+}
+
+#[test]
+fn record_torn_before_its_newline_is_kept() {
+    let path = scratch_log("unterminated");
+    write_sample(&path);
+    let content = fs::read_to_string(&path).unwrap();
+    fs::write(&path, content.trim_end()).unwrap();
+
+    AuditLog::open(&path).unwrap().append(AuditEvent::Restored).unwrap();
+    assert_eq!(audit::verify(&path).unwrap().records, 4);
+}
+
+#[test]
+fn malformed_record_before_the_end_is_an_error() {
+    let path = scratch_log("malformed");
+    write_sample(&path);
+    let content = fs::read_to_string(&path).unwrap();
+    let mut lines: Vec<&str> = content.lines().collect();
+    lines.insert(1, "not a record");
+    fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+    assert!(matches!(AuditLog::open(&path), Err(AuditError::Malformed { line: 2, .. })));
+}
@@ -0,0 +1,223 @@
+#![cfg(feature = "capture")]
+//! Frame sources without a camera: the still images in tests/fixtures/frames
+//! and Motion JPEG videos written on the fly.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{Rgb, RgbImage};
+use showtime_lib::capture::{read_jpeg, Capture, ImageDir, VideoFile};
+use showtime_lib::config::{Resolution, ShowTimeConfig};
+use std::env;
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+const RED: Rgb<u8> = Rgb([255, 0, 0]);
+const GREEN: Rgb<u8> = Rgb([0, 255, 0]);
+const BLACK: Rgb<u8> = Rgb([0, 0, 0]);
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("showtime-capture-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn jpeg(color: Rgb<u8>, size: u32) -> Vec<u8> {
+    let mut data = Vec::new();
+    JpegEncoder::new_with_quality(&mut data, 90)
+        .encode_image(&RgbImage::from_pixel(size, size, color))
+        .unwrap();
+    data
+}
+
+/// A JPEG of `color` carrying a thumbnail in an application segment, the
+/// way cameras embed one in their EXIF data.
+fn jpeg_with_thumbnail(color: Rgb<u8>) -> Vec<u8> {
+    let thumbnail = jpeg(BLACK, 2);
+    let mut data = jpeg(color, 32);
+    let mut segment = vec![0xFF, 0xEF];
+    segment.extend_from_slice(&(thumbnail.len() as u16 + 2).to_be_bytes());
+    segment.extend_from_slice(&thumbnail);
+    data.splice(2..2, segment);
+    data
+}
+
+/// A video of one frame per color, with container bytes between frames.
+fn write_video(path: &Path, colors: &[Rgb<u8>]) {
+    let mut video = b"RIFF\0\0\0\0AVI ".to_vec();
+    for &color in colors {
+        video.extend_from_slice(b"00dc\0\0\0\0");
+        video.extend_from_slice(&jpeg_with_thumbnail(color));
+    }
+    fs::write(path, video).unwrap();
+}
+
+fn assert_color(image: &RgbImage, color: Rgb<u8>) {
+    let pixel = image.get_pixel(image.width() / 2, image.height() / 2);
+    assert!(
+        pixel.0.iter().zip(color.0).all(|(&a, b)| a.abs_diff(b) <= 8),
+        "{:?} is not {:?}",
+        pixel,
+        color
+    );
+}
+
+/// Frame numbers and images of everything `capture` yields.
+fn read_all(capture: &mut Capture) -> Vec<(u64, RgbImage)> {
+    capture.open().unwrap();
+    let mut frames = Vec::new();
+    while let Some(frame) = capture.next_frame().unwrap() {
+        frames.push((frame.number, frame.image));
+    }
+    frames
+}
+
+#[test]
+fn images_are_read_in_file_name_order() {
+    let mut capture = Capture::new(ImageDir::new(fixture("frames")));
+    let frames = read_all(&mut capture);
+    let numbers: Vec<u64> = frames.iter().map(|(number, _)| *number).collect();
+    assert_eq!(numbers, [1, 2, 3]);
+    for ((_, image), color) in frames.iter().zip([RED, GREEN, BLACK]) {
+        assert_color(image, color);
+    }
+}
+
+#[test]
+fn frame_skip_and_resolution_apply_to_any_source() {
+    let resolution = Some(Resolution { width: 16, height: 8 });
+    let mut capture = Capture::new(ImageDir::new(fixture("frames")))
+        .frame_skip(2)
+        .resolution(resolution);
+    let frames = read_all(&mut capture);
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].0, 2);
+    assert_eq!(frames[0].1.dimensions(), (16, 8));
+    assert_color(&frames[0].1, GREEN);
+
+    // Every run starts from the first frame.
+    assert_eq!(read_all(&mut capture).len(), 1);
+}
+
+#[test]
+fn video_frames_end_where_their_segments_do() {
+    let path = temp_dir("video").join("video.avi");
+    write_video(&path, &[RED, GREEN, BLACK]);
+
+    let mut reader = BufReader::new(fs::File::open(&path).unwrap());
+    let first = read_jpeg(&mut reader).unwrap().unwrap();
+    assert_eq!(first, jpeg_with_thumbnail(RED));
+
+    let mut capture = Capture::new(VideoFile::new(&path)).frame_skip(1);
+    let frames = read_all(&mut capture);
+    assert_eq!(frames.len(), 3);
+    for ((_, image), color) in frames.iter().zip([RED, GREEN, BLACK]) {
+        assert_eq!(image.dimensions(), (32, 32));
+        assert_color(image, color);
+    }
+}
+
+#[test]
+fn a_truncated_video_ends_with_its_last_whole_frame() {
+    let path = temp_dir("truncated").join("video.mjpeg");
+    write_video(&path, &[RED, GREEN]);
+    let data = fs::read(&path).unwrap();
+    fs::write(&path, &data[..data.len() - 10]).unwrap();
+
+    let mut capture = Capture::new(VideoFile::new(&path));
+    let frames = read_all(&mut capture);
+    assert_eq!(frames.len(), 1);
+    assert_color(&frames[0].1, RED);
+}
+
+#[test]
+fn other_video_codecs_are_not_read_as_an_empty_video() {
+    let path = temp_dir("h264").join("video.avi");
+    fs::write(&path, b"RIFF\0\0\0\0AVI 00dc\0\0\0\0\0\0\0\x01\x67\x42").unwrap();
+
+    let mut capture = Capture::new(VideoFile::new(&path));
+    capture.open().unwrap();
+    let err = capture.next_frame().unwrap_err();
+    assert!(err.to_string().contains("no Motion JPEG frame"), "{}", err);
+}
+
+#[test]
+fn frames_must_be_images_or_motion_jpeg_for_the_onnx_backend() {
+    let load = |backend: &str, frames: &Path| {
+        let plugin = serde_json::json!({ "detector": { "backend": backend } });
+        let frames = frames.to_str().unwrap().to_string();
+        ShowTimeConfig::load_with_env(Some(&plugin), None, |var| {
+            (var == "SHOWTIME_DETECTOR_FRAMES").then(|| frames.clone())
+        })
+    };
+
+    let err = load("python", &fixture("frames")).unwrap_err();
+    assert!(err.to_string().contains("only read by the onnx backend"), "{}", err);
+
+    if cfg!(feature = "onnx") {
+        load("onnx", &fixture("frames")).unwrap();
+        load("onnx", Path::new("session.MJPEG")).unwrap();
+        let err = load("onnx", Path::new("session.mp4")).unwrap_err();
+        assert!(err.to_string().contains("Motion JPEG"), "{}", err);
+    }
+}
+
+#[test]
+fn resolution_is_configurable() {
+    let config = ShowTimeConfig::load_with_env(None, None, |var| {
+        (var == "SHOWTIME_RESOLUTION").then(|| "640x480".to_string())
+    })
+    .unwrap();
+    assert_eq!(
+        config.detector.resolution,
+        Some(Resolution {
+            width: 640,
+            height: 480
+        })
+    );
+
+    for invalid in ["640", "640x", "0x480"] {
+        let result = ShowTimeConfig::load_with_env(None, None, |var| {
+            (var == "SHOWTIME_RESOLUTION").then(|| invalid.to_string())
+        });
+        assert!(result.is_err(), "{}", invalid);
+    }
+}
+
+#[cfg(feature = "onnx")]
+#[test]
+fn a_video_is_analysed_like_a_camera() {
+    use showtime_lib::detector::onnx::{OnnxDetector, OnnxSettings};
+    use showtime_lib::detector::{Detector, Next};
+    use showtime_lib::protocol::DetectionEvent;
+    use std::time::Duration;
+
+    let path = temp_dir("onnx").join("video.mjpeg");
+    write_video(&path, &[GREEN, RED, BLACK, RED]);
+    let settings = OnnxSettings {
+        conf_threshold: 0.5,
+        target_labels: vec!["cell phone".to_string()],
+        heartbeat_interval: Duration::from_secs(60),
+    };
+    let capture = Capture::new(VideoFile::new(&path)).frame_skip(2);
+    let mut detector = OnnxDetector::new(fixture("onnx/model.onnx"), capture, settings);
+
+    detector.start().unwrap();
+    let mut detections = Vec::new();
+    loop {
+        match detector.next_event(Duration::from_secs(30)) {
+            Next::Event(Ok(DetectionEvent::Detection { frame, .. })) => detections.push(frame),
+            Next::Event(event) => {
+                event.unwrap();
+            }
+            Next::Silent => panic!("detector went silent"),
+            Next::Ended => break,
+        }
+    }
+    assert_eq!(detector.wait(), "frames exhausted");
+    assert_eq!(detections, [2, 4]);
+}
@@ -0,0 +1,537 @@
+//! The command layer and detector loop under Tauri's mock runtime. Needs no
+//! display: enforcement lands on a `RecordingSurface`.
+
+use serde_json::{json, Value};
+use showtime_lib::audit::{self, AuditEvent, AuditLog};
+use showtime_lib::commands;
+use showtime_lib::config::{ProtectionConfig, ShowTimeConfig};
+use showtime_lib::detector::{MockDetector, Step};
+use showtime_lib::enforcement::{self, Effect, RecordingSurface};
+use showtime_lib::events;
+use showtime_lib::logs::DetectorLogs;
+use showtime_lib::pipeline;
+use showtime_lib::policy::{Action, PolicyRule, PolicyTable};
+use showtime_lib::protocol::DetectionEvent;
+use showtime_lib::registry::{self, ProtectionRegistry};
+use showtime_lib::status::ProtectionState;
+use showtime_lib::supervisor::FailurePolicy;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+use tauri::ipc::{CallbackFn, InvokeBody};
+use tauri::test::{mock_builder, mock_context, noop_assets, MockRuntime, INVOKE_KEY};
+use tauri::webview::InvokeRequest;
+use tauri::{App, Listener, Manager, WebviewWindow, WebviewWindowBuilder};
+
+struct Harness {
+    app: App<MockRuntime>,
+    main: WebviewWindow<MockRuntime>,
+    surface: RecordingSurface,
+    settings: ShowTimeConfig,
+    audit_path: PathBuf,
+}
+
+fn harness(name: &str, failure_policy: FailurePolicy) -> Harness {
+    let audit_path = std::env::temp_dir()
+        .join(format!("showtime-commands-{}-{}", name, std::process::id()))
+        .join("audit.jsonl");
+    let _ = fs::remove_file(&audit_path);
+
+    let settings = ShowTimeConfig {
+        protection: ProtectionConfig {
+            failure_policy,
+            max_restarts: 0,
+            vote_window: 3,
+            vote_threshold: 2,
+            min_hold_ms: 0,
+            restore_cooldown_ms: 0,
+            heartbeat_timeout_ms: 50,
+            startup_timeout_ms: 50,
+            ..ProtectionConfig::default()
+        },
+        policy: PolicyTable {
+            default_action: Action::LogOnly,
+            rules: vec![
+                PolicyRule {
+                    labels: vec!["cell phone".to_string()],
+                    min_confidence: 0.0,
+                    max_confidence: 1.0,
+                    action: Action::Blackout,
+                },
+                PolicyRule {
+                    labels: vec!["camera".to_string()],
+                    min_confidence: 0.0,
+                    max_confidence: 1.0,
+                    action: Action::Warning,
+                },
+                PolicyRule {
+                    labels: vec!["camcorder".to_string()],
+                    min_confidence: 0.0,
+                    max_confidence: 1.0,
+                    action: Action::Blur,
+                },
+            ],
+        },
+        ..ShowTimeConfig::default()
+    };
+
+    let app = mock_builder()
+        .invoke_handler(commands::handler())
+        .build(mock_context(noop_assets()))
+        .unwrap();
+    let surface = RecordingSurface::new();
+    pipeline::manage(&app, &settings, AuditLog::open(&audit_path).unwrap(), surface.clone());
+    registry::watch(app.handle());
+    let main = WebviewWindowBuilder::new(&app, "main", Default::default()).build().unwrap();
+    // Most tests only look at enforcement.
+    assert_eq!(surface.take(), [Effect::Protect("main".to_string())]);
+
+    Harness {
+        app,
+        main,
+        surface,
+        settings,
+        audit_path,
+    }
+}
+
+impl Harness {
+    fn invoke(&self, cmd: &str, args: Value) -> Result<Value, Value> {
+        tauri::test::get_ipc_response(
+            &self.main,
+            InvokeRequest {
+                cmd: cmd.into(),
+                callback: CallbackFn(0),
+                error: CallbackFn(1),
+                url: "http://tauri.localhost".parse().unwrap(),
+                body: InvokeBody::Json(args),
+                headers: Default::default(),
+                invoke_key: INVOKE_KEY.to_string(),
+            },
+        )
+        .map(|body| body.deserialize::<Value>().unwrap())
+    }
+
+    /// Plays `steps` as a single detector run and waits for the supervisor
+    /// to give up on it.
+    fn run_detector(&self, steps: Vec<Step>) {
+        pipeline::start_detector(
+            self.app.handle().clone(),
+            Box::new(MockDetector::new(steps)),
+            self.settings.protection.supervisor(),
+        )
+        .join()
+        .unwrap();
+    }
+
+    fn listen(&self, event: &str) -> Receiver<Value> {
+        let (tx, rx) = mpsc::channel();
+        self.app.listen_any(event, move |event| {
+            let _ = tx.send(serde_json::from_str(event.payload()).unwrap());
+        });
+        rx
+    }
+
+    fn status(&self) -> Value {
+        self.invoke("get_protection_status", json!({})).unwrap()
+    }
+
+    fn audit_events(&self) -> Vec<AuditEvent> {
+        audit::read_verified(&self.audit_path)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.event)
+            .collect()
+    }
+}
+
+fn detection_event(frame: u64, label: &str) -> DetectionEvent {
+    DetectionEvent::Detection {
+        frame,
+        label: label.to_string(),
+        confidence: 0.9,
+        timestamp: "2024-05-01T10:00:00".to_string(),
+        captured_at: None,
+    }
+}
+
+fn detection(frame: u64, label: &str) -> Step {
+    Step::Event(detection_event(frame, label))
+}
+
+fn clear(frame: u64) -> Step {
+    Step::Event(DetectionEvent::Clear {
+        frame,
+        timestamp: "2024-05-01T10:00:00".to_string(),
+        captured_at: None,
+    })
+}
+
+fn drain(rx: &Receiver<Value>) -> Vec<Value> {
+    std::iter::from_fn(|| rx.recv_timeout(Duration::from_millis(100)).ok()).collect()
+}
+
+fn lift() -> Vec<Effect> {
+    vec![Effect::HideOverlay, Effect::Eval(enforcement::LIFT_SCRIPT.to_string())]
+}
+
+#[test]
+fn greet_answers_over_ipc() {
+    let harness = harness("greet", FailurePolicy::FailOpen);
+    assert_eq!(
+        harness.invoke("greet", json!({ "name": "Ada" })),
+        Ok(json!("Hello, Ada! You've been greeted from Rust!"))
+    );
+}
+
+#[test]
+fn detections_black_out_and_clear_frames_restore() {
+    let harness = harness("detect", FailurePolicy::FailOpen);
+    let detections = harness.listen(events::DETECTION);
+    let changes = harness.listen(events::BLACKOUT_CHANGED);
+
+    harness.run_detector(vec![detection(1, "cell phone"), detection(2, "cell phone"), clear(3), clear(4)]);
+
+    let mut expected = vec![Effect::ShowOverlay(None)];
+    expected.extend(lift());
+    assert_eq!(harness.surface.effects(), expected);
+
+    let detections = drain(&detections);
+    assert_eq!(detections.len(), 2);
+    assert_eq!(detections[0]["label"], "cell phone");
+    assert_eq!(detections[0]["action"], "blackout");
+
+    let changes = drain(&changes);
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0]["action"], "blackout");
+    assert_eq!(changes[0]["reason"]["kind"], "detection");
+    assert_eq!(changes[1]["action"], Value::Null);
+
+    let status = harness.status();
+    assert_eq!(status["action"], Value::Null);
+    assert_eq!(status["detector"]["state"], "gave_up");
+    assert_eq!(status["last_detection"]["frame"], 2);
+}
+
+#[test]
+fn lighter_actions_run_page_scripts() {
+    let harness = harness("warning", FailurePolicy::FailOpen);
+    harness.run_detector(vec![detection(1, "camera"), detection(2, "camera"), clear(3), clear(4)]);
+
+    let mut expected = vec![Effect::Eval(
+        enforcement::page_script(Action::Warning).unwrap().to_string(),
+    )];
+    expected.extend(lift());
+    assert_eq!(harness.surface.effects(), expected);
+}
+
+#[test]
+fn malformed_lines_are_ignored() {
+    let harness = harness("malformed", FailurePolicy::FailOpen);
+    harness.run_detector(vec![
+        Step::Line("not json".to_string()),
+        Step::Line(r#"{"v":99,"type":"clear","frame":1,"timestamp":"x"}"#.to_string()),
+        detection(2, "cell phone"),
+    ]);
+
+    assert_eq!(harness.surface.effects(), []);
+    assert_eq!(harness.status()["last_detection"]["frame"], 2);
+}
+
+#[test]
+fn detector_failure_fails_closed() {
+    let harness = harness("fail-closed", FailurePolicy::FailClosed);
+    harness.run_detector(vec![Step::Exit("camera unplugged".to_string())]);
+
+    assert_eq!(harness.surface.effects(), [Effect::ShowOverlay(None)]);
+    let status = harness.status();
+    assert_eq!(status["action"], "blackout");
+    assert_eq!(status["reason"]["kind"], "detector_down");
+
+    let refused = harness.invoke("restore_window", json!({}));
+    assert_eq!(refused, Err(json!("the detector is down and protection fails closed")));
+    assert_eq!(harness.surface.effects(), [Effect::ShowOverlay(None)]);
+
+    let events = harness.audit_events();
+    assert!(matches!(events[0], AuditEvent::DetectorGaveUp { crashes: 1, .. }));
+    assert_eq!(events[1], AuditEvent::Enforced { action: Action::Blackout });
+    assert!(matches!(events[2], AuditEvent::RestoreRefused { .. }));
+}
+
+#[test]
+fn stalled_detector_fails_closed() {
+    let harness = harness("stalled", FailurePolicy::FailClosed);
+    harness.run_detector(vec![
+        Step::Event(DetectionEvent::Heartbeat {
+            frame: 30,
+            fps: 29.5,
+            timestamp: "2024-05-01T10:00:00".to_string(),
+        }),
+        Step::Sleep(Duration::from_millis(500)),
+        clear(31),
+    ]);
+
+    assert_eq!(harness.surface.effects(), [Effect::ShowOverlay(None)]);
+    let status = harness.status();
+    assert_eq!(status["action"], "blackout");
+    assert_eq!(status["reason"]["reason"], "no heartbeat for 50 ms");
+    assert_eq!(status["last_heartbeat"]["fps"], 29.5);
+
+    let events = harness.audit_events();
+    assert_eq!(events[0], AuditEvent::DetectorStalled { silent_ms: 50 });
+    assert_eq!(events[1], AuditEvent::Enforced { action: Action::Blackout });
+    assert!(matches!(&events[2], AuditEvent::DetectorGaveUp { reason, .. } if reason.starts_with("detector stalled")));
+}
+
+#[test]
+fn shutdown_stops_the_detector_without_failing_closed() {
+    let harness = harness("shutdown", FailurePolicy::FailClosed);
+    let detector = pipeline::start_detector(
+        harness.app.handle().clone(),
+        Box::new(MockDetector::new(vec![clear(1), Step::Sleep(Duration::from_secs(10))]).then(vec![clear(2)])),
+        harness.settings.protection.supervisor(),
+    );
+
+    let started = std::time::Instant::now();
+    detector.shutdown();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(harness.surface.effects(), []);
+    assert_eq!(harness.audit_events(), []);
+}
+
+#[test]
+fn manual_blackout_is_held_until_restored() {
+    let harness = harness("manual", FailurePolicy::FailOpen);
+
+    assert_eq!(harness.invoke("trigger_blackout", json!({})), Ok(Value::Null));
+    assert_eq!(harness.surface.take(), [Effect::ShowOverlay(None)]);
+    assert_eq!(harness.status()["reason"]["kind"], "manual");
+
+    // Blacking out again changes nothing on screen.
+    assert_eq!(harness.invoke("trigger_blackout", json!({})), Ok(Value::Null));
+    assert_eq!(harness.surface.take(), []);
+
+    assert_eq!(harness.invoke("restore_window", json!({})), Ok(Value::Null));
+    assert_eq!(harness.surface.take(), lift());
+    assert_eq!(harness.status()["action"], Value::Null);
+    assert_eq!(
+        harness.audit_events(),
+        [
+            AuditEvent::Enforced { action: Action::Blackout },
+            AuditEvent::Restored
+        ]
+    );
+}
+
+#[test]
+fn restore_is_refused_while_a_threat_is_reported() {
+    let harness = harness("refused", FailurePolicy::FailOpen);
+    harness
+        .app
+        .state::<ProtectionState>()
+        .observe(&detection_event(1, "cell phone"));
+
+    harness.invoke("trigger_blackout", json!({})).unwrap();
+    assert_eq!(
+        harness.invoke("restore_window", json!({})),
+        Err(json!("the detector still reports a threat"))
+    );
+    assert_eq!(harness.status()["action"], "blackout");
+}
+
+#[test]
+fn audit_log_is_verifiable_over_ipc() {
+    let harness = harness("audit", FailurePolicy::FailOpen);
+    harness.invoke("trigger_blackout", json!({})).unwrap();
+
+    let report = harness.invoke("verify_audit_log", json!({})).unwrap();
+    assert_eq!(report["records"], 1);
+    let exported = harness.invoke("export_audit_log", json!({})).unwrap();
+    assert_eq!(exported[0]["event"]["kind"], "enforced");
+}
+
+#[test]
+fn detector_logs_are_readable_over_ipc() {
+    let harness = harness("logs", FailurePolicy::FailOpen);
+    let logs = harness.app.state::<DetectorLogs>();
+    logs.record("loading model");
+    logs.record("Error: Cannot open camera");
+
+    let lines = harness.invoke("get_detector_logs", json!({})).unwrap();
+    assert_eq!(lines.as_array().unwrap().len(), 2);
+    assert_eq!(lines[1]["severity"], "error");
+    assert_eq!(lines[1]["message"], "Error: Cannot open camera");
+}
+
+#[test]
+fn metrics_count_detections_and_blackouts() {
+    let harness = harness("metrics", FailurePolicy::FailOpen);
+    harness.run_detector(vec![detection(1, "cell phone"), detection(2, "cell phone"), clear(3), clear(4)]);
+
+    let metrics = harness.invoke("get_metrics", json!({})).unwrap();
+    assert_eq!(metrics["frames"], 4);
+    assert_eq!(metrics["detections"]["cell phone"], 2);
+    assert_eq!(metrics["blackouts"], 1);
+    assert_eq!(metrics["enforcements"]["lift"], 1);
+    assert_eq!(metrics["enforcement_latency"]["count"], 2);
+    // The fixtures carry no capture time.
+    assert_eq!(metrics["blackout_latency"]["count"], 0);
+}
+
+#[test]
+fn new_windows_are_protected_and_catch_up_on_a_blackout() {
+    let harness = harness("windows", FailurePolicy::FailOpen);
+    WebviewWindowBuilder::new(&harness.app, "viewer-1", Default::default()).build().unwrap();
+    assert_eq!(harness.surface.take(), [Effect::Protect("viewer-1".to_string())]);
+
+    harness.invoke("trigger_blackout", json!({})).unwrap();
+    harness.surface.take();
+    WebviewWindowBuilder::new(&harness.app, "viewer-2", Default::default()).build().unwrap();
+    assert_eq!(
+        harness.surface.take(),
+        [Effect::Protect("viewer-2".to_string()), Effect::ShowOverlay(None)]
+    );
+
+    // The app's own windows are left alone.
+    WebviewWindowBuilder::new(&harness.app, "blackout-viewer-2", Default::default()).build().unwrap();
+    assert_eq!(harness.surface.effects(), []);
+    assert_eq!(
+        harness.app.state::<ProtectionRegistry>().labels(),
+        ["main", "viewer-1", "viewer-2"]
+    );
+}
+
+#[test]
+fn every_page_a_protected_window_loads_is_guarded() {
+    let harness = harness("navigate", FailurePolicy::FailOpen);
+    // The mock runtime never reports page loads; the hook is called as the
+    // real one would after the navigation.
+    harness.main.navigate("http://tauri.localhost/other".parse().unwrap()).unwrap();
+    registry::page_loaded(harness.app.handle(), "main");
+    assert_eq!(
+        harness.surface.take(),
+        [Effect::EvalIn("main".to_string(), enforcement::GUARD_SCRIPT.to_string())]
+    );
+
+    // The app's own windows are left alone.
+    WebviewWindowBuilder::new(&harness.app, "blackout-main", Default::default()).build().unwrap();
+    registry::page_loaded(harness.app.handle(), "blackout-main");
+    assert_eq!(harness.surface.effects(), []);
+}
+
+#[test]
+fn page_effects_come_back_after_a_navigation() {
+    let harness = harness("reload", FailurePolicy::FailOpen);
+    let protection = harness.app.state::<ProtectionState>();
+    for frame in [1, 2] {
+        if let Some(response) = protection.observe(&detection_event(frame, "camcorder")).0.enforcement {
+            pipeline::respond(harness.app.handle(), response);
+        }
+    }
+    let blur = enforcement::page_script(Action::Blur).unwrap().to_string();
+    assert_eq!(harness.surface.take(), [Effect::Eval(blur.clone())]);
+
+    // The new page starts without the blur; the protection state still has
+    // it in force.
+    harness.main.navigate("http://tauri.localhost/other".parse().unwrap()).unwrap();
+    registry::page_loaded(harness.app.handle(), "main");
+    assert_eq!(
+        harness.surface.take(),
+        [
+            Effect::EvalIn("main".to_string(), enforcement::GUARD_SCRIPT.to_string()),
+            Effect::EvalIn("main".to_string(), blur),
+        ]
+    );
+}
+
+/// Connects a detector that does whatever it is asked.
+#[cfg(unix)]
+fn attach_obliging_detector(harness: &Harness) {
+    use showtime_lib::detector::{read_message, write_message, DetectorControl};
+    use std::os::unix::net::UnixStream;
+
+    let (app_end, detector_end) = UnixStream::pair().unwrap();
+    harness
+        .app
+        .state::<DetectorControl>()
+        .attach(Box::new(app_end.try_clone().unwrap()), Box::new(app_end));
+    std::thread::spawn(move || {
+        let mut reader = detector_end.try_clone().unwrap();
+        let mut writer = detector_end;
+        while let Ok(Some(body)) = read_message(&mut reader) {
+            let request: Value = serde_json::from_slice(&body).unwrap();
+            let status = json!({
+                "paused": request["command"] == "pause",
+                "conf_threshold": 0.5,
+                "frame_skip": 2,
+                "frame": 40,
+                "fps": 30.0,
+            });
+            let response = json!({ "v": 1, "type": "response", "id": request["id"], "ok": true, "status": status });
+            write_message(&mut writer, &response).unwrap();
+        }
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn detector_settings_are_controlled_and_audited() {
+    let harness = harness("control", FailurePolicy::FailOpen);
+    let pause = json!({ "command": { "command": "pause" } });
+    assert_eq!(
+        harness.invoke("control_detector", pause.clone()),
+        Err(json!("detector is not connected"))
+    );
+
+    attach_obliging_detector(&harness);
+    let status = harness.invoke("control_detector", pause).unwrap();
+    assert_eq!(status["paused"], true);
+    let status = harness
+        .invoke("control_detector", json!({ "command": { "command": "status" } }))
+        .unwrap();
+    assert_eq!(status["frame"], 40);
+
+    // Only the change is audited.
+    assert_eq!(
+        harness.audit_events(),
+        [AuditEvent::DetectorControlled {
+            request: showtime_lib::detector::ControlCommand::Pause
+        }]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn a_paused_detector_is_treated_as_down() {
+    let harness = harness("pause", FailurePolicy::FailClosed);
+    let changes = harness.listen(events::BLACKOUT_CHANGED);
+    attach_obliging_detector(&harness);
+
+    harness
+        .invoke("control_detector", json!({ "command": { "command": "pause" } }))
+        .unwrap();
+    assert_eq!(harness.surface.take(), [Effect::ShowOverlay(None)]);
+    let status = harness.status();
+    assert_eq!(status["paused"], true);
+    assert_eq!(status["action"], "blackout");
+    assert_eq!(status["reason"]["kind"], "paused");
+    assert_eq!(
+        harness.invoke("restore_window", json!({})),
+        Err(json!("detection is paused and protection fails closed"))
+    );
+
+    // Resuming keeps the blackout until the detector sees a clear frame.
+    harness
+        .invoke("control_detector", json!({ "command": { "command": "resume" } }))
+        .unwrap();
+    assert_eq!(harness.surface.take(), []);
+    let status = harness.status();
+    assert_eq!(status["paused"], false);
+    assert_eq!(status["action"], "blackout");
+
+    let changes = drain(&changes);
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0]["paused"], true);
+    assert_eq!(changes[1]["paused"], false);
+}
@@ -0,0 +1,193 @@
+//! Layering of the settings: defaults, then the plugin section of
+//! tauri.conf.json, then the TOML file, then the environment.
+
+use serde_json::json;
+use showtime_lib::config::{ConfigError, Resolution, ShowTimeConfig};
+use showtime_lib::environment::VmPolicy;
+use showtime_lib::logs::LogFormat;
+use showtime_lib::supervisor::FailurePolicy;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn scratch_toml(name: &str, text: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("showtime-config-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("showtime.toml");
+    fs::write(&path, text).unwrap();
+    path
+}
+
+fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+    move |name| {
+        vars.iter()
+            .find(|(var, _)| *var == name)
+            .map(|(_, value)| value.to_string())
+    }
+}
+
+fn load(vars: &[(&str, &str)]) -> Result<ShowTimeConfig, ConfigError> {
+    ShowTimeConfig::load_with_env(None, None, env(vars))
+}
+
+#[test]
+fn each_layer_overrides_the_one_below() {
+    let plugin = json!({
+        "detector": {
+            "conf_threshold": 0.6,
+            "frame_skip": 3,
+            "camera_index": 1,
+            "model": "plugin.onnx",
+        },
+    });
+    let file = scratch_toml("layers", "[detector]\nconf_threshold = 0.7\nframe_skip = 4\n");
+    let vars = [("SHOWTIME_CONF_THRESHOLD", "0.8")];
+    let config = ShowTimeConfig::load_with_env(Some(&plugin), Some(&file), env(&vars)).unwrap();
+
+    assert_eq!(config.detector.conf_threshold, 0.8);
+    assert_eq!(config.detector.frame_skip, 4);
+    assert_eq!(config.detector.camera_index, 1);
+    assert_eq!(config.detector.model, Path::new("plugin.onnx"));
+    assert_eq!(config.detector.target_labels, ShowTimeConfig::default().detector.target_labels);
+}
+
+#[test]
+fn layers_merge_table_by_table() {
+    let plugin = json!({ "protection": { "vote_window": 7, "vote_threshold": 5 } });
+    let file = scratch_toml("merge", "[protection]\nvote_threshold = 4\n");
+    let config = ShowTimeConfig::load_with_env(Some(&plugin), Some(&file), |_| None).unwrap();
+
+    assert_eq!(config.protection.vote_window, 7);
+    assert_eq!(config.protection.vote_threshold, 4);
+    assert_eq!(config.protection.windows, ShowTimeConfig::default().protection.windows);
+}
+
+#[test]
+fn environment_wins_over_the_file_for_every_kind_of_value() {
+    let file = scratch_toml(
+        "env",
+        concat!(
+            "[detector]\n",
+            "camera_index = 2\n",
+            "target_labels = [\"camera\"]\n",
+            "[detector.resolution]\n",
+            "width = 320\n",
+            "height = 240\n",
+            "[logging]\n",
+            "format = \"pretty\"\n",
+        ),
+    );
+    let vars = [
+        ("SHOWTIME_CAMERA_INDEX", "3"),
+        ("SHOWTIME_TARGET_LABELS", " Cell Phone ,,mobile "),
+        ("SHOWTIME_RESOLUTION", "640x480"),
+        ("SHOWTIME_LOG_FORMAT", "json"),
+    ];
+    let config = ShowTimeConfig::load_with_env(None, Some(&file), env(&vars)).unwrap();
+
+    assert_eq!(config.detector.camera_index, 3);
+    assert_eq!(config.detector.target_labels, ["cell phone", "mobile"]);
+    assert_eq!(
+        config.detector.resolution,
+        Some(Resolution {
+            width: 640,
+            height: 480
+        })
+    );
+    assert_eq!(config.logging.format, LogFormat::Json);
+}
+
+#[test]
+fn protection_cannot_be_turned_off_from_the_environment() {
+    let vars = [
+        ("SHOWTIME_FAILURE_POLICY", "fail_open"),
+        ("SHOWTIME_REPLAY", "clear.jsonl"),
+        ("SHOWTIME_VM_POLICY", "allow"),
+        ("SHOWTIME_DETECTOR_BACKEND", "replay"),
+        ("SHOWTIME_PROTECTED_WINDOWS", "none"),
+    ];
+    let config = load(&vars).unwrap();
+    let defaults = ShowTimeConfig::default();
+
+    assert_eq!(config.protection.failure_policy, FailurePolicy::FailClosed);
+    assert_eq!(config.detector.backend, defaults.detector.backend);
+    assert_eq!(config.detector.replay_file, None);
+    assert_eq!(config.environment.on_virtual_machine, VmPolicy::Deny);
+    assert_eq!(config.protection.windows, defaults.protection.windows);
+}
+
+#[test]
+fn validation_sees_the_merged_result() {
+    let file = scratch_toml("validate", "[detector]\nconf_threshold = 1.5\n");
+    assert!(matches!(
+        ShowTimeConfig::load_with_env(None, Some(&file), |_| None),
+        Err(ConfigError::Inconsistent(_))
+    ));
+    let vars = [("SHOWTIME_CONF_THRESHOLD", "0.9")];
+    let config = ShowTimeConfig::load_with_env(None, Some(&file), env(&vars)).unwrap();
+    assert_eq!(config.detector.conf_threshold, 0.9);
+}
+
+#[test]
+fn unparseable_overrides_name_the_variable() {
+    for (var, value) in [
+        ("SHOWTIME_CAMERA_INDEX", "-1"),
+        ("SHOWTIME_RESOLUTION", "640"),
+        ("SHOWTIME_RESOLUTION", "640xtall"),
+        ("SHOWTIME_CONF_THRESHOLD", "high"),
+        ("SHOWTIME_FRAME_SKIP", "two"),
+        ("SHOWTIME_LOG_FORMAT", "xml"),
+        ("SHOWTIME_METRICS_ADDR", "localhost"),
+    ] {
+        match load(&[(var, value)]) {
+            Err(ConfigError::Env(name, got)) => {
+                assert_eq!((name, got.as_str()), (var, value));
+            }
+            other => panic!("{}={}: {:?}", var, value, other),
+        }
+    }
+}
+
+#[test]
+fn overrides_that_parse_are_still_validated() {
+    for (var, value) in [
+        ("SHOWTIME_CONF_THRESHOLD", "1.5"),
+        ("SHOWTIME_FRAME_SKIP", "0"),
+        ("SHOWTIME_TARGET_LABELS", " , "),
+        ("SHOWTIME_RESOLUTION", "0x480"),
+        ("SHOWTIME_METRICS_ADDR", "0.0.0.0:9464"),
+    ] {
+        assert!(
+            matches!(load(&[(var, value)]), Err(ConfigError::Inconsistent(_))),
+            "{}={}",
+            var,
+            value
+        );
+    }
+}
+
+#[test]
+fn invalid_layers_name_their_source() {
+    let plugin = json!({ "detector": { "conf_treshold": 0.6 } });
+    match ShowTimeConfig::load_with_env(Some(&plugin), None, |_| None) {
+        Err(ConfigError::Invalid(source, _)) => assert_eq!(source, "tauri.conf.json"),
+        other => panic!("{:?}", other),
+    }
+
+    let file = scratch_toml("mistyped", "[detector]\nframe_skip = \"2\"\n");
+    match ShowTimeConfig::load_with_env(None, Some(&file), |_| None) {
+        Err(ConfigError::Invalid(source, _)) => assert_eq!(source, file.display().to_string()),
+        other => panic!("{:?}", other),
+    }
+
+    let file = scratch_toml("syntax", "[detector\n");
+    assert!(matches!(
+        ShowTimeConfig::load_with_env(None, Some(&file), |_| None),
+        Err(ConfigError::Toml(..))
+    ));
+
+    let missing = file.with_file_name("missing.toml");
+    assert!(matches!(
+        ShowTimeConfig::load_with_env(None, Some(&missing), |_| None),
+        Err(ConfigError::Io(..))
+    ));
+}
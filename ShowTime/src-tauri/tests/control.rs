@@ -0,0 +1,133 @@
+//! The control channel, against detector.py's own server in front of a fake
+//! capture loop. Unix only, as the Windows side speaks named pipes.
+#![cfg(unix)]
+
+use showtime_lib::detector::{
+    find_program, read_message, write_message, ControlCommand, ControlError, Detector, DetectorControl, Next,
+    PythonDetector, MAX_MESSAGE_LEN,
+};
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn fake_detector(control: &DetectorControl) -> PythonDetector {
+    let python = find_program("python3", std::env::var_os("PATH")).expect("python3 on PATH");
+    let script = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/control/fake_detector.py");
+    PythonDetector::new(python, script)
+        .args(["--conf-threshold", "0.5", "--frame-skip", "2"])
+        .control(control.clone())
+}
+
+fn wait_for_connection(control: &DetectorControl) {
+    let started = Instant::now();
+    while !control.is_connected() {
+        assert!(started.elapsed() < TIMEOUT, "detector never took the connection");
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Runs until the detector's stdout closes and returns why the run ended.
+fn drain(detector: &mut PythonDetector) -> String {
+    loop {
+        match detector.next_event(TIMEOUT) {
+            Next::Event(event) => {
+                event.unwrap();
+            }
+            Next::Silent => panic!("detector went silent"),
+            Next::Ended => return detector.wait(),
+        }
+    }
+}
+
+#[test]
+fn messages_are_length_prefixed() {
+    let mut buffer = Vec::new();
+    write_message(&mut buffer, &ControlCommand::SetFrameSkip { value: 3 }).unwrap();
+    let body = br#"{"command":"set_frame_skip","value":3}"#;
+    assert_eq!(buffer[..4], (body.len() as u32).to_be_bytes());
+    assert_eq!(buffer[4..], body[..]);
+
+    let mut reader = Cursor::new(buffer);
+    assert_eq!(read_message(&mut reader).unwrap().unwrap(), body);
+    assert_eq!(read_message(&mut reader).unwrap(), None);
+
+    let oversized = ((MAX_MESSAGE_LEN + 1) as u32).to_be_bytes();
+    assert!(read_message(&mut Cursor::new(oversized)).is_err());
+}
+
+#[test]
+fn settings_change_without_a_restart() {
+    let control = DetectorControl::default();
+    let pushed = Arc::new(Mutex::new(Vec::new()));
+    let sink = pushed.clone();
+    control.on_status(move |status| sink.lock().unwrap().push(status.clone()));
+
+    assert!(matches!(
+        control.request(ControlCommand::Status, TIMEOUT),
+        Err(ControlError::NotConnected)
+    ));
+
+    let mut detector = fake_detector(&control);
+    detector.start().unwrap();
+    let pid = detector.pid();
+    wait_for_connection(&control);
+
+    let status = control.request(ControlCommand::Status, TIMEOUT).unwrap();
+    assert_eq!((status.paused, status.conf_threshold, status.frame_skip), (false, 0.5, 2));
+
+    let status = control
+        .request(ControlCommand::SetConfThreshold { value: 0.7 }, TIMEOUT)
+        .unwrap();
+    assert_eq!(status.conf_threshold, 0.7);
+    assert_eq!(control.request(ControlCommand::SetFrameSkip { value: 5 }, TIMEOUT).unwrap().frame_skip, 5);
+    assert!(control.request(ControlCommand::Pause, TIMEOUT).unwrap().paused);
+    assert!(!control.request(ControlCommand::Resume, TIMEOUT).unwrap().paused);
+
+    match control.request(ControlCommand::SetFrameSkip { value: 0 }, TIMEOUT) {
+        Err(ControlError::Rejected(reason)) => assert!(reason.contains("at least 1"), "{}", reason),
+        other => panic!("expected a refusal, got {:?}", other),
+    }
+    assert_eq!(detector.pid(), pid);
+
+    // Every change was pushed as well, after the status sent on connecting.
+    let started = Instant::now();
+    while pushed.lock().unwrap().len() < 5 && started.elapsed() < TIMEOUT {
+        thread::sleep(Duration::from_millis(20));
+    }
+    let pushed = pushed.lock().unwrap();
+    let paused: Vec<bool> = pushed.iter().map(|status| status.paused).collect();
+    assert_eq!(paused, [false, false, false, true, false]);
+    assert_eq!(control.status().unwrap().frame_skip, 5);
+
+    control.request(ControlCommand::Shutdown, TIMEOUT).unwrap();
+    assert_eq!(drain(&mut detector), "detector exited with exit status: 0");
+    assert!(!control.is_connected());
+}
+
+#[test]
+fn settings_survive_a_restart() {
+    let control = DetectorControl::default();
+    let mut detector = fake_detector(&control);
+
+    detector.start().unwrap();
+    wait_for_connection(&control);
+    control.request(ControlCommand::Pause, TIMEOUT).unwrap();
+    control
+        .request(ControlCommand::SetConfThreshold { value: 0.8 }, TIMEOUT)
+        .unwrap();
+    control.request(ControlCommand::Shutdown, TIMEOUT).unwrap();
+    drain(&mut detector);
+
+    detector.start().unwrap();
+    wait_for_connection(&control);
+    let status = control.request(ControlCommand::Status, TIMEOUT).unwrap();
+    assert!(status.paused);
+    assert_eq!((status.conf_threshold, status.frame_skip), (0.8, 2));
+
+    detector.stop();
+    detector.wait();
+}
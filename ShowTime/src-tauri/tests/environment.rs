@@ -0,0 +1,94 @@
+use showtime_lib::config::{EnvironmentConfig, ShowTimeConfig};
+use showtime_lib::environment::{Admission, EnvironmentReport, Facts, SignalSource, VmPolicy};
+use std::path::Path;
+
+fn fixture(name: &str) -> EnvironmentReport {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/environment")
+        .join(format!("{}.json", name));
+    let facts: Facts = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    EnvironmentReport::from_facts(facts)
+}
+
+fn admit(policy: VmPolicy, report: &EnvironmentReport) -> Admission {
+    EnvironmentConfig {
+        on_virtual_machine: policy,
+        ..EnvironmentConfig::default()
+    }
+    .admit(report)
+}
+
+#[test]
+fn bare_metal_has_no_signals() {
+    let report = fixture("bare_metal");
+    assert!(report.signals.is_empty());
+    assert_eq!(report.confidence, 0.0);
+    assert_eq!(admit(VmPolicy::Deny, &report), Admission::Allowed);
+}
+
+#[test]
+fn guests_are_denied_by_default() {
+    for name in ["kvm_guest", "hyperv_guest", "xen_pv_guest", "apple_silicon_vm"] {
+        let report = fixture(name);
+        assert!(report.confidence >= 0.8, "{}: {}", name, report.confidence);
+        assert_eq!(
+            EnvironmentConfig::default().admit(&report),
+            Admission::Denied,
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn every_signal_is_reported_with_its_source() {
+    let report = fixture("kvm_guest");
+    let sources: Vec<SignalSource> = report.signals.iter().map(|signal| signal.source).collect();
+    assert_eq!(
+        sources,
+        [
+            SignalSource::HypervisorBit,
+            SignalSource::HypervisorVendor,
+            SignalSource::CpuBrand,
+            SignalSource::Firmware,
+        ]
+    );
+    assert!(report.signals.iter().all(|signal| (0.0..=1.0).contains(&signal.confidence)));
+}
+
+#[test]
+fn hyper_v_on_bare_metal_stays_below_the_threshold() {
+    // Windows with virtualization-based security runs on top of Hyper-V.
+    let report = fixture("windows_vbs_host");
+    assert!(!report.signals.is_empty());
+    assert!(!report.is_virtual_machine(EnvironmentConfig::default().min_confidence));
+    assert_eq!(admit(VmPolicy::Deny, &report), Admission::Allowed);
+}
+
+#[test]
+fn policy_decides_what_a_guest_may_do() {
+    let report = fixture("kvm_guest");
+    assert_eq!(admit(VmPolicy::Allow, &report), Admission::Allowed);
+    assert_eq!(admit(VmPolicy::Warn, &report), Admission::Warned);
+    assert!(admit(VmPolicy::Warn, &report).is_allowed());
+    assert!(!admit(VmPolicy::Deny, &report).is_allowed());
+}
+
+#[test]
+fn environment_policy_is_configurable() {
+    let config = ShowTimeConfig::load_with_env(
+        Some(&serde_json::json!({ "environment": { "on_virtual_machine": "warn", "min_confidence": 0.99 } })),
+        None,
+        |_| None,
+    )
+    .unwrap();
+    assert_eq!(config.environment.on_virtual_machine, VmPolicy::Warn);
+    assert_eq!(config.environment.admit(&fixture("xen_pv_guest")), Admission::Allowed);
+
+    let invalid = ShowTimeConfig::load_with_env(
+        Some(&serde_json::json!({ "environment": { "min_confidence": 1.5 } })),
+        None,
+        |_| None,
+    );
+    assert!(invalid.is_err());
+}
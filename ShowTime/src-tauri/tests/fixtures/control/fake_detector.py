@@ -0,0 +1,40 @@
+"""detector.py's control server in front of a fake capture loop, for tests/control.rs.
+
+The camera and model libraries are stubbed out, so this runs on a bare
+Python install.
+"""
+import argparse
+import pathlib
+import sys
+import threading
+import time
+import types
+from datetime import datetime
+
+sys.modules["cv2"] = types.ModuleType("cv2")
+sys.modules["ultralytics"] = types.SimpleNamespace(YOLO=None)
+sys.path.insert(0, str(pathlib.Path(__file__).resolve().parents[3] / "detector"))
+
+import detector  # noqa: E402
+
+
+def main():
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--conf-threshold", type=float, default=0.5)
+    parser.add_argument("--frame-skip", type=int, default=2)
+    parser.add_argument("--control", required=True)
+    args = parser.parse_args()
+
+    controls = detector.Controls(args.conf_threshold, args.frame_skip)
+    threading.Thread(target=detector.serve_control, args=(args.control, controls), daemon=True).start()
+    frame = 0
+    while detector.running:
+        frame += 1
+        with controls.lock:
+            controls.frame = frame
+        detector.emit("heartbeat", frame=frame, fps=20.0, timestamp=datetime.now().isoformat())
+        time.sleep(0.05)
+
+
+if __name__ == "__main__":
+    main()
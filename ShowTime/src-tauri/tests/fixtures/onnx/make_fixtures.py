@@ -0,0 +1,120 @@
+"""Writes the ONNX detector test fixtures with nothing but the standard library.
+
+model.onnx has YOLOv8's interface, images [1, 3, 640, 640] in and
+output0 [1, 84, 1] out, but a trivial body: one anchor with a fixed box
+whose "cell phone" score is the mean red level of the input and whose
+"person" score is the mean green level. frames/ holds still images to run
+it on, in file name order.
+
+    python tests/fixtures/onnx/make_fixtures.py
+"""
+
+import struct
+import zlib
+from pathlib import Path
+
+HERE = Path(__file__).resolve().parent
+FRAMES = HERE.parent / "frames"
+CLASSES = 80
+PERSON = 0
+CELL_PHONE = 67
+
+
+# Protocol buffer encoding, just what ONNX needs.
+
+def varint(value):
+    out = bytearray()
+    value &= (1 << 64) - 1
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            out.append(byte | 0x80)
+        else:
+            out.append(byte)
+            return bytes(out)
+
+
+def field_varint(number, value):
+    return varint(number << 3) + varint(value)
+
+
+def field_bytes(number, data):
+    if isinstance(data, str):
+        data = data.encode()
+    return varint(number << 3 | 2) + varint(len(data)) + data
+
+
+def tensor(name, dims, floats=None, int64s=None):
+    out = b"".join(field_varint(1, dim) for dim in dims)
+    if floats is not None:
+        out += field_varint(2, 1)  # FLOAT
+        out += field_bytes(9, struct.pack(f"<{len(floats)}f", *floats))
+    else:
+        out += field_varint(2, 7)  # INT64
+        out += field_bytes(9, struct.pack(f"<{len(int64s)}q", *int64s))
+    return out + field_bytes(8, name)
+
+
+def value_info(name, dims):
+    shape = b"".join(field_bytes(1, field_varint(1, dim)) for dim in dims)
+    tensor_type = field_varint(1, 1) + field_bytes(2, shape)
+    return field_bytes(1, name) + field_bytes(2, field_bytes(1, tensor_type))
+
+
+def node(op, inputs, outputs):
+    out = b"".join(field_bytes(1, name) for name in inputs)
+    out += b"".join(field_bytes(2, name) for name in outputs)
+    return out + field_bytes(4, op)
+
+
+def model():
+    channels = 4 + CLASSES
+    weights = [0.0] * (channels * 3)
+    weights[(4 + CELL_PHONE) * 3 + 0] = 1.0
+    weights[(4 + PERSON) * 3 + 1] = 1.0
+    bias = [320.0, 320.0, 100.0, 200.0] + [0.0] * CLASSES
+
+    graph = b"".join(
+        [
+            field_bytes(1, node("GlobalAveragePool", ["images"], ["pooled"])),
+            field_bytes(1, node("Conv", ["pooled", "weights", "bias"], ["scores"])),
+            field_bytes(1, node("Reshape", ["scores", "shape"], ["output0"])),
+            field_bytes(2, "fixture"),
+            field_bytes(5, tensor("weights", [channels, 3, 1, 1], floats=weights)),
+            field_bytes(5, tensor("bias", [channels], floats=bias)),
+            field_bytes(5, tensor("shape", [3], int64s=[1, channels, 1])),
+            field_bytes(11, value_info("images", [1, 3, 640, 640])),
+            field_bytes(12, value_info("output0", [1, channels, 1])),
+        ]
+    )
+    opset = field_bytes(1, "") + field_varint(2, 13)
+    return field_varint(1, 8) + field_bytes(2, "showtime-fixtures") + field_bytes(7, graph) + field_bytes(8, opset)
+
+
+def png(width, height, rgb):
+    def chunk(kind, data):
+        body = kind + data
+        return struct.pack(">I", len(data)) + body + struct.pack(">I", zlib.crc32(body))
+
+    row = b"\x00" + bytes(rgb) * width
+    header = struct.pack(">IIBBBBB", width, height, 8, 2, 0, 0, 0)
+    return (
+        b"\x89PNG\r\n\x1a\n"
+        + chunk(b"IHDR", header)
+        + chunk(b"IDAT", zlib.compress(row * height))
+        + chunk(b"IEND", b"")
+    )
+
+
+def main():
+    (HERE / "model.onnx").write_bytes(model())
+    FRAMES.mkdir(exist_ok=True)
+    for name, rgb in [("1-red.png", (255, 0, 0)), ("2-green.png", (0, 255, 0)), ("3-black.png", (0, 0, 0))]:
+        (FRAMES / name).write_bytes(png(32, 32, rgb))
+    # Twice as wide as high, to exercise letterboxing.
+    (HERE / "wide-red.png").write_bytes(png(64, 32, (255, 0, 0)))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,134 @@
+use showtime_lib::config::{DetectorBackend, DetectorConfig};
+use showtime_lib::detector::{find_program, Launch, LaunchError, SIDECAR_NAME};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("showtime-launch-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn touch(path: &Path) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, "").unwrap();
+}
+
+fn exe(name: &str) -> String {
+    format!("{}{}", name, env::consts::EXE_SUFFIX)
+}
+
+fn config(backend: DetectorBackend) -> DetectorConfig {
+    DetectorConfig {
+        backend,
+        ..DetectorConfig::default()
+    }
+}
+
+#[test]
+fn sidecar_is_found_next_to_the_executable() {
+    let dir = temp_dir("sidecar");
+    let config = config(DetectorBackend::Sidecar);
+
+    let missing = Launch::locate(&config, &dir, &dir, None).unwrap();
+    let expected = dir.join(exe(SIDECAR_NAME));
+    assert_eq!(missing, Err(LaunchError::SidecarMissing(expected.clone())));
+    assert!(missing.unwrap_err().to_string().contains("build_sidecar.py"));
+
+    touch(&expected);
+    assert_eq!(Launch::locate(&config, &dir, &dir, None), Some(Ok(Launch::Sidecar(expected))));
+}
+
+#[test]
+fn script_is_found_in_resources_and_interpreter_on_path() {
+    let dir = temp_dir("script");
+    let resources = dir.join("resources");
+    let bin = dir.join("bin");
+    let path_var = Some(env::join_paths([&bin]).unwrap());
+    let config = config(DetectorBackend::Python);
+
+    assert_eq!(
+        Launch::locate(&config, &dir, &resources, path_var.clone()),
+        Some(Err(LaunchError::ScriptMissing(resources.join("detector/detector.py"))))
+    );
+
+    touch(&resources.join("detector/detector.py"));
+    assert!(matches!(
+        Launch::locate(&config, &dir, &resources, path_var.clone()),
+        Some(Err(LaunchError::InterpreterMissing(_)))
+    ));
+
+    touch(&bin.join(exe("python")));
+    assert_eq!(
+        Launch::locate(&config, &dir, &resources, path_var.clone()),
+        Some(Ok(Launch::Script {
+            interpreter: bin.join(exe("python")),
+            script: resources.join("detector/detector.py"),
+        }))
+    );
+
+    let pinned = DetectorConfig {
+        interpreter: Some("python3.11".to_string()),
+        ..config
+    };
+    assert_eq!(
+        Launch::locate(&pinned, &dir, &resources, path_var),
+        Some(Err(LaunchError::InterpreterMissing(vec!["python3.11".to_string()])))
+    );
+}
+
+#[test]
+fn programs_given_as_paths_are_not_searched_for() {
+    let dir = temp_dir("paths");
+    let program = dir.join("venv").join(exe("python"));
+    let path_var = Some(env::join_paths([&dir]).unwrap());
+
+    assert_eq!(find_program(program.to_str().unwrap(), path_var.clone()), None);
+    touch(&program);
+    assert_eq!(find_program(program.to_str().unwrap(), path_var), Some(program));
+}
+
+#[test]
+fn replay_needs_no_program() {
+    assert_eq!(Launch::locate(&config(DetectorBackend::Replay), Path::new("."), Path::new("."), None), None);
+}
+
+#[test]
+fn model_is_found_in_resources() {
+    let dir = temp_dir("model");
+    let config = DetectorConfig {
+        frames: Some(dir.join("frames")),
+        ..config(DetectorBackend::Onnx)
+    };
+
+    let model = dir.join("detector/yolov8n.onnx");
+    assert_eq!(
+        Launch::locate(&config, &dir, &dir, None),
+        Some(Err(LaunchError::ModelMissing(model.clone())))
+    );
+    touch(&model);
+    assert_eq!(
+        Launch::locate(&config, &dir, &dir, None),
+        Some(Ok(Launch::Model(model)))
+    );
+}
+
+/// A fresh clone has no sidecar, so only the bundle configuration may ask
+/// tauri-build for it.
+#[test]
+fn only_bundles_need_the_sidecar() {
+    let read = |name: &str| -> serde_json::Value {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(name);
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+    };
+    let base = read("tauri.conf.json");
+    let bundle = read("tauri.bundle.conf.json");
+
+    assert_eq!(DetectorConfig::default().backend, DetectorBackend::Python);
+    assert!(base["bundle"].get("externalBin").is_none());
+    assert_eq!(base["plugins"]["showtime"]["detector"]["backend"], "python");
+    assert_eq!(bundle["bundle"]["externalBin"][0], format!("binaries/{}", SIDECAR_NAME));
+    assert_eq!(bundle["plugins"]["showtime"]["detector"]["backend"], "sidecar");
+}
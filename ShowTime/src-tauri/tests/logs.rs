@@ -0,0 +1,35 @@
+use showtime_lib::logs::{DetectorLogs, Severity};
+
+#[test]
+fn severity_is_read_from_the_line() {
+    let logs = DetectorLogs::new(10);
+    assert_eq!(logs.record("Error: Cannot open camera"), Severity::Error);
+    assert_eq!(logs.record("WARNING ⚠️ torch is slow"), Severity::Warn);
+    assert_eq!(logs.record("DEBUG:ultralytics:loaded"), Severity::Debug);
+    assert_eq!(logs.record("[info] ready"), Severity::Info);
+    assert_eq!(logs.record("Stopping detection..."), Severity::Info);
+}
+
+#[test]
+fn tracebacks_are_errors_to_the_last_line() {
+    let logs = DetectorLogs::new(10);
+    for line in [
+        "Traceback (most recent call last):",
+        "  File \"detector.py\", line 62, in main",
+        "    ret, frame = cap.read()",
+        "cv2.error: OpenCV(4.9.0) camera disconnected",
+    ] {
+        assert_eq!(logs.record(line), Severity::Error);
+    }
+    assert_eq!(logs.record("restarting"), Severity::Info);
+}
+
+#[test]
+fn only_the_last_lines_are_kept() {
+    let logs = DetectorLogs::new(2);
+    for line in ["one", "two", "three\n"] {
+        logs.record(line);
+    }
+    let kept: Vec<_> = logs.lines().into_iter().map(|line| line.message).collect();
+    assert_eq!(kept, ["two", "three"]);
+}
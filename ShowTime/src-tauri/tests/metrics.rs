@@ -0,0 +1,105 @@
+use chrono::NaiveDateTime;
+use showtime_lib::metrics::{self, Metrics};
+use showtime_lib::policy::Action;
+use showtime_lib::protection::Enforcement;
+use showtime_lib::protocol::DetectionEvent;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+fn detection(frame: u64, label: &str, captured_at: &str) -> DetectionEvent {
+    DetectionEvent::Detection {
+        frame,
+        label: label.to_string(),
+        confidence: 0.9,
+        timestamp: "2024-05-01T10:00:00.100".to_string(),
+        captured_at: Some(captured_at.to_string()),
+    }
+}
+
+fn at(timestamp: &str) -> NaiveDateTime {
+    timestamp.parse().unwrap()
+}
+
+#[test]
+fn frames_are_timed_from_capture() {
+    let metrics = Metrics::default();
+
+    let latency = metrics.record_frame(
+        &detection(1, "cell phone", "2024-05-01T10:00:00.000"),
+        at("2024-05-01T10:00:00.080"),
+    );
+    assert_eq!(latency, Some(Duration::from_millis(80)));
+    // A second box in the same frame is a detection, not another frame.
+    metrics.record_frame(
+        &detection(1, "camera", "2024-05-01T10:00:00.000"),
+        at("2024-05-01T10:00:00.090"),
+    );
+    metrics.record_frame(
+        &DetectionEvent::Clear {
+            frame: 2,
+            timestamp: "2024-05-01T10:00:00.200".to_string(),
+            captured_at: None,
+        },
+        at("2024-05-01T10:00:00.210"),
+    );
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.frames, 2);
+    assert_eq!(snapshot.detections["cell phone"], 1);
+    assert_eq!(snapshot.detections["camera"], 1);
+    assert_eq!(snapshot.detector_latency.count, 1);
+    assert_eq!(snapshot.detector_latency.buckets[3].count, 1);
+    assert_eq!(snapshot.detector_latency.buckets[2].count, 0);
+}
+
+#[test]
+fn only_blackouts_count_towards_blackout_latency() {
+    let metrics = Metrics::default();
+    let blackout = Enforcement::Apply(Action::Blackout);
+
+    metrics.record_enforcement(blackout);
+    metrics.record_latency(blackout, Duration::from_millis(5), Some(Duration::from_millis(120)));
+    metrics.record_enforcement(Enforcement::Lift);
+    metrics.record_latency(Enforcement::Lift, Duration::from_millis(5), Some(Duration::from_millis(100)));
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.blackouts, 1);
+    assert_eq!(snapshot.enforcements["blackout"], 1);
+    assert_eq!(snapshot.enforcements["lift"], 1);
+    assert_eq!(snapshot.enforcement_latency.count, 2);
+    assert_eq!(snapshot.blackout_latency.count, 1);
+    assert!((snapshot.blackout_latency.sum - 0.12).abs() < 1e-9);
+}
+
+#[test]
+fn prometheus_text_is_served_on_loopback() {
+    let metrics = Metrics::default();
+    metrics.record_frame(
+        &detection(1, "cell \"phone\"", "2024-05-01T10:00:00.000"),
+        at("2024-05-01T10:00:00.030"),
+    );
+    let text = metrics.render_prometheus();
+    assert!(text.contains("# TYPE showtime_detections_total counter\n"));
+    assert!(text.contains("showtime_detections_total{label=\"cell \\\"phone\\\"\"} 1\n"));
+    assert!(text.contains("showtime_detector_latency_seconds_bucket{le=\"0.05\"} 1\n"));
+    assert!(text.contains("showtime_detector_latency_seconds_bucket{le=\"+Inf\"} 1\n"));
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+    metrics::serve_prometheus(addr, move || text.clone()).unwrap();
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("showtime_frames_total 1\n"));
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 404"));
+}
@@ -0,0 +1,188 @@
+#![cfg(feature = "onnx")]
+//! The in-process detector on still images, with a fixture model that has
+//! YOLOv8's interface. See tests/fixtures/onnx/make_fixtures.py.
+
+use image::RgbImage;
+use showtime_lib::capture::{Capture, FrameSource, ImageDir};
+use showtime_lib::detector::onnx::{self, OnnxDetector, OnnxSettings, YoloModel, COCO_LABELS, INPUT_SIZE};
+use showtime_lib::detector::{Detector, Next};
+use showtime_lib::protocol::DetectionEvent;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+fn settings(conf_threshold: f32) -> OnnxSettings {
+    OnnxSettings {
+        conf_threshold,
+        target_labels: vec!["cell phone".to_string(), "camera".to_string()],
+        heartbeat_interval: Duration::from_secs(60),
+    }
+}
+
+fn frames() -> Capture {
+    Capture::new(ImageDir::new(fixture("frames")))
+}
+
+/// Every event of one run.
+fn run(detector: &mut OnnxDetector) -> (Vec<DetectionEvent>, String) {
+    detector.start().unwrap();
+    let mut events = Vec::new();
+    loop {
+        match detector.next_event(Duration::from_secs(30)) {
+            Next::Event(event) => events.push(event.unwrap()),
+            Next::Silent => panic!("detector went silent"),
+            Next::Ended => return (events, detector.wait()),
+        }
+    }
+}
+
+#[test]
+fn letterbox_keeps_the_aspect_ratio_and_pads_grey() {
+    let image = image::open(fixture("onnx/wide-red.png")).unwrap().to_rgb8();
+    let tensor = onnx::letterbox(&image);
+    let input = tensor.to_array_view::<f32>().unwrap();
+    let size = INPUT_SIZE as usize;
+    assert_eq!(input.shape(), [1, 3, size, size]);
+
+    // 64x32 scales to 640x320, centered with 160 rows of padding above and
+    // below.
+    let grey = 114.0 / 255.0;
+    assert_eq!(input[[0, 0, 0, 0]], grey);
+    assert_eq!(input[[0, 1, 159, 320]], grey);
+    assert_eq!(input[[0, 0, 160, 0]], 1.0);
+    assert_eq!(input[[0, 1, 320, 320]], 0.0);
+    assert_eq!(input[[0, 0, 479, 639]], 1.0);
+    assert_eq!(input[[0, 2, 480, 639]], grey);
+}
+
+#[test]
+fn decode_keeps_the_best_class_per_anchor_and_suppresses_overlaps() {
+    let channels = 4 + COCO_LABELS.len();
+    let anchors = 4;
+    let mut output = vec![0.0; channels * anchors];
+    let mut set = |channel: usize, anchor: usize, value: f32| output[channel * anchors + anchor] = value;
+    let phone = 4 + 67;
+    let person = 4;
+    // Two overlapping phones, a person at the same spot and a weak phone
+    // elsewhere.
+    for (anchor, bbox) in [[100.0, 100.0, 50.0, 80.0], [102.0, 101.0, 50.0, 80.0], [100.0, 100.0, 50.0, 80.0], [400.0, 400.0, 20.0, 20.0]]
+        .into_iter()
+        .enumerate()
+    {
+        for (channel, value) in bbox.into_iter().enumerate() {
+            set(channel, anchor, value);
+        }
+    }
+    set(phone, 0, 0.6);
+    set(person, 0, 0.1);
+    set(phone, 1, 0.9);
+    set(person, 2, 0.8);
+    set(phone, 2, 0.3);
+    set(phone, 3, 0.2);
+
+    let candidates = onnx::decode(&output, channels);
+    let found: Vec<(&str, f32)> = candidates
+        .iter()
+        .map(|candidate| (candidate.label(), candidate.confidence))
+        .collect();
+    assert_eq!(found, [("cell phone", 0.9), ("person", 0.8)]);
+    assert_eq!(candidates[0].bbox, [102.0, 101.0, 50.0, 80.0]);
+}
+
+#[test]
+fn model_scores_still_images() {
+    let model = YoloModel::load(&fixture("onnx/model.onnx")).unwrap();
+    let red = image::open(fixture("frames/1-red.png")).unwrap().to_rgb8();
+    let black = image::open(fixture("frames/3-black.png")).unwrap().to_rgb8();
+
+    let candidates = model.detect(&red).unwrap();
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].label(), "cell phone");
+    assert!(candidates[0].confidence > 0.99);
+    assert_eq!(candidates[0].bbox, [320.0, 320.0, 100.0, 200.0]);
+
+    assert_eq!(model.detect(&black).unwrap(), []);
+}
+
+#[test]
+fn detector_reports_like_detector_py() {
+    let mut detector = OnnxDetector::new(fixture("onnx/model.onnx"), frames(), settings(0.5));
+    let (events, reason) = run(&mut detector);
+    assert_eq!(reason, "frames exhausted");
+
+    assert!(matches!(events[0], DetectionEvent::Heartbeat { frame: 0, .. }));
+    let frames: Vec<(u64, Option<&str>)> = events[1..]
+        .iter()
+        .map(|event| match event {
+            DetectionEvent::Detection { frame, label, .. } => (*frame, Some(label.as_str())),
+            DetectionEvent::Clear { frame, .. } => (*frame, None),
+            DetectionEvent::Heartbeat { .. } => panic!("unexpected heartbeat"),
+        })
+        .collect();
+    // The green frame is a person, which is not a target label.
+    assert_eq!(frames, [(1, Some("cell phone")), (2, None), (3, None)]);
+    assert!(events[1..].iter().all(|event| event.captured_at().is_some()));
+
+    // Runs can be repeated, reusing the loaded model.
+    let (again, _) = run(&mut detector);
+    assert_eq!(again.len(), events.len());
+}
+
+#[test]
+fn threshold_and_frame_skip_apply() {
+    let mut strict = OnnxDetector::new(fixture("onnx/model.onnx"), frames(), settings(1.01));
+    let (events, _) = run(&mut strict);
+    assert!(events.iter().all(|event| !matches!(event, DetectionEvent::Detection { .. })));
+
+    let mut detector = OnnxDetector::new(fixture("onnx/model.onnx"), frames().frame_skip(2), settings(0.5));
+    let (events, _) = run(&mut detector);
+    let analysed: Vec<u64> = events[1..].iter().map(DetectionEvent::frame).collect();
+    assert_eq!(analysed, [2]);
+}
+
+#[test]
+fn a_broken_model_fails_the_run() {
+    let mut detector = OnnxDetector::new(fixture("frames/1-red.png"), frames(), settings(0.5));
+    let err = detector.start().unwrap_err();
+    assert!(err.to_string().contains("cannot load"), "{}", err);
+}
+
+/// A camera whose driver never delivers a frame.
+struct Stuck;
+
+impl FrameSource for Stuck {
+    fn open(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn read(&mut self) -> io::Result<Option<RgbImage>> {
+        loop {
+            thread::park();
+        }
+    }
+}
+
+#[test]
+fn a_stuck_run_is_abandoned() {
+    let mut detector = OnnxDetector::new(fixture("onnx/model.onnx"), Capture::new(Stuck), settings(0.5))
+        .stop_grace(Duration::from_millis(200));
+    detector.start().unwrap();
+    assert!(matches!(detector.next_event(Duration::from_secs(30)), Next::Event(Ok(DetectionEvent::Heartbeat { .. }))));
+    assert!(matches!(detector.next_event(Duration::from_millis(100)), Next::Silent));
+
+    // What the supervisor does about a stall.
+    let started = Instant::now();
+    detector.stop();
+    let reason = detector.wait();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(reason.contains("did not stop"), "{}", reason);
+
+    let err = detector.start().unwrap_err();
+    assert!(err.to_string().contains("still stuck"), "{}", err);
+    assert_eq!(detector.wait(), "detector was not running");
+}
@@ -0,0 +1,375 @@
+use showtime_lib::hysteresis::{Clock, HysteresisConfig};
+use showtime_lib::policy::{Action, PolicyRule, PolicyTable};
+use showtime_lib::protection::{Enforcement, Protection};
+use showtime_lib::protocol::DetectionEvent;
+use showtime_lib::supervisor::FailurePolicy;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+#[derive(Clone)]
+struct FakeClock(Rc<Cell<Instant>>);
+
+impl FakeClock {
+    fn new() -> Self {
+        Self(Rc::new(Cell::new(Instant::now())))
+    }
+
+    fn advance(&self, by: Duration) {
+        self.0.set(self.0.get() + by);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.0.get()
+    }
+}
+
+fn rule(labels: &[&str], min: f32, max: f32, action: Action) -> PolicyRule {
+    PolicyRule {
+        labels: labels.iter().map(|label| label.to_string()).collect(),
+        min_confidence: min,
+        max_confidence: max,
+        action,
+    }
+}
+
+fn table() -> PolicyTable {
+    PolicyTable {
+        default_action: Action::LogOnly,
+        rules: vec![
+            rule(&["cell phone"], 0.7, 1.0, Action::Blackout),
+            rule(&["cell phone"], 0.4, 0.7, Action::Blur),
+            rule(&["camera", "camcorder"], 0.0, 1.0, Action::Warning),
+            rule(&[], 0.9, 1.0, Action::Watermark),
+        ],
+    }
+}
+
+fn detection(frame: u64, label: &str, confidence: f32) -> DetectionEvent {
+    DetectionEvent::Detection {
+        frame,
+        label: label.to_string(),
+        confidence,
+        timestamp: "2024-05-01T10:00:00".to_string(),
+        captured_at: None,
+    }
+}
+
+fn clear(frame: u64) -> DetectionEvent {
+    DetectionEvent::Clear {
+        frame,
+        timestamp: "2024-05-01T10:00:00".to_string(),
+        captured_at: None,
+    }
+}
+
+fn hysteresis() -> HysteresisConfig {
+    HysteresisConfig {
+        window: 3,
+        threshold: 2,
+        min_hold: Duration::from_secs(1),
+        restore_cooldown: Duration::from_secs(1),
+    }
+}
+
+#[test]
+fn confidence_bands_select_actions() {
+    let table = table();
+    assert_eq!(table.evaluate("cell phone", 0.95), Action::Blackout);
+    assert_eq!(table.evaluate("cell phone", 0.5), Action::Blur);
+    assert_eq!(table.evaluate("cell phone", 0.2), Action::LogOnly);
+}
+
+#[test]
+fn most_severe_matching_rule_wins() {
+    // Both the camera rule and the catch-all watermark rule match.
+    assert_eq!(table().evaluate("camera", 0.95), Action::Watermark);
+    assert_eq!(table().evaluate("camera", 0.5), Action::Warning);
+}
+
+#[test]
+fn labels_match_case_insensitively() {
+    assert_eq!(table().evaluate("Cell Phone", 0.8), Action::Blackout);
+}
+
+#[test]
+fn unmatched_labels_use_the_default() {
+    let table = PolicyTable {
+        default_action: Action::PauseMedia,
+        ..table()
+    };
+    assert_eq!(table.evaluate("laptop", 0.5), Action::PauseMedia);
+}
+
+#[test]
+fn default_table_blacks_out_default_labels() {
+    let table = PolicyTable::default();
+    assert_eq!(table.evaluate("cell phone", 0.1), Action::Blackout);
+    assert_eq!(table.evaluate("camcorder", 0.9), Action::Blackout);
+    assert_eq!(table.evaluate("screen recorder", 1.0), Action::Blackout);
+    assert_eq!(table.evaluate("laptop", 0.9), Action::LogOnly);
+}
+
+#[test]
+fn inverted_bands_are_rejected() {
+    let table = PolicyTable {
+        default_action: Action::LogOnly,
+        rules: vec![rule(&["phone"], 0.8, 0.2, Action::Blur)],
+    };
+    assert!(table.validate().is_err());
+    assert!(PolicyTable::default().validate().is_ok());
+}
+
+#[test]
+fn table_parses_from_toml() {
+    let table: PolicyTable = toml::from_str(
+        r#"
+        default_action = "warning"
+
+        [[rules]]
+        labels = ["cell phone"]
+        min_confidence = 0.6
+        action = "pause_media"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(table.default_action, Action::Warning);
+    assert_eq!(table.rules[0].max_confidence, 1.0);
+    assert_eq!(table.evaluate("cell phone", 0.7), Action::PauseMedia);
+}
+
+#[test]
+fn protection_applies_the_peak_action_once_debounced() {
+    let clock = FakeClock::new();
+    let mut protection = Protection::with_clock(table(), hysteresis(), clock.clone());
+
+    let first = protection.observe(&detection(1, "cell phone", 0.95));
+    assert_eq!(first.action, Action::Blackout);
+    assert_eq!(first.enforcement, None);
+
+    let second = protection.observe(&detection(2, "cell phone", 0.5));
+    assert_eq!(second.enforcement, Some(Enforcement::Apply(Action::Blackout)));
+    assert_eq!(protection.active(), Some(Action::Blackout));
+}
+
+#[test]
+fn protection_escalates_but_never_downgrades() {
+    let clock = FakeClock::new();
+    let mut protection = Protection::with_clock(table(), hysteresis(), clock.clone());
+
+    protection.observe(&detection(1, "camera", 0.5));
+    let engaged = protection.observe(&detection(2, "camera", 0.5));
+    assert_eq!(engaged.enforcement, Some(Enforcement::Apply(Action::Warning)));
+
+    assert_eq!(protection.observe(&detection(3, "cell phone", 0.9)).enforcement, None);
+    let escalated = protection.observe(&detection(4, "cell phone", 0.9));
+    assert_eq!(escalated.enforcement, Some(Enforcement::Apply(Action::Blackout)));
+
+    let lesser = protection.observe(&detection(5, "camera", 0.5));
+    assert_eq!(lesser.enforcement, None);
+    assert_eq!(protection.active(), Some(Action::Blackout));
+}
+
+#[test]
+fn a_single_spurious_frame_does_not_escalate() {
+    let clock = FakeClock::new();
+    let mut protection = Protection::with_clock(table(), hysteresis(), clock.clone());
+
+    protection.observe(&detection(1, "camera", 0.5));
+    protection.observe(&detection(2, "camera", 0.5));
+    assert_eq!(protection.active(), Some(Action::Warning));
+
+    // One phone frame between camera frames is noise, even at blackout
+    // confidence and even with several boxes in it.
+    for (frame, label, confidence) in [
+        (3, "camera", 0.5),
+        (4, "cell phone", 0.95),
+        (4, "cell phone", 0.9),
+        (5, "camera", 0.5),
+        (6, "camera", 0.5),
+    ] {
+        let verdict = protection.observe(&detection(frame, label, confidence));
+        assert_eq!(verdict.enforcement, None, "frame {}", frame);
+    }
+    assert_eq!(protection.active(), Some(Action::Warning));
+
+    // Two within the window are not, and the worst of them applies.
+    assert_eq!(protection.observe(&detection(7, "cell phone", 0.5)).enforcement, None);
+    assert_eq!(
+        protection.observe(&detection(8, "cell phone", 0.9)).enforcement,
+        Some(Enforcement::Apply(Action::Blackout))
+    );
+}
+
+#[test]
+fn protection_lifts_after_hold_and_cooldown() {
+    let clock = FakeClock::new();
+    let mut protection = Protection::with_clock(table(), hysteresis(), clock.clone());
+
+    protection.observe(&detection(1, "cell phone", 0.9));
+    protection.observe(&detection(2, "cell phone", 0.9));
+    assert_eq!(protection.active(), Some(Action::Blackout));
+
+    clock.advance(Duration::from_millis(500));
+    assert_eq!(protection.observe(&clear(3)).enforcement, None);
+    assert_eq!(protection.observe(&clear(4)).enforcement, None);
+
+    clock.advance(Duration::from_millis(600));
+    assert_eq!(protection.observe(&clear(5)).enforcement, Some(Enforcement::Lift));
+    assert_eq!(protection.active(), None);
+}
+
+#[test]
+fn log_only_detections_never_enforce() {
+    let clock = FakeClock::new();
+    let mut protection = Protection::with_clock(table(), hysteresis(), clock);
+
+    for frame in 1..=5 {
+        let verdict = protection.observe(&detection(frame, "cell phone", 0.2));
+        assert_eq!(verdict.action, Action::LogOnly);
+        assert_eq!(verdict.enforcement, None);
+    }
+}
+
+#[test]
+fn detector_failure_follows_the_failure_policy() {
+    let clock = FakeClock::new();
+    let mut protection = Protection::with_clock(table(), hysteresis(), clock.clone());
+
+    assert_eq!(
+        protection.detector_down(FailurePolicy::FailClosed),
+        Some(Enforcement::Apply(Action::Blackout))
+    );
+    assert_eq!(protection.detector_down(FailurePolicy::FailClosed), None);
+
+    // Time alone does not lift a forced blackout; a clear frame has to arrive.
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(protection.tick(), None);
+    assert_eq!(protection.observe(&clear(1)).enforcement, Some(Enforcement::Lift));
+
+    protection.observe(&detection(2, "cell phone", 0.9));
+    protection.observe(&detection(3, "cell phone", 0.9));
+    assert_eq!(protection.detector_down(FailurePolicy::FailOpen), Some(Enforcement::Lift));
+}
+
+#[test]
+fn pinned_blackout_outlasts_clear_frames() {
+    let clock = FakeClock::new();
+    let mut protection = Protection::with_clock(table(), hysteresis(), clock.clone());
+
+    assert_eq!(protection.pin(), Some(Enforcement::Apply(Action::Blackout)));
+    protection.observe(&detection(1, "camera", 0.5));
+    protection.observe(&detection(2, "camera", 0.5));
+
+    clock.advance(Duration::from_secs(5));
+    for frame in 3..=6 {
+        assert_eq!(protection.observe(&clear(frame)).enforcement, None);
+    }
+    assert_eq!(protection.active(), Some(Action::Blackout));
+    assert_eq!(protection.restore_in(), None);
+
+    assert_eq!(protection.release(), Some(Enforcement::Lift));
+    assert_eq!(protection.active(), None);
+}
+
+#[test]
+fn manual_blackout_alone_is_not_a_threat() {
+    let clock = FakeClock::new();
+    let mut protection = Protection::with_clock(table(), hysteresis(), clock.clone());
+
+    protection.pin();
+    assert!(!protection.threat_active());
+
+    protection.observe(&detection(1, "cell phone", 0.9));
+    assert!(protection.threat_active());
+
+    protection.detector_down(FailurePolicy::FailClosed);
+    assert!(protection.threat_active());
+    clock.advance(Duration::from_secs(5));
+    protection.observe(&clear(2));
+    assert!(!protection.threat_active());
+}
+
+#[test]
+fn heartbeats_do_not_vote() {
+    let clock = FakeClock::new();
+    let mut protection = Protection::with_clock(table(), hysteresis(), clock.clone());
+    let heartbeat = |frame| DetectionEvent::Heartbeat {
+        frame,
+        fps: 30.0,
+        timestamp: "2024-05-01T10:00:00".to_string(),
+    };
+
+    // A heartbeat is not a clear frame, so it cannot lift a forced blackout.
+    protection.detector_down(FailurePolicy::FailClosed);
+    clock.advance(Duration::from_secs(5));
+    let verdict = protection.observe(&heartbeat(10));
+    assert_eq!(verdict.action, Action::LogOnly);
+    assert_eq!(verdict.enforcement, None);
+
+    protection.observe(&clear(11));
+    protection.observe(&detection(12, "cell phone", 0.9));
+    protection.observe(&heartbeat(12));
+    protection.observe(&heartbeat(13));
+    assert_eq!(protection.active(), None);
+
+    // It still lets a blackout expire on time.
+    protection.observe(&detection(14, "cell phone", 0.9));
+    assert_eq!(protection.active(), Some(Action::Blackout));
+    assert_eq!(protection.observe(&clear(15)).enforcement, None);
+    assert_eq!(protection.observe(&clear(16)).enforcement, None);
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(protection.observe(&heartbeat(17)).enforcement, Some(Enforcement::Lift));
+}
+
+#[test]
+fn recorders_hold_their_response_until_they_exit() {
+    let clock = FakeClock::new();
+    let mut protection = Protection::with_clock(table(), hysteresis(), clock.clone());
+    let obs = ["obs".to_string()];
+
+    // Only the catch-all rule matches recorders in this table.
+    let verdict = protection.recorders(&obs);
+    assert_eq!(verdict.action, Action::Watermark);
+    assert_eq!(verdict.enforcement, Some(Enforcement::Apply(Action::Watermark)));
+    assert!(protection.threat_active());
+    assert_eq!(protection.recorders(&obs).enforcement, None);
+
+    // A phone escalates once voted in, but once it is gone the recorder
+    // still holds.
+    assert_eq!(protection.observe(&detection(1, "cell phone", 0.9)).enforcement, None);
+    assert_eq!(
+        protection.observe(&detection(2, "cell phone", 0.9)).enforcement,
+        Some(Enforcement::Apply(Action::Blackout))
+    );
+    clock.advance(Duration::from_secs(5));
+    for frame in 3..=6 {
+        assert_eq!(protection.observe(&clear(frame)).enforcement, None);
+    }
+    assert_eq!(protection.active(), Some(Action::Blackout));
+
+    assert_eq!(protection.recorders(&[]).enforcement, Some(Enforcement::Lift));
+    assert!(!protection.threat_active());
+}
+
+#[test]
+fn recorders_follow_the_policy_table() {
+    let table = PolicyTable {
+        default_action: Action::LogOnly,
+        rules: vec![rule(&["cell phone"], 0.0, 1.0, Action::Blackout)],
+    };
+    let mut protection = Protection::with_clock(table, hysteresis(), FakeClock::new());
+
+    let verdict = protection.recorders(&["obs".to_string()]);
+    assert_eq!((verdict.action, verdict.enforcement), (Action::LogOnly, None));
+    assert!(!protection.threat_active());
+
+    // Recorders exiting leave a detected threat alone.
+    protection.observe(&detection(1, "cell phone", 0.9));
+    protection.observe(&detection(2, "cell phone", 0.9));
+    assert_eq!(protection.recorders(&[]).enforcement, None);
+    assert_eq!(protection.active(), Some(Action::Blackout));
+}
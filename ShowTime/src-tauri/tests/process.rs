@@ -0,0 +1,46 @@
+//! Stopping real child processes. Unix only, as it relies on `sh` and
+//! signals.
+#![cfg(unix)]
+
+use showtime_lib::detector::ChildProcess;
+use std::io::{BufRead, BufReader};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Runs `script` and waits until it has set up its signal handling, which
+/// it reports by printing a line.
+fn spawn(script: &str) -> ChildProcess {
+    let process = ChildProcess::default();
+    let (stdout, _) = process
+        .spawn(Command::new("sh").args(["-c", script]).stdout(Stdio::piped()))
+        .unwrap();
+    BufReader::new(stdout.unwrap()).read_line(&mut String::new()).unwrap();
+    process
+}
+
+#[test]
+fn terminate_asks_politely_first() {
+    let process = spawn("trap 'exit 3' TERM; echo ready; sleep 30 & wait");
+
+    let status = process.terminate(Duration::from_secs(5)).unwrap().unwrap();
+    assert_eq!(status.code(), Some(3));
+    assert_eq!(process.id(), None);
+}
+
+#[test]
+fn terminate_kills_the_group_after_the_grace_period() {
+    let process = spawn("trap '' TERM; echo ready; sleep 30");
+
+    let started = Instant::now();
+    let status = process.terminate(Duration::from_millis(200)).unwrap().unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGKILL));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn waiting_on_nothing_returns_none() {
+    let process = ChildProcess::default();
+    assert!(process.wait().unwrap().is_none());
+    assert!(process.terminate(Duration::ZERO).unwrap().is_none());
+}
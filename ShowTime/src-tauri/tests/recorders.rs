@@ -0,0 +1,110 @@
+//! Recorder scans over made-up process lists.
+
+use serde_json::json;
+use showtime_lib::config::ShowTimeConfig;
+use showtime_lib::recorders::{default_denylist, ProcessInfo, RecorderRule, RecorderScanner};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+fn scanner(denylist: Vec<RecorderRule>, processes: Vec<ProcessInfo>) -> RecorderScanner {
+    RecorderScanner::new(denylist, move || processes.clone())
+}
+
+#[test]
+fn default_denylist_names_recording_tools() {
+    let processes = vec![
+        ProcessInfo::new("obs"),
+        ProcessInfo::new("OBS64.EXE"),
+        // Linux cuts process names short; the executable still gives it away.
+        ProcessInfo::new("simplescreenrec").cmd(["/usr/bin/simplescreenrecorder", "--start-hidden"]),
+        ProcessInfo {
+            name: "kooha-wrapper".to_string(),
+            exe: Some(PathBuf::from("/app/bin/kooha")),
+            cmd: Vec::new(),
+        },
+        ProcessInfo::new("ffmpeg").cmd(["ffmpeg", "-f", "x11grab", "-i", ":0.0", "out.mp4"]),
+        ProcessInfo::new("firefox"),
+        ProcessInfo::new("obs"),
+    ];
+    assert_eq!(
+        scanner(default_denylist(), processes).scan(),
+        ["ffmpeg", "kooha", "obs", "obs64", "simplescreenrecorder"]
+    );
+}
+
+#[test]
+fn general_purpose_tools_count_only_when_grabbing_the_screen() {
+    let transcode = ProcessInfo::new("ffmpeg").cmd(["ffmpeg", "-i", "in.mkv", "out.mp4"]);
+    let named_after_grab = ProcessInfo::new("ffmpeg").cmd(["/tmp/x11grab"]);
+    let pipeline = ProcessInfo::new("gst-launch-1.0").cmd(["gst-launch-1.0", "ximagesrc", "!", "videoconvert"]);
+
+    assert!(scanner(default_denylist(), vec![transcode, named_after_grab]).scan().is_empty());
+    assert_eq!(scanner(default_denylist(), vec![pipeline]).scan(), ["gst-launch-1.0"]);
+}
+
+#[test]
+fn every_scan_reads_the_process_list_again() {
+    let running = Arc::new(Mutex::new(Vec::new()));
+    let source = running.clone();
+    let mut scanner = RecorderScanner::new(vec![RecorderRule::new("wf-recorder")], move || {
+        source.lock().unwrap().clone()
+    });
+
+    assert!(scanner.scan().is_empty());
+    running.lock().unwrap().push(ProcessInfo::new("wf-recorder"));
+    assert_eq!(scanner.scan(), ["wf-recorder"]);
+    running.lock().unwrap().clear();
+    assert!(scanner.scan().is_empty());
+}
+
+#[test]
+fn denylist_is_configurable() {
+    let plugin = json!({
+        "recorders": {
+            "scan_interval_ms": 500,
+            "denylist": [
+                { "name": "vlc", "args": ["screen://"] },
+                { "name": "mystery-capture" },
+            ],
+        },
+    });
+    let config = ShowTimeConfig::load_with_env(Some(&plugin), None, |_| None).unwrap();
+    assert!(config.recorders.enabled);
+    assert_eq!(config.recorders.scan_interval_ms, 500);
+    assert_eq!(
+        config.recorders.denylist,
+        [
+            RecorderRule::new("vlc").args(["screen://"]),
+            RecorderRule::new("mystery-capture")
+        ]
+    );
+    let processes = vec![
+        ProcessInfo::new("obs"),
+        ProcessInfo::new("vlc").cmd(["vlc", "screen://"]),
+        ProcessInfo::new("mystery-capture"),
+    ];
+    assert_eq!(
+        scanner(config.recorders.denylist, processes).scan(),
+        ["mystery-capture", "vlc"]
+    );
+
+    // Only the deployment can turn the scan off; the environment may tune it.
+    let tuned = ShowTimeConfig::load_with_env(None, None, |var| match var {
+        "SHOWTIME_RECORDER_SCAN" => Some("false".to_string()),
+        "SHOWTIME_RECORDER_SCAN_INTERVAL_MS" => Some("250".to_string()),
+        _ => None,
+    })
+    .unwrap();
+    assert!(tuned.recorders.enabled);
+    assert_eq!(tuned.recorders.scan_interval_ms, 250);
+    let disabled =
+        ShowTimeConfig::load_with_env(Some(&json!({ "recorders": { "enabled": false } })), None, |_| None).unwrap();
+    assert!(!disabled.recorders.enabled);
+
+    for invalid in [
+        json!({ "recorders": { "scan_interval_ms": 0 } }),
+        json!({ "recorders": { "denylist": [{ "name": " " }] } }),
+    ] {
+        assert!(ShowTimeConfig::load_with_env(Some(&invalid), None, |_| None).is_err(), "{}", invalid);
+    }
+}
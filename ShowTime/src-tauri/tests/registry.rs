@@ -0,0 +1,37 @@
+use showtime_lib::registry::{label_matches, ProtectionRegistry};
+
+#[test]
+fn patterns_match_window_labels() {
+    assert!(label_matches("main", "main"));
+    assert!(!label_matches("main", "main-2"));
+    assert!(label_matches("*", ""));
+    assert!(label_matches("*", "anything"));
+    assert!(label_matches("viewer-*", "viewer-1"));
+    assert!(label_matches("viewer-*", "viewer-"));
+    assert!(!label_matches("viewer-*", "main"));
+    assert!(label_matches("*-player", "video-player"));
+    assert!(label_matches("a*b*c", "abc"));
+    assert!(label_matches("a*b*c", "a-b-b-c"));
+    assert!(!label_matches("a*bc*c", "abc"));
+    assert!(!label_matches("a*a", "a"));
+}
+
+#[test]
+fn windows_are_enrolled_once_and_internal_windows_never() {
+    let registry = ProtectionRegistry::new(vec!["main".to_string(), "viewer-*".to_string()]);
+
+    assert!(registry.enroll("main"));
+    assert!(!registry.enroll("main"));
+    assert!(registry.enroll("viewer-2"));
+    assert!(!registry.enroll("settings"));
+    assert_eq!(registry.labels(), ["main", "viewer-2"]);
+
+    registry.remove("main");
+    assert_eq!(registry.labels(), ["viewer-2"]);
+
+    let everything = ProtectionRegistry::new(vec!["*".to_string()]);
+    assert!(everything.matches("settings"));
+    assert!(!everything.matches("blackout-main"));
+    assert!(!everything.matches("watermark-main"));
+    assert!(!everything.matches("denied"));
+}
@@ -0,0 +1,256 @@
+//! Restart behaviour of the supervisor, driven by scripted and recorded
+//! detectors.
+
+use showtime_lib::detector::{Detector, MockDetector, Next, ReplayDetector, Step};
+use showtime_lib::protocol::DetectionEvent;
+use showtime_lib::supervisor::{supervise, Backoff, DetectorStatus, Shutdown, SupervisorConfig};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+fn config() -> SupervisorConfig {
+    SupervisorConfig {
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(4),
+        max_restarts: 10,
+        stable_after: Duration::from_secs(3600),
+        heartbeat_timeout: Duration::from_secs(1),
+        startup_timeout: Duration::from_secs(1),
+        ..SupervisorConfig::default()
+    }
+}
+
+fn clear(frame: u64) -> Step {
+    Step::Event(DetectionEvent::Clear {
+        frame,
+        timestamp: "2024-05-01T10:00:00".to_string(),
+        captured_at: None,
+    })
+}
+
+fn exit(reason: &str) -> Step {
+    Step::Exit(reason.to_string())
+}
+
+/// Supervises `detector` to the end and returns every status it reported
+/// and the frames of every event it forwarded.
+fn run(config: &SupervisorConfig, detector: &mut dyn Detector) -> (Vec<DetectorStatus>, Vec<u64>) {
+    let (mut statuses, mut frames) = (Vec::new(), Vec::new());
+    supervise(
+        config,
+        detector,
+        &Shutdown::default(),
+        |event| frames.push(event.map_or(u64::MAX, |event| event.frame())),
+        |status| statuses.push(status.clone()),
+    );
+    (statuses, frames)
+}
+
+fn retries(statuses: &[DetectorStatus]) -> Vec<u64> {
+    statuses
+        .iter()
+        .filter_map(|status| match status {
+            DetectorStatus::Crashed { retry_in_ms, .. } => Some(*retry_in_ms),
+            _ => None,
+        })
+        .collect()
+}
+
+fn scratch_recording(name: &str, lines: &[String]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("showtime-replay-{}-{}.jsonl", name, std::process::id()));
+    fs::write(&path, lines.join("\n")).unwrap();
+    path
+}
+
+/// A protocol line for a clear frame analysed `ms` milliseconds in.
+fn recorded_clear(frame: u64, ms: u64) -> String {
+    let timestamp = chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+        .unwrap()
+        .and_hms_opt(10, 0, 0)
+        .unwrap()
+        + chrono::Duration::milliseconds(ms as i64);
+    format!(
+        r#"{{"v":2,"type":"clear","frame":{},"timestamp":"{}"}}"#,
+        frame,
+        timestamp.format("%Y-%m-%dT%H:%M:%S%.6f")
+    )
+}
+
+#[test]
+fn backoff_doubles_up_to_the_max_and_resets() {
+    let mut backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(50));
+    let delays: Vec<u128> = (0..5).map(|_| backoff.next_delay().as_millis()).collect();
+    assert_eq!(delays, [10, 20, 40, 50, 50]);
+    backoff.reset();
+    assert_eq!(backoff.next_delay(), Duration::from_millis(10));
+}
+
+#[test]
+fn restarts_back_off_while_the_detector_keeps_crashing() {
+    let mut detector = MockDetector::new(vec![clear(1), exit("boom")])
+        .then(vec![clear(2), exit("boom")])
+        .then(vec![exit("boom")])
+        .then(vec![exit("boom")]);
+    let (statuses, frames) = run(&config(), &mut detector);
+
+    assert_eq!(frames, [1, 2]);
+    assert_eq!(retries(&statuses), [1, 2, 4, 4, 4, 4, 4, 4, 4, 4]);
+    assert_eq!(
+        statuses[..3],
+        [
+            DetectorStatus::Starting { attempt: 1 },
+            DetectorStatus::Running { pid: None },
+            DetectorStatus::Crashed {
+                crashes: 1,
+                reason: "boom".to_string(),
+                retry_in_ms: 1,
+            },
+        ]
+    );
+}
+
+#[test]
+fn a_healthy_run_resets_the_backoff_and_the_crash_count() {
+    let config = SupervisorConfig {
+        max_restarts: 2,
+        stable_after: Duration::from_millis(50),
+        ..config()
+    };
+    let mut detector = MockDetector::new(vec![exit("boom")])
+        .then(vec![exit("boom")])
+        .then(vec![clear(1), Step::Sleep(Duration::from_millis(60)), exit("boom")])
+        .then(vec![exit("boom")]);
+    let (statuses, _) = run(&config, &mut detector);
+
+    // Without the reset the third crash would already give up.
+    assert_eq!(retries(&statuses), [1, 2, 1, 2]);
+    assert_eq!(
+        statuses.last(),
+        Some(&DetectorStatus::GaveUp {
+            crashes: 5,
+            reason: "failed to start detector: mock script exhausted".to_string(),
+        })
+    );
+}
+
+#[test]
+fn gives_up_after_max_restarts_in_a_row() {
+    let config = SupervisorConfig {
+        max_restarts: 3,
+        ..config()
+    };
+    let (statuses, _) = run(&config, &mut MockDetector::default());
+
+    let reason = "failed to start detector: mock script exhausted".to_string();
+    let mut expected = Vec::new();
+    for (attempt, retry_in_ms) in [(1, 1), (2, 2), (3, 4)] {
+        expected.push(DetectorStatus::Starting { attempt });
+        expected.push(DetectorStatus::Crashed {
+            crashes: attempt,
+            reason: reason.clone(),
+            retry_in_ms,
+        });
+    }
+    expected.push(DetectorStatus::Starting { attempt: 4 });
+    expected.push(DetectorStatus::GaveUp { crashes: 4, reason });
+    assert_eq!(statuses, expected);
+}
+
+#[test]
+fn zero_restarts_gives_up_on_the_first_crash() {
+    let config = SupervisorConfig {
+        max_restarts: 0,
+        ..config()
+    };
+    let (statuses, _) = run(&config, &mut MockDetector::new(vec![clear(1), exit("boom")]));
+    assert_eq!(
+        statuses.last(),
+        Some(&DetectorStatus::GaveUp {
+            crashes: 1,
+            reason: "boom".to_string(),
+        })
+    );
+    assert!(retries(&statuses).is_empty());
+}
+
+#[test]
+fn a_silent_run_is_reported_stalled_and_restarted() {
+    let config = SupervisorConfig {
+        max_restarts: 1,
+        heartbeat_timeout: Duration::from_millis(20),
+        ..config()
+    };
+    let mut detector = MockDetector::new(vec![clear(1), Step::Sleep(Duration::from_secs(10))]).then(vec![clear(2)]);
+    let (statuses, frames) = run(&config, &mut detector);
+
+    assert_eq!(frames, [1, 2]);
+    assert!(statuses.contains(&DetectorStatus::Stalled { silent_ms: 20 }));
+    assert!(matches!(
+        &statuses[3],
+        DetectorStatus::Crashed { reason, .. } if reason == "detector stalled: no output for 20 ms (mock run stopped)"
+    ));
+}
+
+#[test]
+fn replay_keeps_the_recorded_spacing_scaled_by_speed() {
+    let path = scratch_recording(
+        "spacing",
+        &[recorded_clear(1, 0), recorded_clear(2, 200), recorded_clear(3, 400)],
+    );
+    let mut detector = ReplayDetector::new(&path).speed(2.0);
+    detector.start().unwrap();
+
+    let started = Instant::now();
+    let mut frames = Vec::new();
+    while let Next::Event(event) = detector.next_event(Duration::from_secs(1)) {
+        frames.push(event.unwrap().frame());
+    }
+    let elapsed = started.elapsed();
+
+    assert_eq!(frames, [1, 2, 3]);
+    assert!(elapsed >= Duration::from_millis(200), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_millis(400), "{:?}", elapsed);
+
+    let mut unpaced = ReplayDetector::new(&path).speed(f64::INFINITY);
+    unpaced.start().unwrap();
+    let started = Instant::now();
+    while let Next::Event(_) = unpaced.next_event(Duration::from_secs(1)) {}
+    assert!(started.elapsed() < Duration::from_millis(100));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn replay_pauses_longer_than_the_timeout_look_silent() {
+    let path = scratch_recording("pause", &[recorded_clear(1, 0), recorded_clear(2, 300)]);
+    let mut detector = ReplayDetector::new(&path);
+    detector.start().unwrap();
+
+    assert!(matches!(detector.next_event(Duration::from_millis(100)), Next::Event(Ok(_))));
+    assert!(matches!(detector.next_event(Duration::from_millis(100)), Next::Silent));
+    // The pending line is still delivered once its time has come.
+    match detector.next_event(Duration::from_secs(1)) {
+        Next::Event(Ok(event)) => assert_eq!(event.frame(), 2),
+        _ => panic!("expected frame 2"),
+    }
+    assert!(matches!(detector.next_event(Duration::from_secs(1)), Next::Ended));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn a_replay_is_supervised_like_any_detector() {
+    let path = scratch_recording(
+        "supervised",
+        &[recorded_clear(1, 0), "not json".to_string(), recorded_clear(2, 10)],
+    );
+    let config = SupervisorConfig {
+        max_restarts: 1,
+        ..config()
+    };
+    let (statuses, frames) = run(&config, &mut ReplayDetector::new(&path).speed(f64::INFINITY));
+
+    // Each pass replays the whole file; rejected lines are forwarded too.
+    assert_eq!(frames, [1, u64::MAX, 2, 1, u64::MAX, 2]);
+    let reason = format!("replay of {} finished", path.display());
+    assert_eq!(statuses.last(), Some(&DetectorStatus::GaveUp { crashes: 2, reason }));
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,91 @@
+//! What the watermark shows. Drawing it takes a window, so that part is
+//! exercised through the script alone.
+
+use chrono::{Local, TimeZone};
+use showtime_lib::config::{ShowTimeConfig, WatermarkConfig};
+use showtime_lib::watermark::{new_session_id, Watermark};
+use std::collections::HashSet;
+use std::time::Duration;
+
+fn watermark(text: &str) -> Watermark {
+    let config = WatermarkConfig {
+        text: text.to_string(),
+        opacity: 0.25,
+        ..WatermarkConfig::default()
+    };
+    Watermark::new(config, "alice", "0123456789ab")
+}
+
+#[test]
+fn text_names_user_session_and_time() {
+    let now = Local.with_ymd_and_hms(2025, 3, 14, 9, 26, 53).unwrap();
+    let watermark = watermark("{user} / {session} / {time}");
+    assert_eq!(watermark.text(now), "alice / 0123456789ab / 2025-03-14 09:26:53");
+}
+
+#[test]
+fn pattern_drifts_within_bounds() {
+    let offsets: Vec<(f64, f64)> = (0..600).map(|secs| Watermark::offset(Duration::from_secs(secs))).collect();
+    assert_eq!(offsets[0], (0.0, 0.0));
+    assert!(offsets.iter().all(|(x, y)| x.abs() <= 120.0 && y.abs() <= 120.0));
+    assert_ne!(offsets[10], offsets[20]);
+    assert!(offsets.iter().any(|&(x, _)| x == 120.0));
+}
+
+#[test]
+fn script_escapes_the_text() {
+    let now = Local::now();
+    let script = watermark(r#"'); alert("{user}"); ('"#).script(now);
+    assert!(script.contains(r#"const text = "'); alert(\"alice\"); ('";"#), "{}", script);
+    assert!(script.contains("opacity: 0.25;"));
+    assert!(script.contains("showtime-watermark"));
+}
+
+#[test]
+fn session_ids_are_short_and_unique() {
+    let ids: HashSet<String> = (0..100).map(|_| new_session_id()).collect();
+    assert_eq!(ids.len(), 100);
+    for id in &ids {
+        assert_eq!(id.len(), 12);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()), "{}", id);
+    }
+}
+
+#[test]
+fn watermark_is_off_by_default() {
+    let config = ShowTimeConfig::load_with_env(None, None, |_| None).unwrap();
+    assert!(!config.watermark.enabled);
+    assert!(!Watermark::start(&config.watermark).is_enabled());
+}
+
+#[test]
+fn watermark_is_configurable() {
+    let config = ShowTimeConfig::load_with_env(None, None, |var| match var {
+        "SHOWTIME_WATERMARK" => Some("true".to_string()),
+        "SHOWTIME_WATERMARK_TEXT" => Some("{session}".to_string()),
+        "SHOWTIME_WATERMARK_OPACITY" => Some("0.3".to_string()),
+        "SHOWTIME_USER_ID" => Some("bob".to_string()),
+        _ => None,
+    })
+    .unwrap();
+    assert!(config.watermark.enabled);
+    assert_eq!(config.watermark.text, "{session}");
+    assert_eq!(config.watermark.opacity, 0.3);
+
+    let watermark = Watermark::start(&config.watermark);
+    assert_eq!(watermark.user(), "bob");
+    assert_eq!(watermark.text(Local::now()), watermark.session());
+
+    for (var, value) in [
+        ("SHOWTIME_WATERMARK_OPACITY", "1.5"),
+        ("SHOWTIME_WATERMARK_TEXT", " "),
+        ("SHOWTIME_WATERMARK", "maybe"),
+    ] {
+        let result = ShowTimeConfig::load_with_env(None, None, |name| match name {
+            _ if name == var => Some(value.to_string()),
+            "SHOWTIME_WATERMARK" => Some("true".to_string()),
+            _ => None,
+        });
+        assert!(result.is_err(), "{}={}", var, value);
+    }
+}